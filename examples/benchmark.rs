@@ -157,7 +157,7 @@ async fn init_database(n: i32) -> anyhow::Result<u128> {
 
     let start = SystemTime::now();
     for i in 0..n {
-        let worker = InsertWorker::boxed(keyspace.clone(), format!("Key {}", i), i, 3);
+        let worker = InsertWorker::boxed(keyspace.clone(), format!("Key {}", i), i, Consistency::One, 3);
         let request = keyspace
             .insert(&format!("Key {}", i), &i)
             .consistency(Consistency::One)
@@ -171,6 +171,7 @@ async fn init_database(n: i32) -> anyhow::Result<u128> {
             sender.clone(),
             keyspace.clone(),
             format!("Key {}", i),
+            Consistency::One,
             2,
             PhantomData::<i32>,
         );
@@ -247,17 +248,14 @@ impl VoidDecoder for MyKeyspace {}
 impl RowsDecoder<String, i32> for MyKeyspace {
     type Row = i32;
     fn try_decode(decoder: Decoder) -> anyhow::Result<Option<i32>> {
-        anyhow::ensure!(decoder.is_rows()?, "Decoded response is not rows!");
-        Self::Row::rows_iter(decoder)?
-            .next()
-            .map(|row| Some(row))
-            .ok_or_else(|| anyhow::anyhow!("Row not found!"))
+        decode_first_row(decoder)
     }
 }
 
-impl<T> ComputeToken<T> for MyKeyspace {
-    fn token(_key: &T) -> i64 {
-        rand::random()
+impl<T: TokenEncoder> ComputeToken<T> for MyKeyspace {
+    fn token(key: &T) -> i64 {
+        // the murmur3 token of the partition key
+        key.get_token()
     }
 }
 
@@ -0,0 +1,258 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This crate implements the derive macros of the scylla-rs `ColumnEncoder` and `ColumnDecoder` traits,
+//! which map a struct to a CQL user defined type (UDT) value, and of the `Row` trait, which maps a struct
+//! to a result-set row.
+//!
+//! A UDT value is the concatenation of its `[bytes]` encoded fields in the order of the type definition,
+//! so the struct fields must be declared in the same order as the UDT fields. A field can be given its
+//! UDT field name with `#[cql(rename = "...")]`, which is reported when the field fails to decode.
+//! The trailing fields which are missing from a value (ie added to the type after the value was written)
+//! are decoded as nulls, so they should be `Option`s.
+//!
+//! A `Row` decodes its fields from the columns in declaration order, the nullable columns should be `Option`s
+//! and the `#[cql(skip)]` fields are not decoded but set to their `Default`. With `#[cql(by_name)]` on the
+//! struct, the column names of the result metadata (if included) are checked against the field names
+//! (or their renames) before decoding.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+/// Derive `ColumnEncoder` for a struct which maps to a UDT.
+#[proc_macro_derive(ColumnEncoder, attributes(cql))]
+pub fn derive_column_encoder(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encoder(input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Derive `ColumnDecoder` for a struct which maps to a UDT.
+#[proc_macro_derive(ColumnDecoder, attributes(cql))]
+pub fn derive_column_decoder(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_decoder(input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Derive `Row` for a struct which maps to a result-set row.
+#[proc_macro_derive(Row, attributes(cql))]
+pub fn derive_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_row(input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// A struct field along with its UDT field or column name.
+struct UdtField {
+    member: syn::Member,
+    ty: syn::Type,
+    name: String,
+    named: bool,
+    skip: bool,
+}
+
+/// The `#[cql(...)]` attributes of a struct or a field.
+#[derive(Default)]
+struct CqlAttrs {
+    rename: Option<String>,
+    skip: bool,
+    by_name: bool,
+}
+
+fn expand_encoder(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = udt_fields(&input)?;
+    reject_row_attrs(&input, &fields)?;
+    let where_clause = input.generics.make_where_clause();
+    for field in fields.iter() {
+        let ty = &field.ty;
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::scylla_rs::cql::ColumnEncoder));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let members = fields.iter().map(|field| &field.member);
+    Ok(quote! {
+        impl #impl_generics ::scylla_rs::cql::ColumnEncoder for #ident #ty_generics #where_clause {
+            fn encode(&self, buffer: &mut Vec<u8>) {
+                // the udt byte_size is computed once all the fields are encoded
+                buffer.extend(&[0, 0, 0, 0]);
+                let current_length = buffer.len();
+                #(
+                    ::scylla_rs::cql::ColumnEncoder::encode(&self.#members, buffer);
+                )*
+                let udt_byte_size = buffer.len() - current_length;
+                buffer[(current_length - 4)..current_length].copy_from_slice(&i32::to_be_bytes(udt_byte_size as i32));
+            }
+        }
+    })
+}
+
+fn expand_decoder(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = udt_fields(&input)?;
+    reject_row_attrs(&input, &fields)?;
+    let where_clause = input.generics.make_where_clause();
+    for field in fields.iter() {
+        let ty = &field.ty;
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::scylla_rs::cql::ColumnDecoder));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let decode_fields = fields.iter().map(|field| {
+        let UdtField { member, ty, name, .. } = field;
+        quote! {
+            #member: ::scylla_rs::cql::decode_udt_field::<#ty>(slice, &mut start, #name)?
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::scylla_rs::cql::ColumnDecoder for #ident #ty_generics #where_clause {
+            fn try_decode(slice: &[u8]) -> ::scylla_rs::anyhow::Result<Self> {
+                let mut start = 0;
+                Ok(Self {
+                    #(#decode_fields,)*
+                })
+            }
+        }
+    })
+}
+
+fn expand_row(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = udt_fields(&input)?;
+    let by_name = cql_attrs(&input.attrs)?.by_name;
+    let where_clause = input.generics.make_where_clause();
+    for field in fields.iter() {
+        let ty = &field.ty;
+        where_clause.predicates.push(if field.skip {
+            parse_quote!(#ty: ::std::default::Default)
+        } else {
+            parse_quote!(#ty: ::scylla_rs::cql::ColumnDecoder)
+        });
+    }
+    let check_column_names = if by_name {
+        if let Some(field) = fields.iter().find(|field| !field.skip && !field.named) {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "A tuple struct field must be renamed to be decoded by name",
+            ));
+        }
+        let names = fields.iter().filter(|field| !field.skip).map(|field| &field.name);
+        quote! {
+            if let Some(column_specs) = ::scylla_rs::cql::ColumnValue::column_specs(rows)? {
+                ::scylla_rs::cql::check_column_names(column_specs, &[#(#names),*])?;
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let decode_fields = fields.iter().map(|field| {
+        let UdtField { member, ty, name, .. } = field;
+        if field.skip {
+            quote! {
+                #member: ::std::default::Default::default()
+            }
+        } else {
+            quote! {
+                #member: ::scylla_rs::cql::ColumnValue::column_value::<#ty>(rows)
+                    .map_err(|e| ::scylla_rs::anyhow::anyhow!("Failed to decode the column {}: {}", #name, e))?
+            }
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::scylla_rs::cql::Row for #ident #ty_generics #where_clause {
+            fn try_decode_row<R: ::scylla_rs::cql::Rows + ::scylla_rs::cql::ColumnValue>(
+                rows: &mut R,
+            ) -> ::scylla_rs::anyhow::Result<Self> {
+                #check_column_names
+                Ok(Self {
+                    #(#decode_fields,)*
+                })
+            }
+        }
+    })
+}
+
+/// Collect the fields of the struct in declaration order.
+fn udt_fields(input: &DeriveInput) -> syn::Result<Vec<UdtField>> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "This trait can only be derived for a struct",
+            ))
+        }
+    };
+    let fields = match fields {
+        Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+        Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+        Fields::Unit => Vec::new(),
+    };
+    fields
+        .into_iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let (member, name) = match &field.ident {
+                Some(ident) => (syn::Member::Named(ident.clone()), ident.to_string()),
+                None => (syn::Member::Unnamed(index.into()), index.to_string()),
+            };
+            let attrs = cql_attrs(&field.attrs)?;
+            if attrs.by_name {
+                return Err(syn::Error::new_spanned(field, "by_name is a struct attribute"));
+            }
+            Ok(UdtField {
+                member,
+                ty: field.ty.clone(),
+                named: field.ident.is_some() || attrs.rename.is_some(),
+                name: attrs.rename.unwrap_or(name),
+                skip: attrs.skip,
+            })
+        })
+        .collect()
+}
+
+/// Reject the attributes which are only supported by the `Row` derive.
+fn reject_row_attrs(input: &DeriveInput, fields: &[UdtField]) -> syn::Result<()> {
+    if cql_attrs(&input.attrs)?.by_name {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "by_name is only supported by the Row derive",
+        ));
+    }
+    if let Some(field) = fields.iter().find(|field| field.skip) {
+        return Err(syn::Error::new_spanned(
+            &field.ty,
+            "A UDT field can't be skipped, as the UDT values are positional",
+        ));
+    }
+    Ok(())
+}
+
+/// Parse the `#[cql(rename = "...", skip, by_name)]` attributes.
+fn cql_attrs(attrs: &[syn::Attribute]) -> syn::Result<CqlAttrs> {
+    let mut cql_attrs = CqlAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("cql")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => {
+                for nested in list.nested.iter() {
+                    match nested {
+                        NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("rename") => {
+                            match &name_value.lit {
+                                Lit::Str(name) => cql_attrs.rename = Some(name.value()),
+                                lit => return Err(syn::Error::new_spanned(lit, "Expected a string")),
+                            }
+                        }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => cql_attrs.skip = true,
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("by_name") => cql_attrs.by_name = true,
+                        nested => return Err(syn::Error::new_spanned(nested, "Unknown cql attribute")),
+                    }
+                }
+            }
+            meta => return Err(syn::Error::new_spanned(meta, "Expected #[cql(...)]")),
+        }
+    }
+    Ok(cql_attrs)
+}
@@ -23,6 +23,7 @@ pub struct BatchRequest<S> {
     inner: Vec<u8>,
     map: HashMap<[u8; 16], Box<dyn AnyStatement<S>>>,
     keyspace: S,
+    idempotent: bool,
 }
 
 /// A marker trait which holds dynamic types for a statement
@@ -123,6 +124,33 @@ impl<S: Keyspace> BatchRequest<S> {
         DecodeResult::batch()
     }
 
+    /// Send a local request to a replica of the local rack and return a type marker
+    pub fn send_local_rack(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeVoid<S>> {
+        send_local_rack(
+            self.token,
+            self.inner,
+            worker,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::batch()
+    }
+
+    /// Send a request to the replica picked by the load balancing policy and return a type marker
+    pub fn send_with_policy(
+        self,
+        worker: Box<dyn Worker>,
+        policy: &dyn LoadBalancingPolicy,
+    ) -> DecodeResult<DecodeVoid<S>> {
+        send_with_policy(
+            self.token,
+            self.inner,
+            worker,
+            policy,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::batch()
+    }
+
     /// Send a global request using the keyspace impl and return a type marker
     pub fn send_global(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeVoid<S>> {
         send_global(
@@ -143,6 +171,12 @@ impl<S: Keyspace> BatchRequest<S> {
     pub fn payload(&self) -> &Vec<u8> {
         &self.inner
     }
+
+    /// Check whether the batch is idempotent, which is the case if all its statements are idempotent and it
+    /// isn't a counter batch
+    pub fn is_idempotent(&self) -> bool {
+        self.idempotent
+    }
 }
 
 /// A batch collector, used to collect statements and build a `BatchRequest`.
@@ -175,6 +209,7 @@ pub struct BatchCollector<S, Type: Copy + Into<u8>, Stage> {
     builder: BatchBuilder<Type, Stage>,
     map: HashMap<[u8; 16], Box<dyn AnyStatement<S>>>,
     keyspace: S,
+    idempotent: bool,
 }
 
 impl<S: Keyspace + Clone> BatchCollector<S, BatchTypeUnset, BatchType> {
@@ -187,6 +222,7 @@ impl<S: Keyspace + Clone> BatchCollector<S, BatchTypeUnset, BatchType> {
             builder: crate::cql::Batch::new(),
             map: HashMap::new(),
             keyspace: keyspace.clone(),
+            idempotent: true,
         }
     }
 
@@ -199,27 +235,31 @@ impl<S: Keyspace + Clone> BatchCollector<S, BatchTypeUnset, BatchType> {
             builder: crate::cql::Batch::with_capacity(capacity),
             map: HashMap::new(),
             keyspace: keyspace.clone(),
+            idempotent: true,
         }
     }
 
     /// Specify the batch type using an enum
     pub fn batch_type<Type: Copy + Into<u8>>(self, batch_type: Type) -> BatchCollector<S, Type, BatchStatementOrId> {
-        Self::step(self.builder.batch_type(batch_type), self.map, self.keyspace)
+        let counter: u8 = BatchTypeCounter.into();
+        let idempotent = batch_type.into() != counter;
+        Self::step(self.builder.batch_type(batch_type), self.map, self.keyspace, idempotent)
     }
 
     /// Specify the batch type as Logged
     pub fn logged(self) -> BatchCollector<S, BatchTypeLogged, BatchStatementOrId> {
-        Self::step(self.builder.logged(), self.map, self.keyspace)
+        Self::step(self.builder.logged(), self.map, self.keyspace, self.idempotent)
     }
 
     /// Specify the batch type as Unlogged
     pub fn unlogged(self) -> BatchCollector<S, BatchTypeUnlogged, BatchStatementOrId> {
-        Self::step(self.builder.unlogged(), self.map, self.keyspace)
+        Self::step(self.builder.unlogged(), self.map, self.keyspace, self.idempotent)
     }
 
     /// Specify the batch type as Counter
     pub fn counter(self) -> BatchCollector<S, BatchTypeCounter, BatchStatementOrId> {
-        Self::step(self.builder.counter(), self.map, self.keyspace)
+        // the counter updates are never idempotent
+        Self::step(self.builder.counter(), self.map, self.keyspace, false)
     }
 }
 
@@ -232,6 +272,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.insert_is_idempotent::<K, V>();
         // Add PreparedId to map if is_prepared
         if S::QueryOrPrepared::is_prepared() {
             let id = self.keyspace.insert_id();
@@ -248,7 +289,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         // bind_values of Insert<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append an unprepared insert query using the statement defined in the `Insert` impl.
@@ -256,12 +297,13 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
     where
         S: Insert<K, V>,
     {
+        let idempotent = self.idempotent && self.keyspace.insert_is_idempotent::<K, V>();
         // this will advnace the builder with QueryStatement
         let builder = <QueryStatement as InsertRecommended<S, K, V>>::make(self.builder, &self.keyspace);
         // bind_values of Insert<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append a prepared insert query using the statement defined in the `Insert` impl.
@@ -271,6 +313,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.insert_is_idempotent::<K, V>();
         // Add PreparedId to map
         let id = self.keyspace.insert_id();
         self.map.insert(
@@ -285,7 +328,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         // bind_values of Insert<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append an update query using the default query type defined in the `UpdateBatch` impl
@@ -296,6 +339,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.update_is_idempotent::<K, V>();
         // Add PreparedId to map if is_prepared
         if S::QueryOrPrepared::is_prepared() {
             let id = self.keyspace.update_id();
@@ -312,7 +356,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         // bind_values of Update<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append an unprepared update query using the statement defined in the `Update` impl.
@@ -320,12 +364,13 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
     where
         S: Update<K, V>,
     {
+        let idempotent = self.idempotent && self.keyspace.update_is_idempotent::<K, V>();
         // this will advnace the builder with QueryStatement
         let builder = <QueryStatement as UpdateRecommended<S, K, V>>::make(self.builder, &self.keyspace);
         // bind_values of Update<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append a prepared update query using the statement defined in the `Update` impl.
@@ -335,6 +380,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.update_is_idempotent::<K, V>();
         // Add PreparedId to map
         let id = self.keyspace.update_id();
         self.map.insert(
@@ -349,7 +395,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         // bind_values of Update<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append a delete query using the default query type defined in the `DeleteBatch` impl
@@ -360,6 +406,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.delete_is_idempotent::<K, V>();
         // Add PreparedId to map if is_prepared
         if S::QueryOrPrepared::is_prepared() {
             let id = self.keyspace.delete_id();
@@ -376,7 +423,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         // bind_values of Delete<K, V>
         let builder = S::bind_values(builder, key);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append an unprepared delete query using the statement defined in the `Delete` impl.
@@ -384,12 +431,13 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
     where
         S: Delete<K, V>,
     {
+        let idempotent = self.idempotent && self.keyspace.delete_is_idempotent::<K, V>();
         // this will advnace the builder with QueryStatement
         let builder = <QueryStatement as DeleteRecommended<S, K, V>>::make(self.builder, &self.keyspace);
         // bind_values of Delete<K, V>
         let builder = S::bind_values(builder, key);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append a prepared delete query using the statement defined in the `Delete` impl.
@@ -399,6 +447,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.delete_is_idempotent::<K, V>();
         // Add PreparedId to map
         let id = self.keyspace.delete_id();
         self.map.insert(
@@ -413,7 +462,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchStatementO
         // bind_values of Delete<K, V>
         let builder = S::bind_values(builder, key);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 }
 
@@ -426,6 +475,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.insert_is_idempotent::<K, V>();
         // Add PreparedId to map if is_prepared
         if S::QueryOrPrepared::is_prepared() {
             let id = self.keyspace.insert_id();
@@ -442,7 +492,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         // bind_values of Insert<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append an unprepared insert query using the statement defined in the `Insert` impl.
@@ -450,12 +500,13 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
     where
         S: Insert<K, V>,
     {
+        let idempotent = self.idempotent && self.keyspace.insert_is_idempotent::<K, V>();
         // this will advnace the builder with QueryStatement
         let builder = <QueryStatement as InsertRecommended<S, K, V>>::make(self.builder, &self.keyspace);
         // bind_values of Insert<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append a prepared insert query using the statement defined in the `Insert` impl.
@@ -465,6 +516,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.insert_is_idempotent::<K, V>();
         // Add PreparedId to map
         let id = self.keyspace.insert_id();
         self.map.insert(
@@ -479,7 +531,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         // bind_values of Insert<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append an update query using the default query type defined in the `UpdateBatch` impl
@@ -490,6 +542,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.update_is_idempotent::<K, V>();
         // Add PreparedId to map if is_prepared
         if S::QueryOrPrepared::is_prepared() {
             let id = self.keyspace.update_id();
@@ -506,7 +559,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         // bind_values of Update<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append an unprepared update query using the statement defined in the `Update` impl.
@@ -514,12 +567,13 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
     where
         S: Update<K, V>,
     {
+        let idempotent = self.idempotent && self.keyspace.update_is_idempotent::<K, V>();
         // this will advnace the builder with QueryStatement
         let builder = <QueryStatement as UpdateRecommended<S, K, V>>::make(self.builder, &self.keyspace);
         // bind_values of Update<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append a prepared update query using the statement defined in the `Update` impl.
@@ -529,6 +583,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.update_is_idempotent::<K, V>();
         // Add PreparedId to map
         let id = self.keyspace.update_id();
         self.map.insert(
@@ -543,7 +598,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         // bind_values of Update<K, V>
         let builder = S::bind_values(builder, key, value);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append a delete query using the default query type defined in the `DeleteBatch` impl
@@ -554,6 +609,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.delete_is_idempotent::<K, V>();
         // Add PreparedId to map if is_prepared
         if S::QueryOrPrepared::is_prepared() {
             let id = self.keyspace.delete_id();
@@ -570,7 +626,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         // bind_values of Delete<K, V>
         let builder = S::bind_values(builder, key);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append an unprepared delete query using the statement defined in the `Delete` impl.
@@ -578,12 +634,13 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
     where
         S: Delete<K, V>,
     {
+        let idempotent = self.idempotent && self.keyspace.delete_is_idempotent::<K, V>();
         // this will advnace the builder with QueryStatement
         let builder = <QueryStatement as DeleteRecommended<S, K, V>>::make(self.builder, &self.keyspace);
         // bind_values of Delete<K, V>
         let builder = S::bind_values(builder, key);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Append a prepared delete query using the statement defined in the `Delete` impl.
@@ -593,6 +650,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         K: 'static + Clone + Send,
         V: 'static + Clone + Send,
     {
+        let idempotent = self.idempotent && self.keyspace.delete_is_idempotent::<K, V>();
         // Add PreparedId to map
         let id = self.keyspace.delete_id();
         self.map.insert(
@@ -607,23 +665,38 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchValues> {
         // bind_values of Delete<K, V>
         let builder = S::bind_values(builder, key);
 
-        Self::step(builder, self.map, self.keyspace)
+        Self::step(builder, self.map, self.keyspace, idempotent)
     }
 
     /// Set the consistency for this batch
     pub fn consistency(self, consistency: Consistency) -> BatchCollector<S, Type, BatchFlags> {
-        Self::step(self.builder.consistency(consistency), self.map, self.keyspace)
+        Self::step(
+            self.builder.consistency(consistency),
+            self.map,
+            self.keyspace,
+            self.idempotent,
+        )
     }
 }
 
 impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchFlags> {
     /// Set the serial consistency for the batch
     pub fn serial_consistency(self, consistency: Consistency) -> BatchCollector<S, Type, BatchTimestamp> {
-        Self::step(self.builder.serial_consistency(consistency), self.map, self.keyspace)
+        Self::step(
+            self.builder.serial_consistency(consistency),
+            self.map,
+            self.keyspace,
+            self.idempotent,
+        )
     }
     /// Set the timestamp for the batch
     pub fn timestamp(self, timestamp: i64) -> BatchCollector<S, Type, BatchBuild> {
-        Self::step(self.builder.timestamp(timestamp), self.map, self.keyspace)
+        Self::step(
+            self.builder.timestamp(timestamp),
+            self.map,
+            self.keyspace,
+            self.idempotent,
+        )
     }
     /// Build the batch request using the current collector
     pub fn build(self) -> anyhow::Result<BatchRequest<S>> {
@@ -632,6 +705,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchFlags> {
             map: self.map,
             inner: self.builder.build()?.0.into(),
             keyspace: self.keyspace,
+            idempotent: self.idempotent,
         })
     }
 }
@@ -639,7 +713,12 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchFlags> {
 impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchTimestamp> {
     /// Set the timestamp for the batch
     pub fn timestamp(self, timestamp: i64) -> BatchCollector<S, Type, BatchBuild> {
-        Self::step(self.builder.timestamp(timestamp), self.map, self.keyspace)
+        Self::step(
+            self.builder.timestamp(timestamp),
+            self.map,
+            self.keyspace,
+            self.idempotent,
+        )
     }
     /// Build the batch request using the current collector
     pub fn build(self) -> anyhow::Result<BatchRequest<S>> {
@@ -648,6 +727,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchTimestamp>
             map: self.map,
             inner: self.builder.build()?.0.into(),
             keyspace: self.keyspace,
+            idempotent: self.idempotent,
         })
     }
 }
@@ -660,6 +740,7 @@ impl<S: Keyspace, Type: Copy + Into<u8>> BatchCollector<S, Type, BatchBuild> {
             map: self.map,
             inner: self.builder.build()?.0.into(),
             keyspace: self.keyspace,
+            idempotent: self.idempotent,
         })
     }
 }
@@ -669,8 +750,14 @@ impl<S: Keyspace, Type: Copy + Into<u8>, Stage> BatchCollector<S, Type, Stage> {
         builder: BatchBuilder<NextType, NextStage>,
         map: HashMap<[u8; 16], Box<dyn AnyStatement<S>>>,
         keyspace: S,
+        idempotent: bool,
     ) -> BatchCollector<S, NextType, NextStage> {
-        BatchCollector { builder, map, keyspace }
+        BatchCollector {
+            builder,
+            map,
+            keyspace,
+            idempotent,
+        }
     }
 }
 
@@ -16,6 +16,7 @@ use super::*;
 ///     cql::{Batch, Consistency, PreparedStatement, Values, VoidDecoder},
 /// };
 /// use std::borrow::Cow;
+/// # use scylla_rs::cql::TokenEncoder;
 /// # #[derive(Default, Clone, Debug)]
 /// # struct MyKeyspace {
 /// #     pub name: Cow<'static, str>,
@@ -35,8 +36,8 @@ use super::*;
 /// #     }
 /// # }
 /// # impl ComputeToken<i32> for MyKeyspace {
-/// #     fn token(_key: &i32) -> i64 {
-/// #         rand::random()
+/// #     fn token(key: &i32) -> i64 {
+/// #         key.get_token()
 /// #     }
 /// # }
 /// # impl VoidDecoder for MyKeyspace {}
@@ -55,7 +56,7 @@ use super::*;
 ///
 /// # let keyspace = MyKeyspace::new();
 /// # let my_key = 1;
-/// let worker = DeleteWorker::boxed(keyspace.clone(), my_key, 3);
+/// let worker = DeleteWorker::boxed(keyspace.clone(), my_key, Consistency::One, 3);
 ///
 /// let request = keyspace // A Scylla keyspace
 ///     .delete::<MyValueType>(&my_key) // Get the Delete Request by specifying the Value type
@@ -77,6 +78,11 @@ pub trait Delete<K, V>: Keyspace + VoidDecoder + ComputeToken<K> {
         md5::compute(self.delete_statement().as_bytes()).into()
     }
 
+    /// Whether the delete can be applied more than once with the same outcome, override it for the
+    /// conditional deletes (`IF EXISTS`).
+    fn is_idempotent(&self) -> bool {
+        true
+    }
     /// Bind the cql values to the builder
     fn bind_values<T: Values>(builder: T, key: &K) -> T::Return;
 }
@@ -117,6 +123,7 @@ impl<S: Keyspace, K> GetDeleteRequest<S, K> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             builder: S::QueryOrPrepared::make(Query::new(), self),
         }
     }
@@ -128,6 +135,7 @@ impl<S: Keyspace, K> GetDeleteRequest<S, K> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             builder: <QueryStatement as DeleteRecommended<S, K, V>>::make(Query::new(), self),
         }
     }
@@ -139,6 +147,7 @@ impl<S: Keyspace, K> GetDeleteRequest<S, K> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             builder: <PreparedStatement as DeleteRecommended<S, K, V>>::make(Query::new(), self),
         }
     }
@@ -147,16 +156,19 @@ pub struct DeleteBuilder<'a, S, K, V, Stage> {
     _marker: PhantomData<(&'a S, &'a K, &'a V)>,
     keyspace: &'a S,
     key: &'a K,
+    token: Option<i64>,
     builder: QueryBuilder<Stage>,
 }
 
 impl<'a, S: Delete<K, V>, K, V> DeleteBuilder<'a, S, K, V, QueryConsistency> {
     pub fn consistency(self, consistency: Consistency) -> DeleteBuilder<'a, S, K, V, QueryValues> {
+        let builder = S::bind_values(self.builder.consistency(consistency), self.key);
         DeleteBuilder {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
-            builder: S::bind_values(self.builder.consistency(consistency), self.key),
+            token: partition_key_token(&builder),
+            builder,
         }
     }
 }
@@ -167,27 +179,30 @@ impl<'a, S: Delete<K, V>, K, V> DeleteBuilder<'a, S, K, V, QueryValues> {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             builder: self.builder.timestamp(timestamp),
         }
     }
     /// Build the DeleteRequest
     pub fn build(self) -> anyhow::Result<DeleteRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
 impl<'a, S: Delete<K, V>, K, V> DeleteBuilder<'a, S, K, V, QueryBuild> {
     /// Build the DeleteRequest
     pub fn build(self) -> anyhow::Result<DeleteRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
-/// Defines two helper methods to specify statement / id
+/// Defines the helper methods to specify statement / id / idempotency
 pub trait GetDeleteStatement<S> {
     /// Specifies the Key and Value type for a delete statement
     fn delete_statement<K, V>(&self) -> Cow<'static, str>
@@ -198,6 +213,11 @@ pub trait GetDeleteStatement<S> {
     fn delete_id<K, V>(&self) -> [u8; 16]
     where
         S: Delete<K, V>;
+
+    /// Specifies the Key and Value type for the idempotency of a statement
+    fn delete_is_idempotent<K, V>(&self) -> bool
+    where
+        S: Delete<K, V>;
 }
 
 impl<S: Keyspace> GetDeleteStatement<S> for S {
@@ -214,6 +234,13 @@ impl<S: Keyspace> GetDeleteStatement<S> for S {
     {
         S::id(self)
     }
+
+    fn delete_is_idempotent<K, V>(&self) -> bool
+    where
+        S: Delete<K, V>,
+    {
+        S::is_idempotent(self)
+    }
 }
 
 /// A request to delete a record which can be sent to the ring
@@ -250,6 +277,10 @@ where
     fn payload(&self) -> &Vec<u8> {
         &self.inner
     }
+
+    fn is_idempotent(&self) -> bool {
+        self.keyspace.delete_is_idempotent::<K, V>()
+    }
 }
 
 impl<S: Delete<K, V>, K, V> DeleteRequest<S, K, V> {
@@ -264,6 +295,33 @@ impl<S: Delete<K, V>, K, V> DeleteRequest<S, K, V> {
         DecodeResult::delete()
     }
 
+    /// Send a local request to a replica of the local rack and return a type marker
+    pub fn send_local_rack(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeVoid<S>> {
+        send_local_rack(
+            self.token,
+            self.inner,
+            worker,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::delete()
+    }
+
+    /// Send a request to the replica picked by the load balancing policy and return a type marker
+    pub fn send_with_policy(
+        self,
+        worker: Box<dyn Worker>,
+        policy: &dyn LoadBalancingPolicy,
+    ) -> DecodeResult<DecodeVoid<S>> {
+        send_with_policy(
+            self.token,
+            self.inner,
+            worker,
+            policy,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::delete()
+    }
+
     /// Send a global request using the keyspace impl and return a type marker
     pub fn send_global(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeVoid<S>> {
         send_global(
@@ -16,6 +16,7 @@ use super::*;
 ///     cql::{Batch, Consistency, PreparedStatement, Values, VoidDecoder},
 /// };
 /// use std::borrow::Cow;
+/// # use scylla_rs::cql::TokenEncoder;
 /// # #[derive(Default, Clone, Debug)]
 /// # struct MyKeyspace {
 /// #     pub name: Cow<'static, str>,
@@ -35,8 +36,8 @@ use super::*;
 /// #     }
 /// # }
 /// # impl ComputeToken<i32> for MyKeyspace {
-/// #     fn token(_key: &i32) -> i64 {
-/// #         rand::random()
+/// #     fn token(key: &i32) -> i64 {
+/// #         key.get_token()
 /// #     }
 /// # }
 /// # impl VoidDecoder for MyKeyspace {}
@@ -55,7 +56,7 @@ use super::*;
 ///
 /// # let keyspace = MyKeyspace::new();
 /// # let (my_key, my_val) = (1, 1.0);
-/// let worker = InsertWorker::boxed(keyspace.clone(), my_key, my_val, 3);
+/// let worker = InsertWorker::boxed(keyspace.clone(), my_key, my_val, Consistency::One, 3);
 ///
 /// let request = keyspace // A Scylla keyspace
 ///     .insert(&my_key, &my_val) // Get the Insert Request
@@ -74,6 +75,11 @@ pub trait Insert<K, V>: Keyspace + VoidDecoder + ComputeToken<K> {
     fn id(&self) -> [u8; 16] {
         md5::compute(self.insert_statement().as_bytes()).into()
     }
+    /// Whether the insert can be applied more than once with the same outcome, which allows it to be retried
+    /// after a timeout. Override it for the lightweight transactions (`IF NOT EXISTS`).
+    fn is_idempotent(&self) -> bool {
+        true
+    }
     /// Bind the cql values to the builder
     fn bind_values<T: Values>(builder: T, key: &K, value: &V) -> T::Return;
 }
@@ -113,6 +119,7 @@ impl<S: Insert<K, V>, K, V> GetInsertRequest<S, K, V> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             value,
             builder: S::QueryOrPrepared::make(Query::new(), self),
         }
@@ -125,6 +132,7 @@ impl<S: Insert<K, V>, K, V> GetInsertRequest<S, K, V> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             value,
             builder: <QueryStatement as InsertRecommended<S, K, V>>::make(Query::new(), self),
         }
@@ -137,6 +145,7 @@ impl<S: Insert<K, V>, K, V> GetInsertRequest<S, K, V> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             value,
             builder: <PreparedStatement as InsertRecommended<S, K, V>>::make(Query::new(), self),
         }
@@ -146,17 +155,20 @@ pub struct InsertBuilder<'a, S, K, V, Stage> {
     _marker: PhantomData<(&'a S, &'a K, &'a V)>,
     keyspace: &'a S,
     key: &'a K,
+    token: Option<i64>,
     value: &'a V,
     builder: QueryBuilder<Stage>,
 }
 impl<'a, S: Insert<K, V>, K, V> InsertBuilder<'a, S, K, V, QueryConsistency> {
     pub fn consistency(self, consistency: Consistency) -> InsertBuilder<'a, S, K, V, QueryValues> {
+        let builder = S::bind_values(self.builder.consistency(consistency), self.key, self.value);
         InsertBuilder {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
             value: self.value,
-            builder: S::bind_values(self.builder.consistency(consistency), self.key, self.value),
+            token: partition_key_token(&builder),
+            builder,
         }
     }
 }
@@ -167,28 +179,31 @@ impl<'a, S: Insert<K, V>, K, V> InsertBuilder<'a, S, K, V, QueryValues> {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             value: self.value,
             builder: self.builder.timestamp(timestamp),
         }
     }
     /// Build the InsertRequest
     pub fn build(self) -> anyhow::Result<InsertRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
 impl<'a, S: Insert<K, V>, K, V> InsertBuilder<'a, S, K, V, QueryBuild> {
     /// Build the InsertRequest
     pub fn build(self) -> anyhow::Result<InsertRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
-/// Defines two helper methods to specify statement / id
+/// Defines the helper methods to specify statement / id / idempotency
 pub trait GetInsertStatement<S> {
     /// Specifies the Key and Value type for an insert statement
     fn insert_statement<K, V>(&self) -> Cow<'static, str>
@@ -199,6 +214,11 @@ pub trait GetInsertStatement<S> {
     fn insert_id<K, V>(&self) -> [u8; 16]
     where
         S: Insert<K, V>;
+
+    /// Specifies the Key and Value type for the idempotency of a statement
+    fn insert_is_idempotent<K, V>(&self) -> bool
+    where
+        S: Insert<K, V>;
 }
 
 impl<S: Keyspace> GetInsertStatement<S> for S {
@@ -215,6 +235,13 @@ impl<S: Keyspace> GetInsertStatement<S> for S {
     {
         S::id(self)
     }
+
+    fn insert_is_idempotent<K, V>(&self) -> bool
+    where
+        S: Insert<K, V>,
+    {
+        S::is_idempotent(self)
+    }
 }
 
 /// A request to insert a record which can be sent to the ring
@@ -251,6 +278,10 @@ where
     fn payload(&self) -> &Vec<u8> {
         &self.inner
     }
+
+    fn is_idempotent(&self) -> bool {
+        self.keyspace.insert_is_idempotent::<K, V>()
+    }
 }
 
 impl<S: Insert<K, V>, K, V> InsertRequest<S, K, V> {
@@ -265,6 +296,33 @@ impl<S: Insert<K, V>, K, V> InsertRequest<S, K, V> {
         DecodeResult::insert()
     }
 
+    /// Send a local request to a replica of the local rack and return a type marker
+    pub fn send_local_rack(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeVoid<S>> {
+        send_local_rack(
+            self.token,
+            self.inner,
+            worker,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::insert()
+    }
+
+    /// Send a request to the replica picked by the load balancing policy and return a type marker
+    pub fn send_with_policy(
+        self,
+        worker: Box<dyn Worker>,
+        policy: &dyn LoadBalancingPolicy,
+    ) -> DecodeResult<DecodeVoid<S>> {
+        send_with_policy(
+            self.token,
+            self.inner,
+            worker,
+            policy,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::insert()
+    }
+
     /// Send a global request using the keyspace impl and return a type marker
     pub fn send_global(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeVoid<S>> {
         send_global(
@@ -14,19 +14,25 @@ pub(crate) mod insert;
 /// keyspace. Structs that impl this trait should also impl
 /// required query and decoder traits.
 pub(crate) mod keyspace;
+/// Provides the partition key indexes cache which routes the
+/// requests of the prepared statements by their bound values
+pub(crate) mod routing;
 /// Provides the `Select` trait which can be implemented to
 /// define select queries for Key / Value pairs and how
 /// they are decoded
 pub(crate) mod select;
+/// Provides the `SystemTraces` keyspace which fetches the
+/// tracing session and events of a traced request
+pub(crate) mod tracing;
 /// Provides the `Update` trait which can be implemented to
 /// define update queries for Key / Value pairs and how
 /// they are decoded
 pub(crate) mod update;
 
-use super::{Worker, WorkerError};
+use super::{worker::SpeculativeExecutionPolicy, Worker, WorkerError};
 use crate::{
     app::{
-        ring::Ring,
+        ring::{LoadBalancingPolicy, Ring},
         stage::{ReporterEvent, ReporterHandle},
     },
     cql::{
@@ -38,8 +44,12 @@ pub use batch::*;
 pub use delete::{Delete, DeleteRequest, GetDeleteRequest, GetDeleteStatement};
 pub use insert::{GetInsertRequest, GetInsertStatement, Insert, InsertRequest};
 pub use keyspace::Keyspace;
+pub(crate) use routing::cache_prepared;
+use routing::partition_key_token;
+pub use routing::{partition_key_indexes, set_partition_key_indexes};
 pub use select::{GetSelectRequest, GetSelectStatement, Select, SelectRequest};
 use std::{borrow::Cow, convert::TryInto, marker::PhantomData, ops::Deref};
+pub use tracing::{fetch_trace_session, SystemTraces, TraceEvent, TraceSession, TracingId};
 pub use update::{GetUpdateRequest, GetUpdateStatement, Update, UpdateRequest};
 
 #[repr(u8)]
@@ -53,8 +63,12 @@ enum RequestType {
 }
 
 /// Defines a computed token for a key type
+///
+/// The requests of the prepared statements are routed by the token of their bound partition key values
+/// once the statements are prepared, so this token is only used by the query statements and by the
+/// prepared statements whose partition key indexes are not cached yet.
 pub trait ComputeToken<K>: Keyspace {
-    /// Compute the token from the provided partition_key by using murmur3 hash function
+    /// Compute the token from the provided partition_key by using murmur3 hash function (see `TokenEncoder`)
     fn token(key: &K) -> i64;
 }
 
@@ -71,6 +85,12 @@ pub trait Request: Send {
 
     /// Get the request payload
     fn payload(&self) -> &Vec<u8>;
+
+    /// Check whether the request can be applied more than once with the same outcome, which is required to
+    /// retry it after an error which doesn't tell whether it was applied.
+    fn is_idempotent(&self) -> bool {
+        false
+    }
 }
 
 /// A marker struct which holds types used for a query
@@ -166,6 +186,37 @@ pub fn send_local(token: i64, payload: Vec<u8>, worker: Box<dyn Worker>, _keyspa
     Ring::send_local_random_replica(token, request);
 }
 
+/// Send a local request to the Ring, preferring the replicas of the local rack
+pub fn send_local_rack(token: i64, payload: Vec<u8>, worker: Box<dyn Worker>, _keyspace: String) {
+    let request = ReporterEvent::Request { worker, payload };
+
+    Ring::send_local_rack(token, request);
+}
+
+/// Send a local request to the Ring, along with its speculative attempts to the other replicas
+pub fn send_local_speculative(
+    token: i64,
+    payload: Vec<u8>,
+    worker: Box<dyn Worker>,
+    policy: &'static dyn SpeculativeExecutionPolicy,
+    _keyspace: String,
+) {
+    crate::app::worker::speculate_local(token, payload, worker, policy);
+}
+
+/// Send a request to the Ring, the replica is picked by the load balancing policy
+pub fn send_with_policy(
+    token: i64,
+    payload: Vec<u8>,
+    worker: Box<dyn Worker>,
+    policy: &dyn LoadBalancingPolicy,
+    _keyspace: String,
+) {
+    let request = ReporterEvent::Request { worker, payload };
+
+    Ring::send_with_policy(token, request, policy);
+}
+
 /// Send a global request to the Ring
 pub fn send_global(token: i64, payload: Vec<u8>, worker: Box<dyn Worker>, _keyspace: String) {
     let request = ReporterEvent::Request { worker, payload };
@@ -184,7 +235,7 @@ impl<T> Deref for DecodeResult<T> {
 #[doc(hidden)]
 pub mod tests {
 
-    use crate::app::worker::InsertWorker;
+    use crate::{app::worker::InsertWorker, cql::TokenEncoder};
 
     use super::*;
 
@@ -229,8 +280,8 @@ pub mod tests {
     }
 
     impl ComputeToken<u32> for MyKeyspace {
-        fn token(_key: &u32) -> i64 {
-            rand::random()
+        fn token(key: &u32) -> i64 {
+            key.get_token()
         }
     }
     impl Insert<u32, f32> for MyKeyspace {
@@ -432,7 +483,7 @@ pub mod tests {
     fn test_insert() {
         let keyspace = MyKeyspace { name: "mainnet".into() };
         let req = keyspace.insert(&3, &8.0).consistency(Consistency::One).build().unwrap();
-        let worker = InsertWorker::boxed(keyspace, 3, 8.0, 0);
+        let worker = InsertWorker::boxed(keyspace, 3, 8.0, Consistency::One, 0);
 
         let _res = req.send_local(worker);
     }
@@ -484,4 +535,27 @@ pub mod tests {
         let worker = BatchWorker { request: req.clone() };
         let _res = req.clone().send_local(Box::new(worker));
     }
+
+    #[test]
+    fn test_batch_idempotency() {
+        let keyspace = MyKeyspace::new();
+        let batch = |keyspace: &MyKeyspace| keyspace.batch().logged().insert(&3, &9.0).delete::<_, f32>(&3);
+        let req = batch(&keyspace).consistency(Consistency::One).build().unwrap();
+        assert!(req.is_idempotent());
+        // the updates aren't idempotent by default
+        let req = batch(&keyspace)
+            .update(&3, &8.0)
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert!(!req.is_idempotent());
+        let req = keyspace
+            .batch()
+            .counter()
+            .insert(&3, &9.0)
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert!(!req.is_idempotent());
+    }
 }
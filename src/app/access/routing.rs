@@ -0,0 +1,53 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cql::{is_prepared_result, set_result_metadata_id, Decoder, Prepared, QueryBuilder, QueryValues};
+use log::warn;
+use std::{collections::HashMap, convert::TryFrom, sync::RwLock};
+
+/// The partition key indexes of the prepared statements by their ids, which are cached from the
+/// PREPARED results.
+static PARTITION_KEY_INDEXES: RwLock<Option<HashMap<[u8; 16], Vec<u16>>>> = RwLock::new(None);
+
+/// Cache the partition key indexes of the prepared statement, which is done by the reporters once
+/// the statement is prepared.
+pub fn set_partition_key_indexes(id: [u8; 16], pk_indexes: Vec<u16>) {
+    PARTITION_KEY_INDEXES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(id, pk_indexes);
+}
+
+/// Get the cached partition key indexes of the prepared statement.
+pub fn partition_key_indexes(id: &[u8; 16]) -> Option<Vec<u16>> {
+    PARTITION_KEY_INDEXES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()?
+        .get(id)
+        .cloned()
+}
+
+/// Cache the partition key indexes and the result metadata id (v5) of the response, if it's a PREPARED result.
+pub(crate) fn cache_prepared(payload: &[u8]) {
+    if is_prepared_result(payload) {
+        match Decoder::try_from(payload.to_vec()).and_then(|decoder| Prepared::new(&decoder)) {
+            Ok(prepared) => {
+                if let Some(result_metadata_id) = prepared.result_metadata_id {
+                    set_result_metadata_id(prepared.id, result_metadata_id);
+                }
+                set_partition_key_indexes(prepared.id, prepared.pk_indexes)
+            }
+            Err(e) => warn!("Failed to decode the prepared result: {}", e),
+        }
+    }
+}
+
+/// Compute the token of the bound partition key values of a prepared statement, if its partition key
+/// indexes are cached.
+pub(crate) fn partition_key_token(builder: &QueryBuilder<QueryValues>) -> Option<i64> {
+    let id = builder.prepared_id()?;
+    let guard = PARTITION_KEY_INDEXES.read().unwrap_or_else(|e| e.into_inner());
+    builder.partition_key_token(guard.as_ref()?.get(&id)?)
+}
@@ -17,6 +17,7 @@ use crate::cql::{QueryPagingState, QuerySerialConsistency};
 ///     cql::{Batch, Consistency, PreparedStatement, RowsDecoder, Values},
 /// };
 /// use std::borrow::Cow;
+/// # use scylla_rs::cql::TokenEncoder;
 /// # use scylla_rs::cql::Decoder;
 /// # #[derive(Default, Clone, Debug)]
 /// # struct MyKeyspace {
@@ -37,8 +38,8 @@ use crate::cql::{QueryPagingState, QuerySerialConsistency};
 /// #     }
 /// # }
 /// # impl ComputeToken<i32> for MyKeyspace {
-/// #     fn token(_key: &i32) -> i64 {
-/// #         rand::random()
+/// #     fn token(key: &i32) -> i64 {
+/// #         key.get_token()
 /// #     }
 /// # }
 /// # type MyKeyType = i32;
@@ -64,7 +65,14 @@ use crate::cql::{QueryPagingState, QuerySerialConsistency};
 /// # let my_key = 1;
 /// let (sender, receiver) = tokio::sync::mpsc::unbounded_channel::<Result<Option<MyValueType>, WorkerError>>();
 /// # use std::marker::PhantomData;
-/// let worker = ValueWorker::boxed(sender, keyspace.clone(), my_key, 3, PhantomData::<MyValueType>);
+/// let worker = ValueWorker::boxed(
+///     sender,
+///     keyspace.clone(),
+///     my_key,
+///     Consistency::One,
+///     3,
+///     PhantomData::<MyValueType>,
+/// );
 ///
 /// let request = keyspace // A Scylla keyspace
 ///     .select::<MyValueType>(&my_key) // Get the Select Request by specifying the value type
@@ -85,6 +93,10 @@ pub trait Select<K, V>: Keyspace + RowsDecoder<K, V> + ComputeToken<K> {
     fn id(&self) -> [u8; 16] {
         md5::compute(self.select_statement().as_bytes()).into()
     }
+    /// Whether the select can be retried safely, which is the case unless it has side effects.
+    fn is_idempotent(&self) -> bool {
+        true
+    }
     /// Bind the cql values to the builder
     fn bind_values<T: Values>(builder: T, key: &K) -> T::Return;
 }
@@ -125,6 +137,7 @@ impl<S: Keyspace, K> GetSelectRequest<S, K> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             builder: S::QueryOrPrepared::make(Query::new(), self),
         }
     }
@@ -136,6 +149,7 @@ impl<S: Keyspace, K> GetSelectRequest<S, K> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             builder: <QueryStatement as SelectRecommended<S, K, V>>::make(Query::new(), self),
         }
     }
@@ -147,6 +161,7 @@ impl<S: Keyspace, K> GetSelectRequest<S, K> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             builder: <PreparedStatement as SelectRecommended<S, K, V>>::make(Query::new(), self),
         }
     }
@@ -156,16 +171,19 @@ pub struct SelectBuilder<'a, S, K, V, Stage> {
     _marker: PhantomData<(&'a S, &'a K, &'a V)>,
     keyspace: &'a S,
     key: &'a K,
+    token: Option<i64>,
     builder: QueryBuilder<Stage>,
 }
 
 impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QueryConsistency> {
     pub fn consistency(self, consistency: Consistency) -> SelectBuilder<'a, S, K, V, QueryValues> {
+        let builder = S::bind_values(self.builder.consistency(consistency), self.key);
         SelectBuilder {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
-            builder: S::bind_values(self.builder.consistency(consistency), self.key),
+            token: partition_key_token(&builder),
+            builder,
         }
     }
 }
@@ -175,6 +193,7 @@ impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QueryValues> {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             builder: self.builder.page_size(page_size),
         }
     }
@@ -184,6 +203,7 @@ impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QueryValues> {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             builder: self.builder.paging_state(paging_state),
         }
     }
@@ -192,23 +212,26 @@ impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QueryValues> {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             builder: self.builder.timestamp(timestamp),
         }
     }
     /// Build the SelectRequest
     pub fn build(self) -> anyhow::Result<SelectRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
 impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QueryBuild> {
     /// Build the InsertRequest
     pub fn build(self) -> anyhow::Result<SelectRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
@@ -219,6 +242,7 @@ impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QueryPagingState> {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             builder: self.builder.paging_state(paging_state),
         }
     }
@@ -229,14 +253,16 @@ impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QueryPagingState> {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             builder: self.builder.timestamp(timestamp),
         }
     }
 
     pub fn build(self) -> anyhow::Result<SelectRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QuerySerialConsistency> {
@@ -246,18 +272,20 @@ impl<'a, S: Select<K, V>, K, V> SelectBuilder<'a, S, K, V, QuerySerialConsistenc
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             builder: self.builder.timestamp(timestamp),
         }
     }
 
     pub fn build(self) -> anyhow::Result<SelectRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
-/// Defines two helper methods to specify statement / id
+/// Defines the helper methods to specify statement / id / idempotency
 pub trait GetSelectStatement<S> {
     /// Specifies the Key and Value type for a select statement
     fn select_statement<K, V>(&self) -> Cow<'static, str>
@@ -268,6 +296,11 @@ pub trait GetSelectStatement<S> {
     fn select_id<K, V>(&self) -> [u8; 16]
     where
         S: Select<K, V>;
+
+    /// Specifies the Key and Value type for the idempotency of a statement
+    fn select_is_idempotent<K, V>(&self) -> bool
+    where
+        S: Select<K, V>;
 }
 
 impl<S: Keyspace> GetSelectStatement<S> for S {
@@ -284,6 +317,13 @@ impl<S: Keyspace> GetSelectStatement<S> for S {
     {
         S::id(self)
     }
+
+    fn select_is_idempotent<K, V>(&self) -> bool
+    where
+        S: Select<K, V>,
+    {
+        S::is_idempotent(self)
+    }
 }
 
 /// A request to select a record which can be sent to the ring
@@ -320,6 +360,10 @@ where
     fn payload(&self) -> &Vec<u8> {
         &self.inner
     }
+
+    fn is_idempotent(&self) -> bool {
+        self.keyspace.select_is_idempotent::<K, V>()
+    }
 }
 
 impl<S: Select<K, V>, K, V> SelectRequest<S, K, V> {
@@ -339,6 +383,55 @@ impl<S: Select<K, V>, K, V> SelectRequest<S, K, V> {
         DecodeResult::select()
     }
 
+    /// Send a local request to a replica of the local rack and return a type marker
+    pub fn send_local_rack(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeRows<S, K, V>> {
+        send_local_rack(
+            self.token,
+            self.inner,
+            worker,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::select()
+    }
+
+    /// Send a local request using the keyspace impl and return a type marker, the idempotent requests are also
+    /// sent to the other replicas of the token as the speculative execution policy decides, and the first
+    /// response is passed to the worker
+    pub fn send_local_speculative(
+        self,
+        worker: Box<dyn Worker>,
+        policy: &'static dyn SpeculativeExecutionPolicy,
+    ) -> DecodeResult<DecodeRows<S, K, V>> {
+        if self.keyspace.select_is_idempotent::<K, V>() {
+            send_local_speculative(
+                self.token,
+                self.inner,
+                worker,
+                policy,
+                self.keyspace.name().clone().into_owned(),
+            );
+            DecodeResult::select()
+        } else {
+            self.send_local(worker)
+        }
+    }
+
+    /// Send a request to the replica picked by the load balancing policy and return a type marker
+    pub fn send_with_policy(
+        self,
+        worker: Box<dyn Worker>,
+        policy: &dyn LoadBalancingPolicy,
+    ) -> DecodeResult<DecodeRows<S, K, V>> {
+        send_with_policy(
+            self.token,
+            self.inner,
+            worker,
+            policy,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::select()
+    }
+
     /// Send a global request using the keyspace impl and return a type marker
     pub fn send_global(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeRows<S, K, V>> {
         send_global(
@@ -0,0 +1,337 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::{
+    app::worker::ValueWorker,
+    cql::{ColumnValue, Frame, Row, Rows, TokenEncoder},
+};
+use anyhow::{anyhow, bail};
+use std::{collections::HashMap, io::Cursor, net::IpAddr};
+use tokio::sync::mpsc::unbounded_channel;
+
+/// The number of times the tracing selects are retried.
+const TRACING_RETRIES: usize = 3;
+
+/// The tracing id of a traced request, which is returned in the response header
+/// (see `HeaderFlags::tracing_id`) and is the session id of its tracing session.
+pub type TracingId = [u8; 16];
+
+/// The `system_traces` keyspace, which holds the tracing sessions and their events.
+///
+/// ## Example
+/// ```no_run
+/// use scylla_rs::{app::access::fetch_trace_session, cql::Frame};
+/// # async fn trace(decoder: scylla_rs::cql::Decoder) -> anyhow::Result<()> {
+/// // the response of a request built with `.tracing()`
+/// if let Some(tracing_id) = decoder.flags().tracing_id() {
+///     if let Some(session) = fetch_trace_session(*tracing_id).await? {
+///         for event in session.events {
+///             println!("{} {:?} {}", event.source_elapsed, event.source, event.activity);
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SystemTraces {
+    name: Cow<'static, str>,
+}
+
+impl Default for SystemTraces {
+    fn default() -> Self {
+        Self {
+            name: "system_traces".into(),
+        }
+    }
+}
+
+impl Keyspace for SystemTraces {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl ComputeToken<TracingId> for SystemTraces {
+    fn token(key: &TracingId) -> i64 {
+        (&key[..]).get_token()
+    }
+}
+
+/// A tracing session of a traced request, as recorded by its coordinator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceSession {
+    /// The tracing id of the session
+    pub tracing_id: TracingId,
+    /// The address of the client which sent the request
+    pub client: Option<IpAddr>,
+    /// The command of the request, ie `QUERY`
+    pub command: Option<String>,
+    /// The address of the coordinator
+    pub coordinator: Option<IpAddr>,
+    /// The duration of the request in microseconds, which is only set once the session is complete
+    pub duration: Option<i32>,
+    /// The request parameters, ie the consistency level and the query
+    pub parameters: HashMap<String, String>,
+    /// The request description
+    pub request: Option<String>,
+    /// The start time of the request in milliseconds since the unix epoch
+    pub started_at: Option<i64>,
+    /// The events of the session
+    pub events: Vec<TraceEvent>,
+}
+
+/// A tracing event of a tracing session.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEvent {
+    /// The activity of the event
+    pub activity: String,
+    /// The address of the node on which the event took place
+    pub source: Option<IpAddr>,
+    /// The elapsed time since the request started on the source node, in microseconds
+    pub source_elapsed: i32,
+    /// The thread which recorded the event
+    pub thread: String,
+}
+
+impl Row for TraceSession {
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self> {
+        let session_id = rows.column_value::<Cursor<Vec<u8>>>()?.into_inner();
+        let tracing_id = session_id
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("Invalid tracing session id!"))?;
+        Ok(Self {
+            tracing_id,
+            client: rows.column_value()?,
+            command: rows.column_value()?,
+            coordinator: rows.column_value()?,
+            duration: rows.column_value()?,
+            parameters: rows
+                .column_value::<Option<HashMap<String, String>>>()?
+                .unwrap_or_default(),
+            request: rows.column_value()?,
+            started_at: rows.column_value()?,
+            events: Vec::new(),
+        })
+    }
+}
+
+impl Row for TraceEvent {
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self> {
+        Ok(Self {
+            activity: rows.column_value::<Option<String>>()?.unwrap_or_default(),
+            source: rows.column_value()?,
+            source_elapsed: rows.column_value::<Option<i32>>()?.unwrap_or_default(),
+            thread: rows.column_value::<Option<String>>()?.unwrap_or_default(),
+        })
+    }
+}
+
+impl Select<TracingId, TraceSession> for SystemTraces {
+    type QueryOrPrepared = QueryStatement;
+    fn statement(&self) -> Cow<'static, str> {
+        format!(
+            "SELECT session_id, client, command, coordinator, duration, parameters, request, started_at FROM {}.sessions WHERE session_id = ?",
+            self.name()
+        )
+        .into()
+    }
+    fn bind_values<T: Values>(builder: T, key: &TracingId) -> T::Return {
+        builder.value(&&key[..])
+    }
+}
+
+impl Select<TracingId, Vec<TraceEvent>> for SystemTraces {
+    type QueryOrPrepared = QueryStatement;
+    fn statement(&self) -> Cow<'static, str> {
+        format!(
+            "SELECT activity, source, source_elapsed, thread FROM {}.events WHERE session_id = ?",
+            self.name()
+        )
+        .into()
+    }
+    fn bind_values<T: Values>(builder: T, key: &TracingId) -> T::Return {
+        builder.value(&&key[..])
+    }
+}
+
+impl RowsDecoder<TracingId, TraceSession> for SystemTraces {
+    type Row = TraceSession;
+    fn try_decode(decoder: Decoder) -> anyhow::Result<Option<TraceSession>> {
+        if decoder.is_rows()? {
+            Ok(Self::Row::rows_iter(decoder)?.next())
+        } else {
+            bail!("Tracing session select didn't return rows due to CqlError");
+        }
+    }
+}
+
+impl RowsDecoder<TracingId, Vec<TraceEvent>> for SystemTraces {
+    type Row = TraceEvent;
+    fn try_decode(decoder: Decoder) -> anyhow::Result<Option<Vec<TraceEvent>>> {
+        if decoder.is_rows()? {
+            Ok(Some(Self::Row::rows_iter(decoder)?.collect()))
+        } else {
+            bail!("Tracing events select didn't return rows due to CqlError");
+        }
+    }
+}
+
+/// Fetch the tracing session of a traced request along with its events through the ring.
+///
+/// Returns `None` if the session is not complete yet, as the coordinator writes the traces
+/// asynchronously once the request is done, so the caller may retry a bit later.
+pub async fn fetch_trace_session(tracing_id: TracingId) -> anyhow::Result<Option<TraceSession>> {
+    let keyspace = SystemTraces::default();
+    match select::<TraceSession>(&keyspace, tracing_id).await? {
+        Some(mut session) if session.duration.is_some() => {
+            session.events = select::<Vec<TraceEvent>>(&keyspace, tracing_id)
+                .await?
+                .unwrap_or_default();
+            Ok(Some(session))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Select the value of the tracing id and wait for the response.
+async fn select<V>(keyspace: &SystemTraces, tracing_id: TracingId) -> anyhow::Result<Option<V>>
+where
+    SystemTraces: Select<TracingId, V>,
+    V: 'static + Send + Clone,
+{
+    let (sender, mut receiver) = unbounded_channel::<Result<Option<V>, WorkerError>>();
+    let worker = ValueWorker::boxed(
+        sender,
+        keyspace.clone(),
+        tracing_id,
+        Consistency::One,
+        TRACING_RETRIES,
+        PhantomData::<V>,
+    );
+    keyspace
+        .select::<V>(&tracing_id)
+        .consistency(Consistency::One)
+        .build()?
+        .send_global(worker);
+    receiver
+        .recv()
+        .await
+        .ok_or_else(|| anyhow!("Tracing select worker dropped without a response"))?
+        .map_err(|e| anyhow!(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        convert::TryFrom,
+        net::{Ipv4Addr, Ipv6Addr},
+    };
+
+    fn bytes(value: &[u8]) -> Vec<u8> {
+        let mut bytes = (value.len() as i32).to_be_bytes().to_vec();
+        bytes.extend(value);
+        bytes
+    }
+
+    /// A ROWS result without metadata of the given columns count and rows.
+    fn rows_decoder(columns_count: i32, rows: &[Vec<Option<Vec<u8>>>]) -> Decoder {
+        let mut body: Vec<u8> = Vec::new();
+        // kind, flags (no_metadata), columns_count, rows_count
+        for int in [2, 4, columns_count, rows.len() as i32].iter() {
+            body.extend(&int.to_be_bytes());
+        }
+        for column in rows.iter().flatten() {
+            match column {
+                Some(value) => body.extend(bytes(value)),
+                None => body.extend(&(-1i32).to_be_bytes()),
+            }
+        }
+        let mut frame = vec![0x84, 0, 0, 0, 0x08];
+        frame.extend(&(body.len() as i32).to_be_bytes());
+        frame.extend(body);
+        Decoder::try_from(frame).unwrap()
+    }
+
+    #[test]
+    fn decode_trace_session() {
+        let mut parameters = 2i32.to_be_bytes().to_vec();
+        for string in ["consistency_level", "ONE", "query", "SELECT * FROM ks.table"].iter() {
+            parameters.extend(bytes(string.as_bytes()));
+        }
+        let coordinator = Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1);
+        let row = vec![
+            Some(vec![7; 16]),
+            Some(vec![127, 0, 0, 1]),
+            Some(b"QUERY".to_vec()),
+            Some(coordinator.octets().to_vec()),
+            Some(1234i32.to_be_bytes().to_vec()),
+            Some(parameters),
+            Some(b"Execute CQL3 query".to_vec()),
+            Some(1_600_000_000_123i64.to_be_bytes().to_vec()),
+        ];
+        let session = <SystemTraces as RowsDecoder<TracingId, TraceSession>>::try_decode(rows_decoder(8, &[row]))
+            .unwrap()
+            .unwrap();
+        let mut expected = HashMap::new();
+        expected.insert("consistency_level".to_string(), "ONE".to_string());
+        expected.insert("query".to_string(), "SELECT * FROM ks.table".to_string());
+        assert_eq!(
+            session,
+            TraceSession {
+                tracing_id: [7; 16],
+                client: Some(Ipv4Addr::LOCALHOST.into()),
+                command: Some("QUERY".to_string()),
+                coordinator: Some(coordinator.into()),
+                duration: Some(1234),
+                parameters: expected,
+                request: Some("Execute CQL3 query".to_string()),
+                started_at: Some(1_600_000_000_123),
+                events: Vec::new(),
+            }
+        );
+        // an incomplete session doesn't have a duration yet
+        let row = vec![Some(vec![7; 16]), None, None, None, None, None, None, None];
+        let session = <SystemTraces as RowsDecoder<TracingId, TraceSession>>::try_decode(rows_decoder(8, &[row]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.duration, None);
+        assert!(session.parameters.is_empty());
+    }
+
+    #[test]
+    fn decode_trace_events() {
+        let rows = [
+            vec![
+                Some(b"Parsing a statement".to_vec()),
+                Some(vec![10, 0, 0, 2]),
+                Some(42i32.to_be_bytes().to_vec()),
+                Some(b"shard 0".to_vec()),
+            ],
+            vec![Some(b"Done processing".to_vec()), None, None, None],
+        ];
+        let events = <SystemTraces as RowsDecoder<TracingId, Vec<TraceEvent>>>::try_decode(rows_decoder(4, &rows))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                TraceEvent {
+                    activity: "Parsing a statement".to_string(),
+                    source: Some(Ipv4Addr::new(10, 0, 0, 2).into()),
+                    source_elapsed: 42,
+                    thread: "shard 0".to_string(),
+                },
+                TraceEvent {
+                    activity: "Done processing".to_string(),
+                    source: None,
+                    source_elapsed: 0,
+                    thread: String::new(),
+                },
+            ]
+        );
+    }
+}
@@ -13,6 +13,7 @@ use super::*;
 ///     cql::{Batch, Consistency, PreparedStatement, Values, VoidDecoder},
 /// };
 /// use std::borrow::Cow;
+/// # use scylla_rs::cql::TokenEncoder;
 /// # #[derive(Default, Clone, Debug)]
 /// # struct MyKeyspace {
 /// #     pub name: Cow<'static, str>,
@@ -32,8 +33,8 @@ use super::*;
 /// #     }
 /// # }
 /// # impl ComputeToken<i32> for MyKeyspace {
-/// #     fn token(_key: &i32) -> i64 {
-/// #         rand::random()
+/// #     fn token(key: &i32) -> i64 {
+/// #         key.get_token()
 /// #     }
 /// # }
 /// # impl VoidDecoder for MyKeyspace {}
@@ -70,6 +71,11 @@ pub trait Update<K, V>: Keyspace + VoidDecoder + ComputeToken<K> {
     fn id(&self) -> [u8; 16] {
         md5::compute(self.update_statement().as_bytes()).into()
     }
+    /// Whether the update can be applied more than once with the same outcome. The updates aren't idempotent
+    /// by default, as a retried counter update or list append would be applied twice.
+    fn is_idempotent(&self) -> bool {
+        false
+    }
     /// Bind the cql values to the builder
     fn bind_values<T: Values>(builder: T, key: &K, value: &V) -> T::Return;
 }
@@ -109,6 +115,7 @@ impl<S: Update<K, V>, K, V> GetUpdateRequest<S, K, V> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             value,
             builder: S::QueryOrPrepared::make(Query::new(), self),
         }
@@ -121,6 +128,7 @@ impl<S: Update<K, V>, K, V> GetUpdateRequest<S, K, V> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             value,
             builder: <QueryStatement as UpdateRecommended<S, K, V>>::make(Query::new(), self),
         }
@@ -133,6 +141,7 @@ impl<S: Update<K, V>, K, V> GetUpdateRequest<S, K, V> for S {
             _marker: PhantomData,
             keyspace: self,
             key,
+            token: None,
             value,
             builder: <PreparedStatement as UpdateRecommended<S, K, V>>::make(Query::new(), self),
         }
@@ -142,17 +151,20 @@ pub struct UpdateBuilder<'a, S, K, V, Stage> {
     _marker: PhantomData<(&'a S, &'a K, &'a V)>,
     keyspace: &'a S,
     key: &'a K,
+    token: Option<i64>,
     value: &'a V,
     builder: QueryBuilder<Stage>,
 }
 impl<'a, S: Update<K, V>, K, V> UpdateBuilder<'a, S, K, V, QueryConsistency> {
     pub fn consistency(self, consistency: Consistency) -> UpdateBuilder<'a, S, K, V, QueryValues> {
+        let builder = S::bind_values(self.builder.consistency(consistency), self.key, self.value);
         UpdateBuilder {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
             value: self.value,
-            builder: S::bind_values(self.builder.consistency(consistency), self.key, self.value),
+            token: partition_key_token(&builder),
+            builder,
         }
     }
 }
@@ -163,28 +175,31 @@ impl<'a, S: Update<K, V>, K, V> UpdateBuilder<'a, S, K, V, QueryValues> {
             _marker: self._marker,
             keyspace: self.keyspace,
             key: self.key,
+            token: self.token,
             value: self.value,
             builder: self.builder.timestamp(timestamp),
         }
     }
     /// Build the UpdateRequest
     pub fn build(self) -> anyhow::Result<UpdateRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
 impl<'a, S: Update<K, V>, K, V> UpdateBuilder<'a, S, K, V, QueryBuild> {
     /// Build the UpdateRequest
     pub fn build(self) -> anyhow::Result<UpdateRequest<S, K, V>> {
+        let token = self.token.unwrap_or_else(|| S::token(self.key));
         let query = self.builder.build()?;
         // create the request
-        Ok(self.keyspace.create_request(query, S::token(self.key)))
+        Ok(self.keyspace.create_request(query, token))
     }
 }
 
-/// Defines two helper methods to specify statement / id
+/// Defines the helper methods to specify statement / id / idempotency
 pub trait GetUpdateStatement<S> {
     /// Specifies the Key and Value type for an update statement
     fn update_statement<K, V>(&self) -> Cow<'static, str>
@@ -195,6 +210,11 @@ pub trait GetUpdateStatement<S> {
     fn update_id<K, V>(&self) -> [u8; 16]
     where
         S: Update<K, V>;
+
+    /// Specifies the Key and Value type for the idempotency of a statement
+    fn update_is_idempotent<K, V>(&self) -> bool
+    where
+        S: Update<K, V>;
 }
 
 impl<S: Keyspace> GetUpdateStatement<S> for S {
@@ -211,6 +231,13 @@ impl<S: Keyspace> GetUpdateStatement<S> for S {
     {
         S::id(self)
     }
+
+    fn update_is_idempotent<K, V>(&self) -> bool
+    where
+        S: Update<K, V>,
+    {
+        S::is_idempotent(self)
+    }
 }
 
 /// A request to update a record which can be sent to the ring
@@ -247,6 +274,10 @@ where
     fn payload(&self) -> &Vec<u8> {
         &self.inner
     }
+
+    fn is_idempotent(&self) -> bool {
+        self.keyspace.update_is_idempotent::<K, V>()
+    }
 }
 
 impl<S: Update<K, V>, K, V> UpdateRequest<S, K, V> {
@@ -261,6 +292,33 @@ impl<S: Update<K, V>, K, V> UpdateRequest<S, K, V> {
         DecodeResult::update()
     }
 
+    /// Send a local request to a replica of the local rack and return a type marker
+    pub fn send_local_rack(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeVoid<S>> {
+        send_local_rack(
+            self.token,
+            self.inner,
+            worker,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::update()
+    }
+
+    /// Send a request to the replica picked by the load balancing policy and return a type marker
+    pub fn send_with_policy(
+        self,
+        worker: Box<dyn Worker>,
+        policy: &dyn LoadBalancingPolicy,
+    ) -> DecodeResult<DecodeVoid<S>> {
+        send_with_policy(
+            self.token,
+            self.inner,
+            worker,
+            policy,
+            self.keyspace.name().clone().into_owned(),
+        );
+        DecodeResult::update()
+    }
+
     /// Send a global request using the keyspace impl and return a type marker
     pub fn send_global(self, worker: Box<dyn Worker>) -> DecodeResult<DecodeVoid<S>> {
         send_global(
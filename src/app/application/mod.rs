@@ -4,16 +4,22 @@
 use super::{
     cluster::{ClusterBuilder, ClusterHandle},
     listener::{ListenerBuilder, ListenerHandle},
+    stage::DEFAULT_MAX_PENDING_REQUESTS,
     websocket::WsTx,
     *,
 };
-pub(crate) use crate::cql::{CqlBuilder, PasswordAuth};
+pub(crate) use crate::cql::{
+    upgrade_request, AllowAllAuth, BoxedAuthenticator, CompressionStats, CqlBuilder, CqlStream, Event, EventType,
+    MyCompression, PasswordAuth, Peer, ScramSha256Auth, SegmentCodec, StatusChange, StatusChangeType, TlsConfig,
+    TopologyChange, TopologyChangeType,
+};
 use anyhow::{anyhow, bail};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::{
     collections::HashMap,
     net::SocketAddr,
     ops::{Deref, DerefMut},
+    time::Duration,
 };
 
 mod event_loop;
@@ -33,12 +39,20 @@ builder!(
         reporter_count: u8,
         thread_count: usize,
         local_dc: String,
+        local_rack: String,
         buffer_size: usize,
         recv_buffer_size: u32,
         send_buffer_size: u32,
         listener_handle: ListenerHandle,
         cluster_handle: ClusterHandle,
-        authenticator: PasswordAuth
+        authenticator: BoxedAuthenticator,
+        tls: TlsConfig,
+        compression: Vec<MyCompression>,
+        compression_threshold: usize,
+        request_timeout: Duration,
+        max_pending_requests: usize,
+        pending_timeout: Duration,
+        discover_peers: bool
 });
 
 #[derive(Deserialize, Serialize)]
@@ -109,11 +123,13 @@ pub enum ScyllaEvent<T> {
     Abort,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Debug)]
+#[serde(from = "TopologyJson")]
 /// Topology event
 pub enum Topology {
-    /// AddNode json to add new scylla node
-    AddNode(SocketAddr),
+    /// AddNode json to add new scylla node, with optional credentials which override the cluster authenticator.
+    /// The credentials are never serialized, so the AddNode results don't expose them to the websockets.
+    AddNode(SocketAddr, Option<Credentials>),
     /// RemoveNode json to remove an existing scylla node
     RemoveNode(SocketAddr),
     /// BuildRing json to re/build the cluster topology,
@@ -122,6 +138,89 @@ pub enum Topology {
     BuildRing(u8),
 }
 
+impl Serialize for Topology {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            // the AddNode keeps its original json (without the credentials)
+            Topology::AddNode(address, _) => serializer.serialize_newtype_variant("Topology", 0, "AddNode", address),
+            Topology::RemoveNode(address) => serializer.serialize_newtype_variant("Topology", 1, "RemoveNode", address),
+            Topology::BuildRing(replication_factor) => {
+                serializer.serialize_newtype_variant("Topology", 2, "BuildRing", replication_factor)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+/// The topology json, which accepts the AddNode of a node with or without credentials
+enum TopologyJson {
+    AddNode(AddNodeJson),
+    RemoveNode(SocketAddr),
+    BuildRing(u8),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AddNodeJson {
+    Address(SocketAddr),
+    WithCredentials(SocketAddr, Option<Credentials>),
+}
+
+impl From<TopologyJson> for Topology {
+    fn from(topology: TopologyJson) -> Self {
+        match topology {
+            TopologyJson::AddNode(AddNodeJson::Address(address)) => Topology::AddNode(address, None),
+            TopologyJson::AddNode(AddNodeJson::WithCredentials(address, credentials)) => {
+                Topology::AddNode(address, credentials)
+            }
+            TopologyJson::RemoveNode(address) => Topology::RemoveNode(address),
+            TopologyJson::BuildRing(replication_factor) => Topology::BuildRing(replication_factor),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+/// The credentials used to authenticate the cql connections of a scylla node
+pub enum Credentials {
+    /// No authentication (AllowAllAuthenticator)
+    AllowAll,
+    /// PasswordAuthenticator with the user and password
+    Password {
+        /// The user name
+        user: String,
+        /// The user password
+        pass: String,
+    },
+    /// SCRAM-SHA-256 SASL authentication with the user and password
+    ScramSha256 {
+        /// The user name
+        user: String,
+        /// The user password
+        pass: String,
+    },
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never leak the password into the logs
+        match self {
+            Credentials::AllowAll => write!(f, "AllowAll"),
+            Credentials::Password { user, .. } => write!(f, "Password {{ user: {:?} }}", user),
+            Credentials::ScramSha256 { user, .. } => write!(f, "ScramSha256 {{ user: {:?} }}", user),
+        }
+    }
+}
+
+impl From<Credentials> for BoxedAuthenticator {
+    fn from(credentials: Credentials) -> Self {
+        match credentials {
+            Credentials::AllowAll => BoxedAuthenticator::new(AllowAllAuth),
+            Credentials::Password { user, pass } => BoxedAuthenticator::new(PasswordAuth::new(user, pass)),
+            Credentials::ScramSha256 { user, pass } => BoxedAuthenticator::new(ScramSha256Auth::new(user, pass)),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 /// Indicates which app this message is for
 pub enum SocketMsg<T> {
@@ -208,3 +307,24 @@ impl<H: ScyllaScope> Name for Scylla<H> {
         self.service.get_name()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_node_json() {
+        let address: SocketAddr = "127.0.0.1:9042".parse().unwrap();
+        // the AddNode json without credentials is still supported
+        let topology: Topology = serde_json::from_str(r#"{"AddNode":"127.0.0.1:9042"}"#).unwrap();
+        assert!(matches!(topology, Topology::AddNode(a, None) if a == address));
+        let topology: Topology =
+            serde_json::from_str(r#"{"AddNode":["127.0.0.1:9042",{"Password":{"user":"scylla","pass":"secret"}}]}"#)
+                .unwrap();
+        assert!(matches!(topology, Topology::AddNode(a, Some(Credentials::Password { .. })) if a == address));
+        // the credentials are not serialized
+        let json = serde_json::to_string(&topology).unwrap();
+        assert_eq!(json, r#"{"AddNode":"127.0.0.1:9042"}"#);
+        assert!(!format!("{:?}", topology).contains("secret"));
+    }
+}
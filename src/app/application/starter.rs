@@ -28,10 +28,22 @@ impl<H: ScyllaScope> Starter<H> for ScyllaBuilder<H> {
             .reporter_count(self.reporter_count.clone().unwrap())
             .thread_count(self.thread_count.clone().unwrap())
             .data_centers(vec![self.local_dc.clone().unwrap()])
+            .local_rack(self.local_rack.clone())
             .buffer_size(self.buffer_size.clone().unwrap_or(1024000))
             .recv_buffer_size(self.recv_buffer_size.clone())
             .send_buffer_size(self.send_buffer_size.clone())
-            .authenticator(self.authenticator.clone().unwrap_or(PasswordAuth::default()))
+            .authenticator(
+                self.authenticator
+                    .clone()
+                    .unwrap_or_else(|| BoxedAuthenticator::new(PasswordAuth::default())),
+            )
+            .tls(self.tls.clone())
+            .compression(self.compression.clone().unwrap_or_default())
+            .compression_threshold(self.compression_threshold.unwrap_or_default())
+            .request_timeout(self.request_timeout)
+            .max_pending_requests(self.max_pending_requests.unwrap_or(DEFAULT_MAX_PENDING_REQUESTS))
+            .pending_timeout(self.pending_timeout)
+            .discover_peers(self.discover_peers.unwrap_or(false))
             .build();
         // clone cluster handle
         let cluster_handle = cluster
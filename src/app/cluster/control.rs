@@ -0,0 +1,163 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use std::time::Duration;
+
+/// The server event types the control connection registers for.
+const CONTROL_EVENTS: &[EventType] = &[
+    EventType::TopologyChange,
+    EventType::StatusChange,
+    EventType::SchemaChange,
+];
+/// The delay before reconnecting a lost control connection.
+const RECONNECT_DELAY: Duration = Duration::from_millis(5000);
+
+/// The control connection of the cluster, which is registered for the server events
+/// and pushes them to the cluster.
+pub(crate) struct ControlConnection {
+    pub(crate) address: SocketAddr,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ControlConnection {
+    /// Spawn the control connection to the given node.
+    pub(crate) fn spawn(
+        address: SocketAddr,
+        authenticator: BoxedAuthenticator,
+        tls: Option<TlsConfig>,
+        compression: Vec<MyCompression>,
+        handle: ClusterHandle,
+        reconnect: bool,
+    ) -> Self {
+        let task = tokio::spawn(async move {
+            if reconnect {
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+            match listen(address, authenticator, tls, compression, &handle).await {
+                Ok(()) => (),
+                Err(e) => {
+                    warn!("Control connection to {} lost: {}", address, e);
+                    handle.send(ClusterEvent::ControlConnectionLost(address)).ok();
+                }
+            }
+        });
+        Self { address, task }
+    }
+    /// Abort the control connection.
+    pub(crate) fn abort(self) {
+        self.task.abort();
+    }
+}
+
+/// Register for the server events and push them to the cluster, returns once the cluster is gone.
+async fn listen(
+    address: SocketAddr,
+    authenticator: BoxedAuthenticator,
+    tls: Option<TlsConfig>,
+    compression: Vec<MyCompression>,
+    handle: &ClusterHandle,
+) -> anyhow::Result<()> {
+    let mut cql = CqlBuilder::new()
+        .address(address)
+        .authenticator(authenticator)
+        .tls(tls)
+        .compression(compression)
+        .build()
+        .await?;
+    cql.register(CONTROL_EVENTS).await?;
+    info!("Control connection registered for server events on {}", address);
+    loop {
+        let event = cql.next_event().await?;
+        if handle.send(ClusterEvent::ServerEvent(event)).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+impl Cluster {
+    /// Spawn the control connection (if not already running), preferring any node other than `lost`.
+    pub(crate) fn spawn_control_connection(&mut self, lost: Option<SocketAddr>) {
+        if self.control_connection.is_some() {
+            return;
+        }
+        let node_info = self
+            .nodes
+            .values()
+            .find(|node_info| Some(node_info.address) != lost)
+            .or_else(|| self.nodes.values().next());
+        if let (Some(node_info), Some(handle)) = (node_info, self.handle.clone()) {
+            let control_connection = ControlConnection::spawn(
+                node_info.address,
+                node_info.authenticator.clone(),
+                self.tls.clone(),
+                self.compression.clone(),
+                handle,
+                lost.is_some(),
+            );
+            self.control_connection.replace(control_connection);
+        }
+    }
+    /// Turn the server event into the cluster events which keep the cluster up to date.
+    pub(crate) fn handle_server_event(&mut self, event: Event) {
+        let handle = match self.handle.as_ref() {
+            Some(handle) => handle,
+            None => return,
+        };
+        // the events refer to the nodes by their rpc address
+        let nodes = &self.nodes;
+        let node_address = |address: SocketAddr| {
+            nodes
+                .keys()
+                .find(|node_address| node_address.ip() == address.ip())
+                .cloned()
+        };
+        match event {
+            Event::TopologyChange(TopologyChange {
+                change: TopologyChangeType::NewNode,
+                address,
+            })
+            | Event::StatusChange(StatusChange {
+                change: StatusChangeType::Up,
+                address,
+            }) => {
+                if node_address(address).is_none() && self.event_nodes.insert(address) {
+                    info!("Adding node {} to the cluster", address);
+                    handle.send(ClusterEvent::AddNode(address, None)).ok();
+                }
+            }
+            Event::TopologyChange(TopologyChange {
+                change: TopologyChangeType::RemovedNode,
+                address,
+            }) => {
+                if let Some(address) = node_address(address) {
+                    info!("Removing node {} from the cluster", address);
+                    handle.send(ClusterEvent::RemoveNode(address)).ok();
+                    // rebuild the ring (if it was ever built) without the removed node
+                    if let Some(uniform_rf) = self.uniform_rf {
+                        handle.send(ClusterEvent::BuildRing(uniform_rf)).ok();
+                    }
+                }
+            }
+            Event::TopologyChange(TopologyChange {
+                change: TopologyChangeType::MovedNode,
+                address,
+            }) => {
+                warn!(
+                    "Node {} moved its tokens, the ring must be rebuilt by the admin",
+                    address
+                );
+            }
+            Event::StatusChange(StatusChange {
+                change: StatusChangeType::Down,
+                address,
+            }) => {
+                // the stages of the node keep reconnecting on their own
+                warn!("Node {} is down", address);
+            }
+            Event::SchemaChange(schema_change) => {
+                info!("Schema change: {:?}", schema_change);
+            }
+        }
+    }
+}
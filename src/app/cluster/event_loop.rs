@@ -35,63 +35,50 @@ impl<H: ScyllaScope> EventLoop<ScyllaHandle<H>> for Cluster {
                         let event = ScyllaEvent::Children(ScyllaChild::Cluster(self.service.clone()));
                         let _ = supervisor.send(event);
                     }
-                    // Maybe let the variant to set the PasswordAuth instead of forcing global_auth at the cluster
-                    // level?
-                    ClusterEvent::AddNode(address) => {
+                    ClusterEvent::AddNode(address, authenticator) => {
                         // make sure it doesn't already exist in our cluster
                         if self.nodes.contains_key(&address) {
-                            let event = ScyllaEvent::Result(SocketMsg::Scylla(Err(Topology::AddNode(address))));
+                            let event = ScyllaEvent::Result(SocketMsg::Scylla(Err(Topology::AddNode(address, None))));
                             let _ = supervisor.send(event);
                             continue;
                         }
-                        // to spawn node we first make sure it's online;
-                        let cql = CqlBuilder::new()
-                            .address(address)
-                            .tokens()
-                            .recv_buffer_size(self.recv_buffer_size)
-                            .send_buffer_size(self.send_buffer_size)
-                            .authenticator(self.authenticator.clone())
-                            .build();
-                        match cql.await {
-                            Ok(mut cqlconn) => {
-                                // add it as microservice
-                                let node_service = Service::new().set_name(address.to_string());
-                                self.service.update_microservice(node_service.get_name(), node_service);
-                                let shard_count = cqlconn.shard_count();
-                                if let (Some(dc), Some(tokens)) = (cqlconn.take_dc(), cqlconn.take_tokens()) {
-                                    // create node
-                                    let node = NodeBuilder::new()
-                                        .address(address.clone())
-                                        .reporter_count(self.reporter_count)
-                                        .shard_count(shard_count)
-                                        .data_center(dc.clone())
-                                        .buffer_size(self.buffer_size)
-                                        .recv_buffer_size(self.recv_buffer_size)
-                                        .send_buffer_size(self.send_buffer_size)
-                                        .authenticator(self.authenticator.clone())
-                                        .build();
-                                    // clone the node_handle
-                                    let node_handle = node.clone_handle();
-                                    // get msb
-                                    let msb = cqlconn.msb();
-                                    // create nodeinfo
-                                    let node_info = NodeInfo {
-                                        address: address.clone(),
-                                        msb,
-                                        shard_count,
-                                        node_handle,
-                                        data_center: dc,
-                                        tokens,
-                                    };
-                                    // add node_info to nodes
-                                    self.nodes.insert(address, node_info);
-                                    tokio::spawn(node.start(self.handle.clone()));
-                                } else {
-                                    error!("Failed to retrieve data from CQL Connection!");
+                        // the node authenticator falls back to the cluster one
+                        let authenticator = authenticator.unwrap_or_else(|| self.authenticator.clone());
+                        // discover the rest of the cluster through the first node (if enabled)
+                        let discover_peers = self.discover_peers && self.nodes.is_empty();
+                        match self.spawn_node(address, authenticator.clone(), discover_peers).await {
+                            Ok(peers) => {
+                                // spawn the discovered peers before the admin builds the ring
+                                let mut pending = HashSet::new();
+                                for peer in peers {
+                                    if self.nodes.contains_key(&peer.address) {
+                                        continue;
+                                    }
+                                    info!(
+                                        "Discovered scylla node {} in {}/{}",
+                                        peer.address, peer.data_center, peer.rack
+                                    );
+                                    match self.spawn_node(peer.address, authenticator.clone(), false).await {
+                                        Ok(_) => {
+                                            pending.insert(peer.address);
+                                        }
+                                        Err(e) => warn!("Failed to add discovered scylla node {}: {}", peer.address, e),
+                                    }
                                 }
+                                if !pending.is_empty() {
+                                    // the AddNode is answered once the node and its peers are registered
+                                    pending.insert(address);
+                                    self.discovery.replace((address, pending));
+                                }
+                                // subscribe to the server events through the first node
+                                self.spawn_control_connection(None);
                             }
-                            Err(_) => {
-                                let event = ScyllaEvent::Result(SocketMsg::Scylla(Err(Topology::AddNode(address))));
+                            Err(e) => {
+                                error!("Failed to add scylla node {}: {}", address, e);
+                                // the node can be added again on its next server event
+                                self.event_nodes.remove(&address);
+                                let event =
+                                    ScyllaEvent::Result(SocketMsg::Scylla(Err(Topology::AddNode(address, None))));
                                 let _ = supervisor.send(event);
                             }
                         }
@@ -109,6 +96,13 @@ impl<H: ScyllaScope> EventLoop<ScyllaHandle<H>> for Cluster {
                             node_info.node_handle.shutdown();
                             // update waiting for build to true
                             self.should_build = true;
+                            // move the control connection to another node
+                            if self.control_connection.as_ref().map(|control| control.address) == Some(address) {
+                                if let Some(control_connection) = self.control_connection.take() {
+                                    control_connection.abort();
+                                }
+                                self.spawn_control_connection(Some(address));
+                            }
                             // note: the node tree will not get shutdown unless we drop the ring
                             // but we cannot drop the ring unless we build a new one and atomically swap it,
                             // therefore dashboard admin supposed to BuildRing
@@ -127,9 +121,34 @@ impl<H: ScyllaScope> EventLoop<ScyllaHandle<H>> for Cluster {
                             self.registry.extend(reporters_handles);
                             // update waiting for build to true
                             self.should_build = true;
+                            // the discovered peers are reported within the AddNode of their contact node
+                            let reply = match self.discovery.take() {
+                                Some((contact, mut pending)) if pending.contains(&address) => {
+                                    pending.remove(&address);
+                                    if pending.is_empty() {
+                                        Some(contact)
+                                    } else {
+                                        self.discovery.replace((contact, pending));
+                                        None
+                                    }
+                                }
+                                discovery => {
+                                    self.discovery = discovery;
+                                    Some(address)
+                                }
+                            };
                             // reply to scylla/dashboard
-                            let event = ScyllaEvent::Result(SocketMsg::Scylla(Ok(Topology::AddNode(address))));
-                            let _ = supervisor.send(event);
+                            if let Some(address) = reply {
+                                let event =
+                                    ScyllaEvent::Result(SocketMsg::Scylla(Ok(Topology::AddNode(address, None))));
+                                let _ = supervisor.send(event);
+                            }
+                            // nodes added by the server events don't wait for the admin to rebuild the ring
+                            if self.event_nodes.remove(&address) {
+                                if let (Some(uniform_rf), Some(handle)) = (self.uniform_rf, self.handle.as_ref()) {
+                                    handle.send(ClusterEvent::BuildRing(uniform_rf)).ok();
+                                }
+                            }
                         } else {
                             error!("Failed to parse node address!");
                         }
@@ -151,6 +170,7 @@ impl<H: ScyllaScope> EventLoop<ScyllaHandle<H>> for Cluster {
                             } else {
                                 let (new_arc_ring, old_weak_ring) = build_ring(
                                     &mut self.data_centers,
+                                    self.local_rack.clone(),
                                     &self.nodes,
                                     self.registry.clone(),
                                     self.reporter_count,
@@ -166,6 +186,8 @@ impl<H: ScyllaScope> EventLoop<ScyllaHandle<H>> for Cluster {
                             // reset should_build state to false becaue we built it and we don't want to rebuild again
                             // incase of another BuildRing event
                             self.should_build = false;
+                            // keep the replication factor to rebuild the ring on topology changes
+                            self.uniform_rf.replace(uniform_rf);
                             // reply to scylla/dashboard
                             let event = ScyllaEvent::Result(SocketMsg::Scylla(Ok(Topology::BuildRing(uniform_rf))));
                             let _ = supervisor.send(event);
@@ -175,7 +197,20 @@ impl<H: ScyllaScope> EventLoop<ScyllaHandle<H>> for Cluster {
                             let _ = supervisor.send(event);
                         }
                     }
+                    ClusterEvent::ServerEvent(event) => {
+                        self.handle_server_event(event);
+                    }
+                    ClusterEvent::ControlConnectionLost(address) => {
+                        if self.control_connection.as_ref().map(|control| control.address) == Some(address) {
+                            self.control_connection = None;
+                            self.spawn_control_connection(Some(address));
+                        }
+                    }
                     ClusterEvent::Shutdown => {
+                        // stop the control connection, as it holds a cluster handle
+                        if let Some(control_connection) = self.control_connection.take() {
+                            control_connection.abort();
+                        }
                         // do self cleanup on weaks
                         self.cleanup();
                         // shutdown everything and drop self.tx
@@ -211,6 +246,73 @@ impl<H: ScyllaScope> EventLoop<ScyllaHandle<H>> for Cluster {
 }
 
 impl Cluster {
+    /// Connect to the node to make sure it's online, then spawn its Node actor.
+    /// Returns the peers of the node if `discover_peers` is set.
+    async fn spawn_node(
+        &mut self,
+        address: SocketAddr,
+        authenticator: BoxedAuthenticator,
+        discover_peers: bool,
+    ) -> anyhow::Result<Vec<Peer>> {
+        let mut cql = CqlBuilder::new()
+            .address(address)
+            .tokens()
+            .recv_buffer_size(self.recv_buffer_size)
+            .send_buffer_size(self.send_buffer_size)
+            .authenticator(authenticator.clone())
+            .tls(self.tls.clone())
+            .compression(self.compression.clone());
+        if discover_peers {
+            cql = cql.peers();
+        }
+        let mut cqlconn = cql.build().await?;
+        let (dc, tokens) = match (cqlconn.take_dc(), cqlconn.take_tokens()) {
+            (Some(dc), Some(tokens)) => (dc, tokens),
+            _ => anyhow::bail!("Failed to retrieve data from CQL Connection!"),
+        };
+        let rack = cqlconn.take_rack().unwrap_or_default();
+        // add it as microservice
+        let node_service = Service::new().set_name(address.to_string());
+        self.service.update_microservice(node_service.get_name(), node_service);
+        let shard_count = cqlconn.shard_count();
+        // create node
+        let node = NodeBuilder::new()
+            .address(address)
+            .reporter_count(self.reporter_count)
+            .shard_count(shard_count)
+            .data_center(dc.clone())
+            .buffer_size(self.buffer_size)
+            .recv_buffer_size(self.recv_buffer_size)
+            .send_buffer_size(self.send_buffer_size)
+            .authenticator(authenticator.clone())
+            .tls(self.tls.clone())
+            .compression(self.compression.clone())
+            .compression_threshold(self.compression_threshold)
+            .request_timeout(self.request_timeout)
+            .max_pending_requests(self.max_pending_requests)
+            .pending_timeout(self.pending_timeout)
+            .protocol_version(cqlconn.protocol_version())
+            .build();
+        // clone the node_handle
+        let node_handle = node.clone_handle();
+        // get msb
+        let msb = cqlconn.msb();
+        // create nodeinfo
+        let node_info = NodeInfo {
+            address,
+            msb,
+            shard_count,
+            node_handle,
+            data_center: dc,
+            rack,
+            tokens,
+            authenticator,
+        };
+        // add node_info to nodes
+        self.nodes.insert(address, node_info);
+        tokio::spawn(node.start(self.handle.clone()));
+        Ok(cqlconn.take_peers().unwrap_or_default())
+    }
     fn cleanup(&mut self) {
         // total_weak_count = thread_count + 1(the global weak)
         // so we clear all old weaks once weak_count > self.thread_count
@@ -7,14 +7,17 @@ use super::{
 };
 use crate::app::{
     ring::{build_ring, initialize_ring, ArcRing, Registry, Ring, WeakRing},
-    stage::ReportersHandles,
+    stage::{ReportersHandles, DEFAULT_MAX_PENDING_REQUESTS},
 };
+use control::ControlConnection;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     net::SocketAddr,
     ops::{Deref, DerefMut},
+    time::Duration,
 };
 
+mod control;
 mod event_loop;
 mod init;
 mod terminating;
@@ -26,10 +29,18 @@ builder!(ClusterBuilder {
     reporter_count: u8,
     thread_count: usize,
     data_centers: Vec<String>,
+    local_rack: Option<String>,
     buffer_size: usize,
     recv_buffer_size: Option<u32>,
     send_buffer_size: Option<u32>,
-    authenticator: PasswordAuth
+    authenticator: BoxedAuthenticator,
+    tls: Option<TlsConfig>,
+    compression: Vec<MyCompression>,
+    compression_threshold: usize,
+    request_timeout: Option<Duration>,
+    max_pending_requests: usize,
+    pending_timeout: Option<Duration>,
+    discover_peers: bool
 });
 /// ClusterHandle to be passed to the children (Node)
 #[derive(Clone)]
@@ -69,10 +80,18 @@ pub struct Cluster {
     reporter_count: u8,
     thread_count: usize,
     data_centers: Vec<String>,
+    local_rack: Option<String>,
     buffer_size: usize,
     recv_buffer_size: Option<u32>,
     send_buffer_size: Option<u32>,
-    authenticator: PasswordAuth,
+    authenticator: BoxedAuthenticator,
+    tls: Option<TlsConfig>,
+    compression: Vec<MyCompression>,
+    compression_threshold: usize,
+    request_timeout: Option<Duration>,
+    max_pending_requests: usize,
+    pending_timeout: Option<Duration>,
+    discover_peers: bool,
     nodes: Nodes,
     should_build: bool,
     version: u8,
@@ -81,6 +100,10 @@ pub struct Cluster {
     weak_rings: Vec<Box<WeakRing>>,
     handle: Option<ClusterHandle>,
     inbox: ClusterInbox,
+    control_connection: Option<ControlConnection>,
+    event_nodes: HashSet<SocketAddr>,
+    uniform_rf: Option<u8>,
+    discovery: Option<(SocketAddr, HashSet<SocketAddr>)>,
 }
 
 impl Cluster {
@@ -94,12 +117,17 @@ pub enum ClusterEvent {
     RegisterReporters(Service, HashMap<SocketAddr, ReportersHandles>),
     /// Used by the Node to keep the cluster up to date with its service
     Service(Service),
-    /// Used by Scylla/dashboard to add/connect to new scylla node in the cluster
-    AddNode(SocketAddr),
+    /// Used by Scylla/dashboard to add/connect to new scylla node in the cluster,
+    /// with an optional authenticator which overrides the cluster one
+    AddNode(SocketAddr, Option<BoxedAuthenticator>),
     /// Used by Scylla/dashboard to remove/disconnect from existing scylla node in the cluster
     RemoveNode(SocketAddr),
     /// Used by Scylla/dashboard to build new ring and expose the recent cluster topology
     BuildRing(u8),
+    /// Used by the control connection to push the server events (topology, status and schema changes)
+    ServerEvent(Event),
+    /// Used by the control connection to notify the cluster that it lost the connection to the node
+    ControlConnectionLost(SocketAddr),
     /// Used by Scylla/dashboard to shutdown the cluster
     Shutdown,
 }
@@ -107,7 +135,7 @@ pub enum ClusterEvent {
 impl From<Topology> for ClusterEvent {
     fn from(topo: Topology) -> Self {
         match topo {
-            Topology::AddNode(address) => ClusterEvent::AddNode(address),
+            Topology::AddNode(address, credentials) => ClusterEvent::AddNode(address, credentials.map(Into::into)),
             Topology::RemoveNode(address) => ClusterEvent::RemoveNode(address),
             Topology::BuildRing(t) => ClusterEvent::BuildRing(t),
         }
@@ -130,10 +158,18 @@ impl Builder for ClusterBuilder {
             reporter_count: self.reporter_count.unwrap(),
             thread_count: self.thread_count.unwrap(),
             data_centers: self.data_centers.unwrap(),
+            local_rack: self.local_rack.unwrap_or_default(),
             buffer_size: self.buffer_size.unwrap(),
             recv_buffer_size: self.recv_buffer_size.unwrap(),
             send_buffer_size: self.send_buffer_size.unwrap(),
             authenticator: self.authenticator.unwrap(),
+            tls: self.tls.unwrap(),
+            compression: self.compression.unwrap_or_default(),
+            compression_threshold: self.compression_threshold.unwrap_or_default(),
+            request_timeout: self.request_timeout.unwrap_or_default(),
+            max_pending_requests: self.max_pending_requests.unwrap_or(DEFAULT_MAX_PENDING_REQUESTS),
+            pending_timeout: self.pending_timeout.unwrap_or_default(),
+            discover_peers: self.discover_peers.unwrap_or(false),
             nodes: HashMap::new(),
             should_build: false,
             version: 0,
@@ -142,6 +178,10 @@ impl Builder for ClusterBuilder {
             weak_rings: Vec::new(),
             handle,
             inbox,
+            control_connection: None,
+            event_nodes: HashSet::new(),
+            uniform_rf: None,
+            discovery: None,
         }
         .set_name()
     }
@@ -152,6 +192,8 @@ pub struct NodeInfo {
     pub(crate) address: SocketAddr,
     /// in which data_center the scylla node exist
     pub(crate) data_center: String,
+    /// in which rack the scylla node exist
+    pub(crate) rack: String,
     /// it's the node handle for the Node supervisor tree
     pub(crate) node_handle: NodeHandle,
     /// The tokens of all nodes shards.
@@ -160,6 +202,8 @@ pub struct NodeInfo {
     pub(crate) shard_count: u16,
     /// the most significant bit
     pub(crate) msb: u8,
+    /// the authenticator of the node connections
+    pub(crate) authenticator: BoxedAuthenticator,
 }
 
 /// impl name of the Cluster
@@ -20,6 +20,13 @@ impl Init<ClusterHandle> for Node {
                     .recv_buffer_size(self.recv_buffer_size)
                     .send_buffer_size(self.send_buffer_size)
                     .authenticator(self.authenticator.clone())
+                    .tls(self.tls.clone())
+                    .compression(self.compression.clone())
+                    .compression_threshold(self.compression_threshold)
+                    .request_timeout(self.request_timeout)
+                    .max_pending_requests(self.max_pending_requests)
+                    .pending_timeout(self.pending_timeout)
+                    .protocol_version(self.protocol_version)
                     .build();
                 if let Some(stage_handle) = stage.clone_handle() {
                     self.stages.insert(shard_id, stage_handle);
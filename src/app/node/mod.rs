@@ -3,13 +3,14 @@
 
 use super::{
     cluster::{ClusterEvent, ClusterHandle},
-    stage::{ReportersHandles, StageBuilder, StageEvent, StageHandle},
+    stage::{ReportersHandles, StageBuilder, StageEvent, StageHandle, DEFAULT_MAX_PENDING_REQUESTS},
     *,
 };
 use std::{
     collections::HashMap,
     net::SocketAddr,
     ops::{Deref, DerefMut},
+    time::Duration,
 };
 
 mod event_loop;
@@ -25,7 +26,14 @@ builder!(NodeBuilder {
     buffer_size: usize,
     recv_buffer_size: Option<u32>,
     send_buffer_size: Option<u32>,
-    authenticator: PasswordAuth
+    authenticator: BoxedAuthenticator,
+    tls: Option<TlsConfig>,
+    compression: Vec<MyCompression>,
+    compression_threshold: usize,
+    request_timeout: Option<Duration>,
+    max_pending_requests: usize,
+    pending_timeout: Option<Duration>,
+    protocol_version: u8
 });
 
 /// NodeHandle to be passed to the children (Stage)
@@ -80,7 +88,14 @@ pub struct Node {
     buffer_size: usize,
     recv_buffer_size: Option<u32>,
     send_buffer_size: Option<u32>,
-    authenticator: PasswordAuth,
+    authenticator: BoxedAuthenticator,
+    tls: Option<TlsConfig>,
+    compression: Vec<MyCompression>,
+    compression_threshold: usize,
+    request_timeout: Option<Duration>,
+    max_pending_requests: usize,
+    pending_timeout: Option<Duration>,
+    protocol_version: u8,
     handle: Option<NodeHandle>,
     inbox: NodeInbox,
 }
@@ -109,6 +124,13 @@ impl Builder for NodeBuilder {
             recv_buffer_size: self.recv_buffer_size.unwrap(),
             send_buffer_size: self.send_buffer_size.unwrap(),
             authenticator: self.authenticator.unwrap(),
+            tls: self.tls.unwrap(),
+            compression: self.compression.unwrap(),
+            compression_threshold: self.compression_threshold.unwrap_or_default(),
+            request_timeout: self.request_timeout.unwrap_or_default(),
+            max_pending_requests: self.max_pending_requests.unwrap_or(DEFAULT_MAX_PENDING_REQUESTS),
+            pending_timeout: self.pending_timeout.unwrap_or_default(),
+            protocol_version: self.protocol_version.unwrap(),
             handle,
             inbox,
         }
@@ -0,0 +1,254 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// A replica of the token which can be picked by the load balancing policy.
+#[derive(Clone, Copy)]
+pub struct ReplicaInfo<'a> {
+    /// The address of the replica shard which owns the token, the shard id is the port of the address.
+    pub address: SocketAddr,
+    /// The data center of the replica.
+    pub data_center: &'a str,
+    /// The rack of the replica.
+    pub rack: &'a str,
+    /// Whether the replica is in the local data center.
+    pub is_local: bool,
+    /// The reporters of the replica shard.
+    pub reporters: &'a ReportersHandles,
+}
+
+impl ReplicaInfo<'_> {
+    /// Get the number of the requests which are sent or queued by the replica shard.
+    pub fn load(&self) -> usize {
+        self.reporters.in_flight_requests() + self.reporters.pending_requests()
+    }
+    /// Get the average latency of the replica shard, if any of its requests was answered.
+    pub fn latency(&self) -> Option<Duration> {
+        self.reporters.latency()
+    }
+}
+
+/// Picks the replica which the ring sends the request to.
+pub trait LoadBalancingPolicy: Send + Sync {
+    /// Pick the index of the replica out of the (non empty) replicas of the token, the replicas of the local data
+    /// center come first. An index out of range picks the first replica.
+    fn pick(&self, replicas: &[ReplicaInfo]) -> usize;
+}
+
+/// The load balancing policy which picks the replicas in turn.
+#[derive(Debug, Default)]
+pub struct RoundRobinPolicy {
+    next: AtomicUsize,
+}
+
+impl RoundRobinPolicy {
+    /// Create a new round robin policy.
+    pub const fn new() -> Self {
+        Self {
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl LoadBalancingPolicy for RoundRobinPolicy {
+    fn pick(&self, replicas: &[ReplicaInfo]) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % replicas.len().max(1)
+    }
+}
+
+/// The load balancing policy which samples two random replicas and picks the one with the fewer requests in flight.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PowerOfTwoChoicesPolicy;
+
+impl LoadBalancingPolicy for PowerOfTwoChoicesPolicy {
+    fn pick(&self, replicas: &[ReplicaInfo]) -> usize {
+        // there is no second replica to sample
+        if replicas.len() < 2 {
+            return 0;
+        }
+        let mut rng = thread_rng();
+        let first = rng.gen_range(0..replicas.len());
+        // sample the second replica out of the others
+        let second = (first + rng.gen_range(1..replicas.len())) % replicas.len();
+        if replicas[second].load() < replicas[first].load() {
+            second
+        } else {
+            first
+        }
+    }
+}
+
+/// The load balancing policy which picks a random replica out of the fastest ones, according to the moving
+/// average of their latencies.
+///
+/// The replicas which are slower than the fastest one by more than the exclusion threshold are excluded, while
+/// the replicas without latency are kept until their first response.
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyAwarePolicy {
+    exclusion_threshold: f64,
+}
+
+impl LatencyAwarePolicy {
+    /// Create a new latency aware policy, the exclusion threshold is the ratio to the fastest replica latency.
+    pub const fn new(exclusion_threshold: f64) -> Self {
+        Self { exclusion_threshold }
+    }
+}
+
+impl Default for LatencyAwarePolicy {
+    fn default() -> Self {
+        Self::new(2.0)
+    }
+}
+
+impl LoadBalancingPolicy for LatencyAwarePolicy {
+    fn pick(&self, replicas: &[ReplicaInfo]) -> usize {
+        let fastest = replicas.iter().filter_map(|replica| replica.latency()).min();
+        let candidates: Vec<usize> = replicas
+            .iter()
+            .enumerate()
+            .filter(|(_, replica)| match (replica.latency(), fastest) {
+                (Some(latency), Some(fastest)) => {
+                    latency.as_secs_f64() <= fastest.as_secs_f64() * self.exclusion_threshold
+                }
+                _ => true,
+            })
+            .map(|(index, _)| index)
+            .collect();
+        candidates
+            .get(thread_rng().gen_range(0..candidates.len().max(1)))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// The load balancing policy which lets the child policy pick out of the local data center replicas, falling back
+/// to the remote data centers replicas when none of the local ones is registered.
+#[derive(Clone, Copy)]
+pub struct DcAwarePolicy {
+    child: &'static dyn LoadBalancingPolicy,
+}
+
+impl DcAwarePolicy {
+    /// Create a new data center aware policy with the child policy.
+    pub const fn new(child: &'static dyn LoadBalancingPolicy) -> Self {
+        Self { child }
+    }
+}
+
+impl Default for DcAwarePolicy {
+    fn default() -> Self {
+        Self::new(&PowerOfTwoChoicesPolicy)
+    }
+}
+
+impl LoadBalancingPolicy for DcAwarePolicy {
+    fn pick(&self, replicas: &[ReplicaInfo]) -> usize {
+        // the local replicas come first
+        let local_replicas = replicas.iter().take_while(|replica| replica.is_local).count();
+        if local_replicas > 0 {
+            let index = self.child.pick(&replicas[..local_replicas]);
+            if index < local_replicas {
+                index
+            } else {
+                0
+            }
+        } else {
+            self.child.pick(replicas)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    static ROUND_ROBIN: RoundRobinPolicy = RoundRobinPolicy::new();
+
+    fn reporters(load: &[(usize, Option<u64>)]) -> Vec<ReportersHandles> {
+        load.iter()
+            .map(|&(in_flight_requests, latency)| {
+                ReportersHandles::detached(in_flight_requests, latency.map(Duration::from_millis))
+            })
+            .collect()
+    }
+
+    fn replicas<'a>(reporters: &'a [ReportersHandles], local: usize) -> Vec<ReplicaInfo<'a>> {
+        reporters
+            .iter()
+            .enumerate()
+            .map(|(index, reporters)| ReplicaInfo {
+                address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, index as u8)), 0),
+                data_center: if index < local { "local" } else { "remote" },
+                rack: "rack",
+                is_local: index < local,
+                reporters,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_policy() {
+        let policy = RoundRobinPolicy::new();
+        let reporters = reporters(&[(0, None); 3]);
+        let replicas = replicas(&reporters, 3);
+        let picks: Vec<usize> = (0..4).map(|_| policy.pick(&replicas)).collect();
+        assert_eq!(picks, vec![0, 1, 2, 0]);
+        assert_eq!(policy.pick(&[]), 0);
+    }
+
+    #[test]
+    fn power_of_two_choices_policy() {
+        let policy = PowerOfTwoChoicesPolicy;
+        // the second replica has fewer requests in flight, so it wins every sample
+        let reporters = reporters(&[(5, None), (0, None)]);
+        let replicas = replicas(&reporters, 2);
+        for _ in 0..100 {
+            assert_eq!(policy.pick(&replicas), 1);
+        }
+        // the most loaded replica never wins against any other one
+        let reporters = self::reporters(&[(1, None), (9, None), (2, None)]);
+        let replicas = self::replicas(&reporters, 3);
+        for _ in 0..100 {
+            assert_ne!(policy.pick(&replicas), 1);
+        }
+        assert_eq!(policy.pick(&replicas[..1]), 0);
+        assert_eq!(policy.pick(&[]), 0);
+    }
+
+    #[test]
+    fn latency_aware_policy() {
+        let policy = LatencyAwarePolicy::default();
+        // the replica without latency is kept, while the one slower than twice the fastest is excluded
+        let reporters = reporters(&[(0, Some(10)), (0, Some(30)), (0, None), (0, Some(15))]);
+        let replicas = replicas(&reporters, 4);
+        let mut picked = HashSet::new();
+        for _ in 0..200 {
+            picked.insert(policy.pick(&replicas));
+        }
+        assert_eq!(picked, [0, 2, 3].iter().cloned().collect());
+        assert_eq!(LatencyAwarePolicy::new(4.0).pick(&replicas[1..2]), 0);
+        assert_eq!(policy.pick(&[]), 0);
+    }
+
+    #[test]
+    fn dc_aware_policy() {
+        let policy = DcAwarePolicy::new(&ROUND_ROBIN);
+        // the child policy only picks out of the local replicas
+        let reporters = reporters(&[(0, None); 3]);
+        let replicas = replicas(&reporters, 2);
+        for _ in 0..4 {
+            assert!(policy.pick(&replicas) < 2);
+        }
+        // falling back to the remote replicas
+        let replicas = self::replicas(&reporters, 0);
+        let picked: HashSet<usize> = (0..3).map(|_| policy.pick(&replicas)).collect();
+        assert_eq!(picked.len(), 3);
+    }
+}
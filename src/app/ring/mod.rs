@@ -6,33 +6,41 @@ use crate::app::{
     stage::{ReporterEvent, ReportersHandles},
     worker::WorkerError,
 };
+use crate::cql::CompressionStats;
+pub use load_balancing::{
+    DcAwarePolicy, LatencyAwarePolicy, LoadBalancingPolicy, PowerOfTwoChoicesPolicy, ReplicaInfo, RoundRobinPolicy,
+};
 use std::net::SocketAddr;
 
 use rand::{distributions::Uniform, prelude::ThreadRng, thread_rng, Rng};
 use std::{
     cell::RefCell,
-    collections::HashMap,
-    i64::{MAX, MIN},
+    collections::{HashMap, HashSet},
     sync::{
         atomic::{AtomicPtr, Ordering},
         Arc, Weak,
     },
 };
+
+mod load_balancing;
+
 // types
 /// The token of Ring.
 pub type Token = i64;
 /// The tokens of shards.
-pub type Tokens = Vec<(Token, SocketAddr, DC, Msb, ShardCount)>;
+pub type Tokens = Vec<(Token, SocketAddr, DC, Rack, Msb, ShardCount)>;
 /// The most significant bit in virtual node.
 pub type Msb = u8;
 /// The number of shards.
 pub type ShardCount = u16;
 /// The tuple of recording a virtual node.
-pub type VnodeTuple = (Token, Token, SocketAddr, DC, Msb, ShardCount);
+pub type VnodeTuple = (Token, Token, SocketAddr, DC, Rack, Msb, ShardCount);
 /// The data center string.
 pub type DC = String;
+/// The rack string.
+pub type Rack = String;
 type Replicas = HashMap<DC, Vec<Replica>>;
-type Replica = (SocketAddr, Msb, ShardCount);
+type Replica = (SocketAddr, Msb, ShardCount, Rack);
 type Vcell = Box<dyn Vnode>;
 /// The registry of `SocketAddr` to its reporters.
 pub type Registry = HashMap<SocketAddr, ReportersHandles>;
@@ -45,6 +53,8 @@ pub type GlobalRing = (
     u8,
     Registry,
     Vcell,
+    Option<Rack>,
+    usize,
 );
 /// The atomic `GlobalRing`.
 pub type AtomicRing = AtomicPtr<Weak<GlobalRing>>;
@@ -73,6 +83,10 @@ pub struct Ring {
     pub uniform_dcs: Uniform<usize>,
     /// Uniform to pick random replication refactor
     pub uniform_rf: Uniform<usize>,
+    /// The local rack (used by send_local_rack strategy)
+    pub local_rack: Option<Rack>,
+    /// The uniform replication factor
+    pub replication_factor: usize,
 }
 
 static mut VERSION: u8 = 0;
@@ -89,6 +103,8 @@ thread_local! {
         let dcs = vec!["".to_string()];
         let uniform_dcs: Uniform<usize> = Uniform::new(0,dcs.len());
         let uniform_rf: Uniform<usize> = Uniform::new(0,1);
+        let local_rack = None;
+        let replication_factor = 1;
         RefCell::new(Ring{
             version,
             weak,
@@ -98,7 +114,9 @@ thread_local! {
             rng,
             uniform_dcs,
             uniform_rf,
-            dcs
+            dcs,
+            local_rack,
+            replication_factor
         })
     };
 }
@@ -122,10 +140,47 @@ impl Ring {
     pub fn send_local_random_replica(token: Token, request: ReporterEvent) {
         RING.with(|local| local.borrow_mut().sending().local_random_replica(token, request))
     }
+    /// Send request to the first local datacenter with the given token and a random replica of the local rack,
+    /// falling back to a random replica of the other racks.
+    pub fn send_local_rack(token: Token, request: ReporterEvent) {
+        RING.with(|local| local.borrow_mut().sending().local_rack(token, request))
+    }
     /// Send request to the global datacenter with the given token and a random replica.
     pub fn send_global_random_replica(token: Token, request: ReporterEvent) {
         RING.with(|local| local.borrow_mut().sending().global_random_replica(token, request))
     }
+    /// Send request with the given token to the replica picked by the load balancing policy.
+    pub fn send_with_policy(token: Token, request: ReporterEvent, policy: &dyn LoadBalancingPolicy) {
+        RING.with(|local| local.borrow_mut().sending().with_policy(token, request, policy))
+    }
+    /// Get the number of replicas of the token in the first local datacenter.
+    pub fn local_replicas_count(token: Token) -> usize {
+        RING.with(|local| local.borrow_mut().sending().local_replicas(token))
+    }
+    /// Get the compression counters of the registered stages, the shard id is the port of the address.
+    pub fn compression_stats() -> HashMap<SocketAddr, Arc<CompressionStats>> {
+        RING.with(|local| {
+            local
+                .borrow_mut()
+                .sending()
+                .registry
+                .iter()
+                .map(|(address, reporters_handles)| (*address, reporters_handles.compression_stats().clone()))
+                .collect()
+        })
+    }
+    /// Get the number of queued requests of the registered stages, the shard id is the port of the address.
+    pub fn pending_requests() -> HashMap<SocketAddr, usize> {
+        RING.with(|local| {
+            local
+                .borrow_mut()
+                .sending()
+                .registry
+                .iter()
+                .map(|(address, reporters_handles)| (*address, reporters_handles.pending_requests()))
+                .collect()
+        })
+    }
     /// Rebuild the Ring the most up to date version
     pub fn rebuild() {
         RING.with(|local| {
@@ -137,9 +192,21 @@ impl Ring {
                         Weak::upgrade(GLOBAL_RING.as_ref().unwrap().load(Ordering::Relaxed).as_ref().unwrap())
                     {
                         let new_weak = Arc::downgrade(&arc);
-                        let (dcs, uniform_dcs, uniform_rf, uniform, version, registry, root) = Arc::make_mut(&mut arc);
+                        let (
+                            dcs,
+                            uniform_dcs,
+                            uniform_rf,
+                            uniform,
+                            version,
+                            registry,
+                            root,
+                            local_rack,
+                            replication_factor,
+                        ) = Arc::make_mut(&mut arc);
                         // update the local ring
                         ring.dcs = dcs.clone();
+                        ring.local_rack = local_rack.clone();
+                        ring.replication_factor = *replication_factor;
                         ring.uniform_dcs = *uniform_dcs;
                         ring.uniform_rf = *uniform_rf;
                         ring.uniform = *uniform;
@@ -160,9 +227,21 @@ impl Ring {
                     Weak::upgrade(GLOBAL_RING.as_ref().unwrap().load(Ordering::Relaxed).as_ref().unwrap())
                 {
                     let new_weak = Arc::downgrade(&arc);
-                    let (dcs, uniform_dcs, uniform_rf, uniform, version, registry, root) = Arc::make_mut(&mut arc);
+                    let (
+                        dcs,
+                        uniform_dcs,
+                        uniform_rf,
+                        uniform,
+                        version,
+                        registry,
+                        root,
+                        local_rack,
+                        replication_factor,
+                    ) = Arc::make_mut(&mut arc);
                     // update the local ring
                     self.dcs = dcs.clone();
+                    self.local_rack = local_rack.clone();
+                    self.replication_factor = *replication_factor;
                     self.uniform_dcs = *uniform_dcs;
                     self.uniform_rf = *uniform_rf;
                     self.uniform = *uniform;
@@ -211,6 +290,43 @@ impl Ring {
             self.uniform,
         );
     }
+    fn local_rack(&mut self, token: Token, request: ReporterEvent) {
+        if let Some(rack) = self.local_rack.as_ref() {
+            // send request.
+            self.root.as_mut().search(token).send_rack(
+                &self.dcs[0],
+                rack,
+                self.replication_factor,
+                token,
+                request,
+                &mut self.registry,
+                &mut self.rng,
+                self.uniform,
+            );
+        } else {
+            self.local_random_replica(token, request);
+        }
+    }
+    fn with_policy(&mut self, token: Token, request: ReporterEvent, policy: &dyn LoadBalancingPolicy) {
+        // send request.
+        self.root.as_mut().search(token).send_with_policy(
+            &self.dcs,
+            self.replication_factor,
+            token,
+            request,
+            &mut self.registry,
+            &mut self.rng,
+            self.uniform,
+            policy,
+        );
+    }
+    fn local_replicas(&mut self, token: Token) -> usize {
+        self.root
+            .as_mut()
+            .search(token)
+            .replicas_count(&self.dcs[0])
+            .min(self.replication_factor)
+    }
     fn global_random_replica(&mut self, token: Token, request: ReporterEvent) {
         // send request.
         self.root.as_mut().search(token).send(
@@ -237,6 +353,8 @@ impl Ring {
             version,
             registry,
             root,
+            None, // local_rack
+            1,    // replication_factor
         );
         // create Arc ring
         let arc_ring = Arc::new(global_ring);
@@ -281,9 +399,7 @@ impl SmartId for Replica {
         uniform: Uniform<u8>,
         request: ReporterEvent,
     ) {
-        // shard awareness algo,
-        self.0
-            .set_port((((((token as i128 + MIN as i128) as u64) << self.1) as u128 * self.2 as u128) >> 64) as u16);
+        self.0.set_port(compute_shard_id(token, self.1, self.2));
         let _ = registry
             .get_mut(&self.0)
             .unwrap()
@@ -293,6 +409,12 @@ impl SmartId for Replica {
     }
 }
 
+/// Compute the id of the shard which owns the token.
+fn compute_shard_id(token: Token, msb: Msb, shard_count: ShardCount) -> u16 {
+    // shard awareness algo,
+    (((((token as i128 + i64::MIN as i128) as u64) << msb) as u128 * shard_count as u128) >> 64) as u16
+}
+
 /// Endpoints trait which should be implemented by `Replicas`.
 pub trait Endpoints: EndpointsClone + Send + Sync {
     /// Send the request through the endpoints.
@@ -306,6 +428,36 @@ pub trait Endpoints: EndpointsClone + Send + Sync {
         rng: &mut ThreadRng,
         uniform: Uniform<u8>,
     );
+    /// Send the request to a random replica of the rack, or to a random replica of the data_center if none of the
+    /// replicas is in the rack.
+    #[allow(clippy::too_many_arguments)]
+    fn send_rack(
+        &mut self,
+        data_center: &str,
+        rack: &str,
+        replication_factor: usize,
+        token: Token,
+        request: ReporterEvent,
+        registry: &mut Registry,
+        rng: &mut ThreadRng,
+        uniform: Uniform<u8>,
+    );
+    /// Send the request to the replica picked by the load balancing policy, out of the replicas of the
+    /// data_centers.
+    #[allow(clippy::too_many_arguments)]
+    fn send_with_policy(
+        &mut self,
+        data_centers: &[DC],
+        replication_factor: usize,
+        token: Token,
+        request: ReporterEvent,
+        registry: &mut Registry,
+        rng: &mut ThreadRng,
+        uniform: Uniform<u8>,
+        policy: &dyn LoadBalancingPolicy,
+    );
+    /// Get the number of replicas in the data_center.
+    fn replicas_count(&self, data_center: &str) -> usize;
 }
 
 /// Clone the endpoints.
@@ -340,17 +492,104 @@ impl Endpoints for Replicas {
         mut rng: &mut ThreadRng,
         uniform: Uniform<u8>,
     ) {
-        let replicas = self.get_mut(data_center).expect("Expected Replicas");
+        let replicas = match self.get_mut(data_center) {
+            Some(replicas) if !replicas.is_empty() => replicas,
+            // the data center has no replicas
+            _ => return no_ring(request),
+        };
         if let Some(replica) = replicas.get_mut(replica_index) {
             replica.send_reporter(token, &mut registry, &mut rng, uniform, request);
         } else {
             // send to a random node
             let rf = Uniform::new(0, replicas.len());
-            let mut replica = replicas[rng.sample(rf)];
+            let mut replica = replicas[rng.sample(rf)].clone();
             replica.send_reporter(token, &mut registry, &mut rng, uniform, request);
         }
     }
+    fn send_rack(
+        &mut self,
+        data_center: &str,
+        rack: &str,
+        replication_factor: usize,
+        token: Token,
+        request: ReporterEvent,
+        registry: &mut Registry,
+        rng: &mut ThreadRng,
+        uniform: Uniform<u8>,
+    ) {
+        let replicas = match self.get_mut(data_center) {
+            Some(replicas) if !replicas.is_empty() => replicas,
+            // the data center has no replicas
+            _ => return no_ring(request),
+        };
+        let replication_factor = replication_factor.clamp(1, replicas.len());
+        let rack_replicas: Vec<usize> = (0..replication_factor)
+            .filter(|index| replicas[*index].3 == rack)
+            .collect();
+        let index = if rack_replicas.is_empty() {
+            // none of the replicas is in the rack
+            rng.gen_range(0..replication_factor)
+        } else {
+            rack_replicas[rng.gen_range(0..rack_replicas.len())]
+        };
+        replicas[index].send_reporter(token, registry, rng, uniform, request);
+    }
+    fn send_with_policy(
+        &mut self,
+        data_centers: &[DC],
+        replication_factor: usize,
+        token: Token,
+        request: ReporterEvent,
+        registry: &mut Registry,
+        rng: &mut ThreadRng,
+        uniform: Uniform<u8>,
+        policy: &dyn LoadBalancingPolicy,
+    ) {
+        let address = {
+            // the registered replicas of the local data center come first
+            let mut replicas = Vec::new();
+            for (index, data_center) in data_centers.iter().enumerate() {
+                for replica in self.get(data_center).into_iter().flatten().take(replication_factor) {
+                    let mut address = replica.0;
+                    address.set_port(compute_shard_id(token, replica.1, replica.2));
+                    if let Some(reporters) = registry.get(&address) {
+                        replicas.push(ReplicaInfo {
+                            address,
+                            data_center,
+                            rack: &replica.3,
+                            is_local: index == 0,
+                            reporters,
+                        });
+                    }
+                }
+            }
+            if replicas.is_empty() {
+                None
+            } else {
+                let index = policy.pick(&replicas);
+                Some(replicas.get(index).unwrap_or(&replicas[0]).address)
+            }
+        };
+        match address.and_then(|address| registry.get_mut(&address)) {
+            Some(reporters) => {
+                let _ = reporters.get_mut(&rng.sample(uniform)).unwrap().send(request);
+            }
+            None => no_ring(request),
+        }
+    }
+    fn replicas_count(&self, data_center: &str) -> usize {
+        self.get(data_center).map_or(0, |replicas| replicas.len())
+    }
+}
+/// Respond to the worker of the request with NoRing error, as there is no replica to send it to.
+fn no_ring(request: ReporterEvent) {
+    if let ReporterEvent::Request { worker, .. } = request {
+        worker
+            .handle_error(WorkerError::NoRing, &None)
+            .unwrap_or_else(|e| log::error!("{}", e));
+    };
 }
+
 impl Endpoints for Option<Replicas> {
     // this method will be invoked when we store Replicas as None.
     // used for initial ring to simulate the reporter and respond to worker(self) with NoRing error
@@ -365,11 +604,36 @@ impl Endpoints for Option<Replicas> {
         _uniform: Uniform<u8>,
     ) {
         // simulate reporter,
-        if let ReporterEvent::Request { worker, .. } = request {
-            worker
-                .handle_error(WorkerError::NoRing, &None)
-                .unwrap_or_else(|e| log::error!("{}", e));
-        };
+        no_ring(request);
+    }
+    fn send_rack(
+        &mut self,
+        _: &str,
+        _: &str,
+        _: usize,
+        token: Token,
+        request: ReporterEvent,
+        registry: &mut Registry,
+        rng: &mut ThreadRng,
+        uniform: Uniform<u8>,
+    ) {
+        self.send("", 0, token, request, registry, rng, uniform);
+    }
+    fn send_with_policy(
+        &mut self,
+        _: &[DC],
+        _: usize,
+        token: Token,
+        request: ReporterEvent,
+        registry: &mut Registry,
+        rng: &mut ThreadRng,
+        uniform: Uniform<u8>,
+        _: &dyn LoadBalancingPolicy,
+    ) {
+        self.send("", 0, token, request, registry, rng, uniform);
+    }
+    fn replicas_count(&self, _: &str) -> usize {
+        0
     }
 }
 
@@ -500,22 +764,50 @@ fn compute_vnode(chain: &[(Token, Token, Replicas)]) -> Vcell {
     }
 }
 
-fn walk_clockwise(starting_index: usize, end_index: usize, vnodes: &[VnodeTuple], replicas: &mut Replicas) {
-    for vnode in vnodes.iter().take(end_index).skip(starting_index) {
-        // fetch replica
-        let (_, _, node_id, dc, msb, shard_count) = &vnode;
-        let replica: Replica = (*node_id, *msb, *shard_count);
-        // now push it to Replicas
-        match replicas.get_mut(dc) {
-            Some(vec_replicas_in_dc) => {
-                if !vec_replicas_in_dc.contains(&replica) {
-                    vec_replicas_in_dc.push(replica)
-                }
-            }
-            None => {
-                let vec_replicas_in_dc = vec![replica];
-                replicas.insert(dc.clone(), vec_replicas_in_dc);
+/// Walk the ring clockwise from the starting vnode and push the replicas of each data center in the order of
+/// the NetworkTopologyStrategy placement, the replicas of the racks which already own a replica are skipped until
+/// every rack of the data center owns one.
+fn walk_clockwise(
+    starting_index: usize,
+    vnodes: &[VnodeTuple],
+    racks: &HashMap<&str, HashSet<&str>>,
+    replicas: &mut Replicas,
+) {
+    // the racks which own a replica, and the skipped replicas of each data center
+    let mut owning_racks: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut skipped: HashMap<&str, Vec<Replica>> = HashMap::new();
+    // first walk clockwise phase (start..end), then second walk clockwise phase (0..start)
+    for vnode in vnodes
+        .iter()
+        .skip(starting_index)
+        .chain(vnodes.iter().take(starting_index))
+    {
+        let (_, _, node_id, dc, rack, msb, shard_count) = vnode;
+        if !replicas.contains_key(dc) {
+            replicas.insert(dc.clone(), Vec::new());
+        }
+        let vec_replicas_in_dc = replicas.get_mut(dc).unwrap();
+        let skipped_in_dc = skipped.entry(dc).or_default();
+        if vec_replicas_in_dc
+            .iter()
+            .chain(skipped_in_dc.iter())
+            .any(|replica| replica.0 == *node_id)
+        {
+            continue;
+        }
+        let replica: Replica = (*node_id, *msb, *shard_count, rack.clone());
+        let owning_racks_in_dc = owning_racks.entry(dc).or_default();
+        let racks_count = racks.get(dc.as_str()).map_or(0, |racks_in_dc| racks_in_dc.len());
+        if owning_racks_in_dc.len() == racks_count {
+            vec_replicas_in_dc.push(replica);
+        } else if owning_racks_in_dc.insert(rack) {
+            vec_replicas_in_dc.push(replica);
+            if owning_racks_in_dc.len() == racks_count {
+                // every rack owns a replica, so the skipped replicas come next
+                vec_replicas_in_dc.append(skipped_in_dc);
             }
+        } else {
+            skipped_in_dc.push(replica);
         }
     }
 }
@@ -523,6 +815,7 @@ fn walk_clockwise(starting_index: usize, end_index: usize, vnodes: &[VnodeTuple]
 /// Build the ScyllaDB ring
 pub fn build_ring(
     dcs: &mut Vec<DC>,
+    local_rack: Option<Rack>,
     nodes: &Nodes,
     registry: Registry,
     reporter_count: u8,
@@ -536,6 +829,7 @@ pub fn build_ring(
         tokens: node_tokens,
         address,
         data_center,
+        rack,
         msb,
         shard_count,
         ..
@@ -543,7 +837,7 @@ pub fn build_ring(
     {
         // we generate the tokens li
         for token in node_tokens {
-            let node_token = (*token, address.clone(), data_center.clone(), *msb, *shard_count);
+            let node_token = (*token, *address, data_center.clone(), rack.clone(), *msb, *shard_count);
             tokens.push(node_token)
         }
     }
@@ -551,10 +845,18 @@ pub fn build_ring(
     tokens.sort_unstable_by(|a, b| a.0.cmp(&b.0));
     // create vnodes tuple from tokens
     let mut vnodes = Vec::new();
-    let mut recent_left = MIN;
-    for (right, node_id, dc, msb, shard_count) in &tokens {
+    let mut recent_left = i64::MIN;
+    for (right, node_id, dc, rack, msb, shard_count) in &tokens {
         // create vnode tuple (starting from min)
-        let vnode = (recent_left, *right, *node_id, dc.clone(), *msb, *shard_count);
+        let vnode = (
+            recent_left,
+            *right,
+            *node_id,
+            dc.clone(),
+            rack.clone(),
+            *msb,
+            *shard_count,
+        );
         // push to vnodes
         vnodes.push(vnode);
         // update recent_left to right
@@ -563,7 +865,7 @@ pub fn build_ring(
     // the check bellow is only to make sure if scylla-node didn't already
     // randmoly didn't gen the MIN token by luck.
     // confirm if the vnode_min is not already exist in our token range
-    if vnodes.first().unwrap().1 == MIN {
+    if vnodes.first().unwrap().1 == i64::MIN {
         // remove it, otherwise the first vnode will be(MIN, MIN, ..) and invalidate vnode conditions
         vnodes.remove(0);
     };
@@ -573,14 +875,15 @@ pub fn build_ring(
     // the MAX to our last vnode(the largest token )
     let last_vnode = vnodes.last().unwrap();
     // confirm if the vnode max is not present in our token-range
-    if last_vnode.1 != MAX {
+    if last_vnode.1 != i64::MAX {
         let max_vnode = (
             recent_left,
-            MAX,
+            i64::MAX,
             last_vnode.2,
             last_vnode.3.clone(),
-            last_vnode.4,
+            last_vnode.4.clone(),
             last_vnode.5,
+            last_vnode.6,
         );
         // now push it
         vnodes.push(max_vnode);
@@ -596,6 +899,8 @@ pub fn build_ring(
         version,
         registry,
         root_vnode,
+        local_rack,
+        uniform_rf,
     ));
     // downgrade to weak_ring
     let weak_ring = Arc::downgrade(&arc_ring);
@@ -631,15 +936,17 @@ fn compute_ring(vnodes: &[VnodeTuple], dcs: &mut Vec<DC>) -> Vcell {
 }
 
 fn compute_chain(vnodes: &[VnodeTuple]) -> Vec<(Token, Token, Replicas)> {
+    // collect the racks of each data center
+    let mut racks: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for (_, _, _, dc, rack, _, _) in vnodes {
+        racks.entry(dc).or_default().insert(rack);
+    }
     // compute all possible replicas in advance for each vnode in vnodes
     // prepare ring chain
     let mut chain = Vec::new();
-    for (starting_index, (left, right, _, _, _, _)) in vnodes.iter().enumerate() {
+    for (starting_index, (left, right, ..)) in vnodes.iter().enumerate() {
         let mut replicas: Replicas = HashMap::new();
-        // first walk clockwise phase (start..end)
-        walk_clockwise(starting_index, vnodes.len(), &vnodes, &mut replicas);
-        // second walk clockwise phase (0..start)
-        walk_clockwise(0, starting_index, &vnodes, &mut replicas);
+        walk_clockwise(starting_index, vnodes, &racks, &mut replicas);
         // create vnode
         chain.push((*left, *right, replicas));
     }
@@ -655,9 +962,9 @@ pub fn initialize_ring(version: u8, rebuild: bool) -> (ArcRing, Option<Box<Weak<
 fn generate_and_compute_fake_ring() {
     use std::net::{IpAddr, Ipv4Addr};
     let mut rng = thread_rng();
-    let uniform = Uniform::new(MIN, MAX);
+    let uniform = Uniform::new(i64::MIN, i64::MAX);
     // create test token_range vector // the token range should be fetched from scylla node.
-    let mut tokens: Vec<(Token, SocketAddr, DC)> = Vec::new();
+    let mut tokens: Vec<(Token, SocketAddr, DC, Rack)> = Vec::new();
     // 4 us nodes ids
     let us_node_id_1: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0);
     let us_node_id_2: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), 0);
@@ -669,48 +976,57 @@ fn generate_and_compute_fake_ring() {
     let eu_node_id_3: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(128, 0, 0, 3)), 0);
     let us = "US".to_string();
     let eu = "EU".to_string();
+    // 2 racks in US Datacenter and 3 racks in EU Datacenter
+    let rack_1 = "RACK1".to_string();
+    let rack_2 = "RACK2".to_string();
+    let rack_3 = "RACK3".to_string();
     for _ in 0..256 {
         // 4 nodes in US Datacenter
-        tokens.push((rng.sample(uniform), us_node_id_1, us.clone()));
-        tokens.push((rng.sample(uniform), us_node_id_2, us.clone()));
-        tokens.push((rng.sample(uniform), us_node_id_3, us.clone()));
-        tokens.push((rng.sample(uniform), us_node_id_4, us.clone()));
+        tokens.push((rng.sample(uniform), us_node_id_1, us.clone(), rack_1.clone()));
+        tokens.push((rng.sample(uniform), us_node_id_2, us.clone(), rack_1.clone()));
+        tokens.push((rng.sample(uniform), us_node_id_3, us.clone(), rack_2.clone()));
+        tokens.push((rng.sample(uniform), us_node_id_4, us.clone(), rack_2.clone()));
         // 3 nodes in EU Datacenter
-        tokens.push((rng.sample(uniform), eu_node_id_1, eu.clone()));
-        tokens.push((rng.sample(uniform), eu_node_id_2, eu.clone()));
-        tokens.push((rng.sample(uniform), eu_node_id_3, eu.clone()));
+        tokens.push((rng.sample(uniform), eu_node_id_1, eu.clone(), rack_1.clone()));
+        tokens.push((rng.sample(uniform), eu_node_id_2, eu.clone(), rack_2.clone()));
+        tokens.push((rng.sample(uniform), eu_node_id_3, eu.clone(), rack_3.clone()));
     }
     // sort tokens by token
     tokens.sort_unstable_by(|a, b| a.0.cmp(&b.0));
     // compute replicas for each vnode
     let mut vnodes = Vec::new();
-    let mut recent_left = MIN;
-    for (right, node_id, dc) in &tokens {
+    let mut recent_left = i64::MIN;
+    for (right, node_id, dc, rack) in &tokens {
         // create vnode(starting from min)
-        let vnode = (recent_left, *right, *node_id, dc.clone(), 12, 8); // fake msb/shardcount
-                                                                        // push to vnodes
+        let vnode = (recent_left, *right, *node_id, dc.clone(), rack.clone(), 12, 8); // fake msb/shardcount
+                                                                                      // push to vnodes
         vnodes.push(vnode);
         // update recent_left to right
         recent_left = *right;
     }
     // we don't forget to add max vnode to our token range
-    let (_, recent_node_id, recent_dc) = tokens.last().unwrap();
-    let max_vnode = (recent_left, MAX, *recent_node_id, recent_dc.clone(), 12, 8); //
+    let (_, recent_node_id, recent_dc, recent_rack) = tokens.last().unwrap();
+    let max_vnode = (
+        recent_left,
+        i64::MAX,
+        *recent_node_id,
+        recent_dc.clone(),
+        recent_rack.clone(),
+        12,
+        8,
+    );
     vnodes.push(max_vnode);
     // compute all possible replicas in advance for each vnode in vnodes
-    // prepare ring chain
-    let mut chain = Vec::new();
-    let mut starting_index = 0;
-    for (left, right, _, _, _, _) in &vnodes {
-        let mut replicas: Replicas = HashMap::new();
-        // first walk clockwise phase (start..end)
-        walk_clockwise(starting_index, vnodes.len(), &vnodes, &mut replicas);
-        // second walk clockwise phase (0..start)
-        walk_clockwise(0, starting_index, &vnodes, &mut replicas);
-        // update starting_index
-        starting_index += 1;
-        // create vnode
-        chain.push((*left, *right, replicas));
+    let chain = compute_chain(&vnodes);
+    for (_, _, replicas) in &chain {
+        // every node is a replica, and the first replicas of each datacenter are placed on distinct racks
+        let us_replicas = &replicas[&us];
+        assert_eq!(us_replicas.len(), 4);
+        assert_ne!(us_replicas[0].3, us_replicas[1].3);
+        let eu_replicas = &replicas[&eu];
+        assert_eq!(eu_replicas.len(), 3);
+        let eu_racks: HashSet<&Rack> = eu_replicas.iter().map(|replica| &replica.3).collect();
+        assert_eq!(eu_racks.len(), 3);
     }
     // build computed binary search tree from chain
     // we start spliting from the root which is chain.len()/2
@@ -3,6 +3,7 @@
 
 use super::*;
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
 
 #[async_trait::async_trait]
 impl EventLoop<NodeHandle> for Stage {
@@ -66,29 +67,40 @@ impl EventLoop<NodeHandle> for Stage {
                                     .shard_id(self.shard_id)
                                     .recv_buffer_size(self.recv_buffer_size)
                                     .send_buffer_size(self.send_buffer_size)
+                                    .tls(self.tls.clone())
+                                    .compression(self.compression.clone())
+                                    .compression_threshold(self.compression_threshold)
+                                    .protocol_version(self.protocol_version)
                                     .build();
                                 match cql_builder.await {
                                     Ok(cql_conn) => {
                                         self.session_id += 1;
+                                        let compression = cql_conn.compression();
+                                        let compression_threshold = cql_conn.compression_threshold();
+                                        let segment_codec = cql_conn.segment_codec();
                                         // Split the stream
-                                        let stream: TcpStream = cql_conn.into();
-                                        let (socket_rx, socket_tx) = stream.into_split();
-                                        // spawn sender
-                                        let sender = SenderBuilder::new()
-                                            .socket(socket_tx)
-                                            .appends_num(self.appends_num)
-                                            .payloads(self.payloads.clone())
-                                            .build();
-                                        tokio::spawn(sender.start(self.reporters_handles.clone()));
-                                        // spawn receiver
-                                        let receiver = ReceiverBuilder::new()
-                                            .socket(socket_rx)
-                                            .appends_num(self.appends_num)
-                                            .payloads(self.payloads.clone())
-                                            .session_id(self.session_id)
-                                            .buffer_size(self.buffer_size)
-                                            .build();
-                                        tokio::spawn(receiver.start(self.reporters_handles.clone()));
+                                        match cql_conn.into() {
+                                            CqlStream::Tcp(stream) => {
+                                                let (socket_rx, socket_tx) = stream.into_split();
+                                                self.start_session(
+                                                    socket_rx,
+                                                    socket_tx,
+                                                    compression,
+                                                    compression_threshold,
+                                                    segment_codec,
+                                                );
+                                            }
+                                            CqlStream::Tls(stream) => {
+                                                let (socket_rx, socket_tx) = tokio::io::split(stream);
+                                                self.start_session(
+                                                    socket_rx,
+                                                    socket_tx,
+                                                    compression,
+                                                    compression_threshold,
+                                                    segment_codec,
+                                                );
+                                            }
+                                        }
                                     }
                                     Err(_) => {
                                         tokio::time::sleep(Duration::from_millis(5000)).await;
@@ -110,3 +122,41 @@ impl EventLoop<NodeHandle> for Stage {
         }
     }
 }
+
+impl Stage {
+    /// Spawn the sender and receiver of a new session over the split socket halves
+    fn start_session<R, W>(
+        &self,
+        socket_rx: R,
+        socket_tx: W,
+        compression: MyCompression,
+        compression_threshold: usize,
+        segment_codec: Option<SegmentCodec>,
+    ) where
+        R: 'static + AsyncRead + Unpin + Send,
+        W: 'static + AsyncWrite + Unpin + Send,
+    {
+        // spawn sender
+        let sender = SenderBuilder::new()
+            .socket(socket_tx)
+            .appends_num(self.appends_num)
+            .payloads(self.payloads.clone())
+            .compression(compression)
+            .compression_threshold(compression_threshold)
+            .compression_stats(self.compression_stats.clone())
+            .segment_codec(segment_codec)
+            .build();
+        tokio::spawn(sender.start(self.reporters_handles.clone()));
+        // spawn receiver
+        let receiver = ReceiverBuilder::new()
+            .socket(socket_rx)
+            .appends_num(self.appends_num)
+            .payloads(self.payloads.clone())
+            .session_id(self.session_id)
+            .buffer_size(self.buffer_size)
+            .compression(compression)
+            .segment_codec(segment_codec)
+            .build();
+        tokio::spawn(receiver.start(self.reporters_handles.clone()));
+    }
+}
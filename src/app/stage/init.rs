@@ -34,6 +34,9 @@ impl Init<NodeHandle> for Stage {
                             .address(self.address.clone())
                             .payloads(self.payloads.clone())
                             .streams(streams.to_owned().into_iter().collect())
+                            .request_timeout(self.request_timeout)
+                            .max_pending_requests(self.max_pending_requests)
+                            .pending_timeout(self.pending_timeout)
                             .build();
                         // clone reporter_handle
                         if let Some(reporter_handle) = reporter.clone_handle() {
@@ -7,7 +7,7 @@ use super::{
 };
 use receiver::ReceiverBuilder;
 use reporter::ReporterBuilder;
-pub use reporter::{ReporterEvent, ReporterHandle};
+pub use reporter::{ReporterEvent, ReporterHandle, DEFAULT_MAX_PENDING_REQUESTS};
 use sender::SenderBuilder;
 use std::{
     cell::UnsafeCell,
@@ -15,8 +15,8 @@ use std::{
     net::SocketAddr,
     ops::{Deref, DerefMut},
     sync::Arc,
+    time::Duration,
 };
-use tokio::net::TcpStream;
 
 mod event_loop;
 mod init;
@@ -25,12 +25,54 @@ mod reporter;
 mod sender;
 mod terminating;
 
-/// The reporters of shard id to its corresponding sender of stage reporter events.
+/// The reporters of shard id to its corresponding sender of stage reporter events, along with the compression
+/// counters of the stage.
 #[derive(Clone)]
-pub struct ReportersHandles(HashMap<u8, ReporterHandle>);
+pub struct ReportersHandles(HashMap<u8, ReporterHandle>, Arc<CompressionStats>);
+
+impl ReportersHandles {
+    /// Get the compression counters of the request frames written by the stage.
+    pub fn compression_stats(&self) -> &Arc<CompressionStats> {
+        &self.1
+    }
+    /// Get the number of requests which are queued by the reporters of the stage, waiting for a free stream.
+    pub fn pending_requests(&self) -> usize {
+        self.values()
+            .map(|reporter_handle| reporter_handle.pending_requests())
+            .sum()
+    }
+    /// Get the number of requests which were sent by the reporters of the stage and are waiting for responses.
+    pub fn in_flight_requests(&self) -> usize {
+        self.values()
+            .map(|reporter_handle| reporter_handle.in_flight_requests())
+            .sum()
+    }
+    /// Get the average latency of the reporters of the stage, if any of their requests was answered.
+    pub fn latency(&self) -> Option<Duration> {
+        let latencies: Vec<Duration> = self
+            .values()
+            .filter_map(|reporter_handle| reporter_handle.latency())
+            .collect();
+        if latencies.is_empty() {
+            None
+        } else {
+            Some(latencies.iter().sum::<Duration>() / latencies.len() as u32)
+        }
+    }
+}
 /// The thread-safe reusable payloads.
 pub type Payloads = Arc<Vec<Reusable>>;
 
+#[cfg(test)]
+impl ReportersHandles {
+    /// Create the handles of a single detached reporter with the given load.
+    pub(crate) fn detached(in_flight_requests: usize, latency: Option<Duration>) -> Self {
+        let mut reporters_handles = HashMap::new();
+        reporters_handles.insert(0, ReporterHandle::detached(in_flight_requests, latency));
+        Self(reporters_handles, Default::default())
+    }
+}
+
 impl Deref for ReportersHandles {
     type Target = HashMap<u8, ReporterHandle>;
     fn deref(&self) -> &Self::Target {
@@ -59,12 +101,19 @@ impl Shutdown for ReportersHandles {
 // Stage builder
 builder!(StageBuilder {
     address: SocketAddr,
-    authenticator: PasswordAuth,
+    authenticator: BoxedAuthenticator,
     reporter_count: u8,
     shard_id: u16,
     buffer_size: usize,
     recv_buffer_size: Option<u32>,
     send_buffer_size: Option<u32>,
+    tls: Option<TlsConfig>,
+    compression: Vec<MyCompression>,
+    compression_threshold: usize,
+    request_timeout: Option<Duration>,
+    max_pending_requests: usize,
+    pending_timeout: Option<Duration>,
+    protocol_version: u8,
     handle: StageHandle,
     inbox: StageInbox
 });
@@ -106,7 +155,7 @@ pub enum StageEvent {
 pub struct Stage {
     service: Service,
     address: SocketAddr,
-    authenticator: PasswordAuth,
+    authenticator: BoxedAuthenticator,
     appends_num: i16,
     reporter_count: u8,
     reporters_handles: Option<ReportersHandles>,
@@ -116,6 +165,14 @@ pub struct Stage {
     buffer_size: usize,
     recv_buffer_size: Option<u32>,
     send_buffer_size: Option<u32>,
+    tls: Option<TlsConfig>,
+    compression: Vec<MyCompression>,
+    compression_threshold: usize,
+    request_timeout: Option<Duration>,
+    max_pending_requests: usize,
+    pending_timeout: Option<Duration>,
+    compression_stats: Arc<CompressionStats>,
+    protocol_version: u8,
     handle: Option<StageHandle>,
     inbox: StageInbox,
 }
@@ -159,19 +216,32 @@ impl Builder for StageBuilder {
         let vector: Vec<Reusable> = Vec::new();
         let payloads: Payloads = Arc::new(vector);
         let reporter_count = self.reporter_count.unwrap();
+        // the counters are kept across the sessions of the stage
+        let compression_stats = Arc::new(CompressionStats::default());
         Self::State {
             service: Service::new(),
             address: self.address.unwrap(),
             authenticator: self.authenticator.unwrap(),
             appends_num: 32767 / (reporter_count as i16),
             reporter_count,
-            reporters_handles: Some(ReportersHandles(HashMap::with_capacity(reporter_count as usize))),
+            reporters_handles: Some(ReportersHandles(
+                HashMap::with_capacity(reporter_count as usize),
+                compression_stats.clone(),
+            )),
             session_id: 0,
             shard_id: self.shard_id.unwrap(),
             payloads,
             buffer_size: self.buffer_size.unwrap_or(1024000),
             recv_buffer_size: self.recv_buffer_size.unwrap(),
             send_buffer_size: self.send_buffer_size.unwrap(),
+            tls: self.tls.unwrap(),
+            compression: self.compression.unwrap(),
+            compression_threshold: self.compression_threshold.unwrap_or_default(),
+            request_timeout: self.request_timeout.unwrap_or_default(),
+            max_pending_requests: self.max_pending_requests.unwrap_or(DEFAULT_MAX_PENDING_REQUESTS),
+            pending_timeout: self.pending_timeout.unwrap_or_default(),
+            compression_stats,
+            protocol_version: self.protocol_version.unwrap(),
             handle,
             inbox,
         }
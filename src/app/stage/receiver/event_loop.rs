@@ -4,7 +4,7 @@
 use super::*;
 
 #[async_trait::async_trait]
-impl EventLoop<ReportersHandles> for Receiver {
+impl<R: 'static + AsyncRead + Unpin + Send> EventLoop<ReportersHandles> for Receiver<R> {
     async fn event_loop(
         &mut self,
         status: Result<(), Need>,
@@ -16,19 +16,14 @@ impl EventLoop<ReportersHandles> for Receiver {
                 let event = ReporterEvent::Session(Session::Service(self.service.clone()));
                 reporter_handle.send(event).ok();
             }
-            while let Ok(n) = self.socket.read(&mut self.buffer[self.i..]).await {
+            while let Ok(n) = self.socket.read_buf(&mut self.buffer).await {
                 if n != 0 {
-                    self.current_length += n;
-                    if self.current_length < CQL_FRAME_HEADER_BYTES_LENGTH {
-                        self.i = self.current_length;
-                    } else {
-                        self.handle_frame_header(0)
-                            .and_then(|_| self.handle_frame(n, 0, reporter_handles))
-                            .map_err(|e| {
-                                error!("{}", e);
-                                Need::Abort
-                            })?;
-                    }
+                    self.handle_frames(reporter_handles).map_err(|e| {
+                        error!("{}", e);
+                        Need::Abort
+                    })?;
+                    // make room for the next read, the codec reserves the rest of a partial frame
+                    self.buffer.reserve(self.buffer_size);
                 } else {
                     break;
                 }
@@ -40,82 +35,23 @@ impl EventLoop<ReportersHandles> for Receiver {
     }
 }
 
-impl Receiver {
-    fn handle_remaining_buffer(&mut self, i: usize, reporters_handles: &ReportersHandles) -> anyhow::Result<()> {
-        if self.current_length < CQL_FRAME_HEADER_BYTES_LENGTH {
-            self.buffer.copy_within(i..(i + self.current_length), self.i);
-            self.i = self.current_length;
-        } else {
-            self.handle_frame_header(i)
-                .and_then(|_| self.handle_frame(self.current_length, i, reporters_handles))?;
-        }
-        Ok(())
-    }
-    fn handle_frame_header(&mut self, padding: usize) -> anyhow::Result<()> {
-        // if no-header decode the header and resize the payload(if needed).
-        if !self.header {
-            // decode total_length(HEADER_LENGTH + frame_body_length)
-            let buf = &self.buffer[padding..];
-            self.total_length = get_total_length_usize(&buf);
-            // decode stream_id
-            self.stream_id = get_stream_id(&buf);
-            // get mut ref to payload for stream_id
-            let payload = self.payloads[self.stream_id as usize]
+impl<R: 'static + AsyncRead + Unpin + Send> Receiver<R> {
+    /// Decode the received frames (or v5 segments) and hand over every complete frame to its reporter.
+    fn handle_frames(&mut self, reporters_handles: &ReportersHandles) -> anyhow::Result<()> {
+        while let Some(decoder) = self.codec.decode(&mut self.buffer)? {
+            let stream_id = decoder.stream()?;
+            // the decoded frame replaces the payload
+            let payload = self.payloads[stream_id as usize]
                 .as_mut_payload()
-                .ok_or_else(|| anyhow!("No payload for stream {}!", self.stream_id))?;
-            // resize payload only if total_length is larger than the payload length
-            if self.total_length > payload.len() {
-                // resize the len of the payload.
-                payload.resize(self.total_length, 0);
-            }
-            // set header to true
-            self.header = true;
-        }
-        Ok(())
-    }
-    fn handle_frame(
-        &mut self,
-        n: usize,
-        mut padding: usize,
-        reporters_handles: &ReportersHandles,
-    ) -> anyhow::Result<()> {
-        let start = self.current_length - n - self.i;
-        if self.current_length >= self.total_length {
-            // get mut ref to payload for stream_id as giveload
-            let giveload = self.payloads[self.stream_id as usize]
-                .as_mut_payload()
-                .ok_or_else(|| anyhow!("No payload for stream {}!", self.stream_id))?;
-            // memcpy the current bytes from self.buffer into payload
-            let old_padding = padding;
-            // update padding
-            padding += self.total_length - start;
-            giveload[start..self.total_length].copy_from_slice(&self.buffer[old_padding..padding]);
+                .ok_or_else(|| anyhow!("No payload for stream {}!", stream_id))?;
+            *payload = decoder.into_buffer();
             // tell reporter that giveload is ready.
             let reporter_handle = reporters_handles
-                .get(&compute_reporter_num(self.stream_id, self.appends_num))
-                .ok_or_else(|| anyhow!("No reporter handle for stream {}!", self.stream_id))?;
-
+                .get(&compute_reporter_num(stream_id, self.appends_num))
+                .ok_or_else(|| anyhow!("No reporter handle for stream {}!", stream_id))?;
             reporter_handle
-                .send(ReporterEvent::Response {
-                    stream_id: self.stream_id,
-                })
+                .send(ReporterEvent::Response { stream_id })
                 .unwrap_or_else(|e| error!("{}", e));
-            // set header to false
-            self.header = false;
-            // update current_length
-            self.current_length -= self.total_length;
-            // set self.i to zero
-            self.i = 0;
-            self.handle_remaining_buffer(padding, reporters_handles)?;
-        } else {
-            // get mut ref to payload for stream_id
-            let payload = self.payloads[self.stream_id as usize]
-                .as_mut_payload()
-                .ok_or_else(|| anyhow!("No payload for stream {}!", self.stream_id))?;
-            // memcpy the current bytes from self.buffer into payload
-            payload[start..self.current_length].copy_from_slice(&self.buffer[padding..(padding + n + self.i)]);
-            // set self.i to zero
-            self.i = 0;
         }
         Ok(())
     }
@@ -4,7 +4,7 @@
 use super::*;
 
 #[async_trait::async_trait]
-impl Init<ReportersHandles> for Receiver {
+impl<R: 'static + AsyncRead + Unpin + Send> Init<ReportersHandles> for Receiver<R> {
     async fn init(&mut self, status: Result<(), Need>, _supervisor: &mut Option<ReportersHandles>) -> Result<(), Need> {
         status
     }
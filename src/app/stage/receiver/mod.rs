@@ -2,62 +2,64 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::{reporter::*, *};
+use crate::cql::{Frame, FrameCodec};
 use anyhow::anyhow;
-use tokio::{io::AsyncReadExt, net::tcp::OwnedReadHalf};
+use bytes::BytesMut;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::codec::Decoder as _;
 
 mod event_loop;
 mod init;
 mod terminating;
 
-const CQL_FRAME_HEADER_BYTES_LENGTH: usize = 9;
-
 // Receiver builder
-builder!(ReceiverBuilder {
-    socket: OwnedReadHalf,
+builder!(ReceiverBuilder<R> {
+    socket: R,
     session_id: usize,
     payloads: Payloads,
     buffer_size: usize,
-    appends_num: i16
+    appends_num: i16,
+    compression: MyCompression,
+    segment_codec: Option<SegmentCodec>
 });
 
-/// Receiver state
-pub struct Receiver {
+/// Receiver state, which reads the frames from the read half of the socket
+pub struct Receiver<R> {
     service: Service,
-    socket: OwnedReadHalf,
-    stream_id: i16,
-    total_length: usize,
-    current_length: usize,
-    header: bool,
-    buffer: Vec<u8>,
-    i: usize,
+    socket: R,
+    buffer: BytesMut,
+    buffer_size: usize,
     appends_num: i16,
     payloads: Payloads,
+    codec: FrameCodec,
 }
 
-impl ActorBuilder<ReportersHandles> for ReceiverBuilder {}
+impl<R: 'static + AsyncRead + Unpin + Send> ActorBuilder<ReportersHandles> for ReceiverBuilder<R> {}
 
 /// implementation of builder
-impl Builder for ReceiverBuilder {
-    type State = Receiver;
+impl<R: 'static + AsyncRead + Unpin + Send> Builder for ReceiverBuilder<R> {
+    type State = Receiver<R>;
     fn build(self) -> Self::State {
+        let buffer_size = self.buffer_size.unwrap();
+        let codec = FrameCodec::new(self.compression.unwrap_or_default());
         Self::State {
             service: Service::new(),
             socket: self.socket.unwrap(),
-            stream_id: 0,
-            total_length: 0,
-            current_length: 0,
-            header: false,
-            buffer: vec![0; self.buffer_size.unwrap()],
-            i: 0,
+            buffer: BytesMut::with_capacity(buffer_size),
+            buffer_size,
             appends_num: self.appends_num.unwrap(),
             payloads: self.payloads.unwrap(),
+            codec: match self.segment_codec.unwrap() {
+                Some(segment_codec) => codec.with_segments(segment_codec),
+                None => codec,
+            },
         }
         .set_name()
     }
 }
 
 /// impl name of the Receiver
-impl Name for Receiver {
+impl<R: 'static + AsyncRead + Unpin + Send> Name for Receiver<R> {
     fn set_name(mut self) -> Self {
         let name = String::from("Receiver");
         self.service.update_name(name);
@@ -69,8 +71,8 @@ impl Name for Receiver {
 }
 
 #[async_trait::async_trait]
-impl AknShutdown<Receiver> for ReportersHandles {
-    async fn aknowledge_shutdown(self, mut _state: Receiver, _status: Result<(), Need>) {
+impl<R: 'static + AsyncRead + Unpin + Send> AknShutdown<Receiver<R>> for ReportersHandles {
+    async fn aknowledge_shutdown(self, mut _state: Receiver<R>, _status: Result<(), Need>) {
         _state.service.update_status(ServiceStatus::Stopped);
         for reporter_handle in self.values() {
             let event = ReporterEvent::Session(Session::Service(_state.service.clone()));
@@ -78,16 +80,3 @@ impl AknShutdown<Receiver> for ReportersHandles {
         }
     }
 }
-
-fn get_total_length_usize(buffer: &[u8]) -> usize {
-    CQL_FRAME_HEADER_BYTES_LENGTH +
-    // plus body length
-    ((buffer[5] as usize) << 24) +
-    ((buffer[6] as usize) << 16) +
-    ((buffer[7] as usize) <<  8) +
-    (buffer[8] as usize)
-}
-
-fn get_stream_id(buffer: &[u8]) -> i16 {
-    ((buffer[2] as i16) << 8) | buffer[3] as i16
-}
@@ -4,7 +4,7 @@
 use super::*;
 
 #[async_trait::async_trait]
-impl Terminating<ReportersHandles> for Receiver {
+impl<R: 'static + AsyncRead + Unpin + Send> Terminating<ReportersHandles> for Receiver<R> {
     async fn terminating(
         &mut self,
         _status: Result<(), Need>,
@@ -11,42 +11,19 @@ impl EventLoop<StageHandle> for Reporter {
         supervisor: &mut Option<StageHandle>,
     ) -> Result<(), Need> {
         if let Some(supervisor) = supervisor.as_ref() {
-            while let Some(event) = self.inbox.rx.recv().await {
+            while let Some(event) = self.next_event().await {
                 match event {
-                    ReporterEvent::Request { worker, mut payload } => {
-                        if let Some(stream) = self.streams.iter().next().cloned() {
-                            // Send the event
-                            match &self.sender_handle {
-                                Some(sender) => {
-                                    self.streams.remove(&stream);
-                                    // Assign stream_id to the payload
-                                    assign_stream_to_payload(stream, &mut payload);
-                                    // store payload as reusable at payloads[stream]
-                                    self.payloads[stream as usize].as_mut().replace(payload);
-                                    self.workers.insert(stream, worker);
-                                    sender.send(stream).unwrap_or_else(|e| error!("{}", e));
-                                }
-                                None => {
-                                    // This means the sender_tx had been droped as a result of checkpoint from
-                                    // receiver
-                                    worker
-                                        .handle_error(WorkerError::Other(anyhow!("No Sender!")), &self.handle)
-                                        .unwrap_or_else(|e| error!("{}", e));
-                                }
-                            }
-                        } else {
-                            // Send overload to the worker in-case we don't have anymore streams
-                            worker
-                                .handle_error(WorkerError::Overload, &self.handle)
-                                .unwrap_or_else(|e| error!("{}", e));
-                        }
+                    ReporterEvent::Request { worker, payload } => {
+                        self.handle_request(worker, payload);
                     }
                     ReporterEvent::Response { stream_id } => {
                         self.handle_response(stream_id).unwrap_or_else(|e| error!("{}", e));
+                        self.drain_pending();
                     }
                     ReporterEvent::Err(io_error, stream_id) => {
                         self.handle_error(stream_id, WorkerError::Other(io_error))
                             .unwrap_or_else(|e| error!("{}", e));
+                        self.drain_pending();
                     }
                     ReporterEvent::Session(session) => {
                         match session {
@@ -128,11 +105,177 @@ impl EventLoop<StageHandle> for Reporter {
 }
 
 impl Reporter {
+    /// Receive the next event, meanwhile the requests are expired once their deadlines pass.
+    ///
+    /// The deadlines are checked before waiting and once an event is received, as under steady traffic the
+    /// events keep arriving before the earliest deadline.
+    async fn next_event(&mut self) -> Option<ReporterEvent> {
+        loop {
+            self.expire();
+            if let Some(deadline) = self.next_deadline() {
+                if let Ok(event) = tokio::time::timeout_at(deadline.into(), self.inbox.rx.recv()).await {
+                    self.expire();
+                    return event;
+                }
+            } else {
+                let event = self.inbox.rx.recv().await;
+                self.expire();
+                return event;
+            }
+        }
+    }
+    /// Get the earliest deadline of the in-flight and queued requests.
+    fn next_deadline(&self) -> Option<Instant> {
+        let request_deadline = self.deadlines.iter().next().map(|&(deadline, _)| deadline);
+        // the queue is ordered by arrival, so its front request is the first one to expire
+        let pending_deadline = self
+            .pending_timeout
+            .and_then(|timeout| self.pending.front().map(|request| request.queued_at + timeout));
+        request_deadline.into_iter().chain(pending_deadline).min()
+    }
+    /// Expire the in-flight and queued requests, if the earliest deadline passed.
+    fn expire(&mut self) {
+        if self.next_deadline().is_some_and(|deadline| deadline <= Instant::now()) {
+            self.expire_requests();
+            self.expire_pending();
+        }
+    }
+    fn handle_request(&mut self, worker: Box<dyn Worker>, payload: Vec<u8>) {
+        if let Some(stream) = self.streams.iter().next().cloned() {
+            // Send the event
+            if self.sender_handle.is_some() {
+                self.send_request(stream, worker, payload);
+            } else {
+                // This means the sender_tx had been droped as a result of checkpoint from
+                // receiver
+                worker
+                    .handle_error(WorkerError::Other(anyhow!("No Sender!")), &self.handle)
+                    .unwrap_or_else(|e| error!("{}", e));
+            }
+        } else if self.pending.len() < self.max_pending_requests {
+            // Queue the request until a stream is freed up
+            self.pending.push_back(PendingRequest {
+                worker,
+                payload,
+                queued_at: Instant::now(),
+            });
+            self.stats.pending_requests.store(self.pending.len(), Ordering::Relaxed);
+        } else {
+            // Send overload to the worker in-case we don't have anymore streams and the queue is full
+            worker
+                .handle_error(WorkerError::Overload, &self.handle)
+                .unwrap_or_else(|e| error!("{}", e));
+        }
+    }
+    fn send_request(&mut self, stream: i16, worker: Box<dyn Worker>, mut payload: Vec<u8>) {
+        if let Some(sender) = self.sender_handle.as_ref() {
+            self.streams.remove(&stream);
+            // Assign stream_id to the payload
+            assign_stream_to_payload(stream, &mut payload);
+            // store payload as reusable at payloads[stream]
+            self.payloads[stream as usize].as_mut().replace(payload);
+            let deadline = worker
+                .timeout()
+                .or(self.request_timeout)
+                .map(|timeout| Instant::now() + timeout);
+            if let Some(deadline) = deadline {
+                self.deadlines.insert((deadline, stream));
+            }
+            self.workers.insert(
+                stream,
+                InFlightRequest {
+                    worker,
+                    deadline,
+                    sent_at: Instant::now(),
+                },
+            );
+            self.stats
+                .in_flight_requests
+                .store(self.workers.len(), Ordering::Relaxed);
+            sender.send(stream).unwrap_or_else(|e| error!("{}", e));
+        }
+    }
+    /// Send the queued requests as long as there are free streams.
+    fn drain_pending(&mut self) {
+        self.expire_pending();
+        while self.sender_handle.is_some() {
+            if let Some(stream) = self.streams.iter().next().cloned() {
+                if let Some(PendingRequest { worker, payload, .. }) = self.pending.pop_front() {
+                    self.send_request(stream, worker, payload);
+                    continue;
+                }
+            }
+            break;
+        }
+        self.stats.pending_requests.store(self.pending.len(), Ordering::Relaxed);
+    }
+    /// Send overload to the queued requests which waited longer than the pending timeout.
+    fn expire_pending(&mut self) {
+        if let Some(timeout) = self.pending_timeout {
+            let now = Instant::now();
+            while let Some(request) = self.pending.front() {
+                if request.queued_at + timeout > now {
+                    break;
+                }
+                if let Some(PendingRequest { worker, .. }) = self.pending.pop_front() {
+                    worker
+                        .handle_error(WorkerError::Overload, &self.handle)
+                        .unwrap_or_else(|e| error!("{}", e));
+                }
+            }
+            self.stats.pending_requests.store(self.pending.len(), Ordering::Relaxed);
+        }
+    }
+    fn expire_requests(&mut self) {
+        let now = Instant::now();
+        while let Some(&(deadline, stream)) = self.deadlines.iter().next() {
+            if deadline > now {
+                break;
+            }
+            self.deadlines.remove(&(deadline, stream));
+            if let Some(InFlightRequest { worker, sent_at, .. }) = self.workers.remove(&stream) {
+                self.stats
+                    .in_flight_requests
+                    .store(self.workers.len(), Ordering::Relaxed);
+                // the stalled replica is penalized by the latency of the expired request
+                self.record_latency(now - sent_at);
+                // quarantine the stream_id, otherwise the late response would be handled by the next request.
+                self.quarantine.insert(stream);
+                worker
+                    .handle_error(WorkerError::Timeout, &self.handle)
+                    .unwrap_or_else(|e| error!("{}", e));
+            }
+        }
+    }
+    /// Release the stream_id if it's quarantined, dropping its late response.
+    fn release_quarantined(&mut self, stream: i16) -> bool {
+        if self.quarantine.remove(&stream) {
+            self.payloads[stream as usize].as_mut().take();
+            self.streams.insert(stream);
+            true
+        } else {
+            false
+        }
+    }
+    fn remove_worker(&mut self, stream: i16) -> Option<InFlightRequest> {
+        let request = self.workers.remove(&stream)?;
+        self.stats
+            .in_flight_requests
+            .store(self.workers.len(), Ordering::Relaxed);
+        if let Some(deadline) = request.deadline {
+            self.deadlines.remove(&(deadline, stream));
+        }
+        Some(request)
+    }
     fn handle_response(&mut self, stream: i16) -> anyhow::Result<()> {
+        if self.release_quarantined(stream) {
+            return Ok(());
+        }
         // push the stream_id back to streams vector.
         self.streams.insert(stream);
         // remove the worker from workers.
-        if let Some(worker) = self.workers.remove(&stream) {
+        if let Some(InFlightRequest { worker, sent_at, .. }) = self.remove_worker(stream) {
+            self.record_latency(sent_at.elapsed());
             if let Some(payload) = self.payloads[stream as usize].as_mut().take() {
                 if is_cql_error(&payload) {
                     let error = Decoder::try_from(payload)
@@ -140,6 +283,8 @@ impl Reporter {
                         .unwrap_or_else(|e| WorkerError::Other(e));
                     worker.handle_error(error, &self.handle)?;
                 } else {
+                    // route the requests of the prepared statements by their bound partition key values
+                    cache_prepared(&payload);
                     worker.handle_response(payload)?;
                 }
             } else {
@@ -151,10 +296,13 @@ impl Reporter {
         Ok(())
     }
     fn handle_error(&mut self, stream: i16, error: WorkerError) -> anyhow::Result<()> {
+        if self.release_quarantined(stream) {
+            return Ok(());
+        }
         // push the stream_id back to streams vector.
         self.streams.insert(stream);
         // remove the worker from workers and send error.
-        if let Some(worker) = self.workers.remove(&stream) {
+        if let Some(InFlightRequest { worker, .. }) = self.remove_worker(stream) {
             // drop payload.
             if let Some(_payload) = self.payloads[stream as usize].as_mut().take() {
                 worker.handle_error(error, &self.handle)?;
@@ -167,3 +315,195 @@ impl Reporter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    type Outcomes = mpsc::UnboundedReceiver<(usize, Option<String>)>;
+
+    /// A worker which reports the outcome of its request, the error message if it failed.
+    struct TestWorker {
+        id: usize,
+        outcomes: mpsc::UnboundedSender<(usize, Option<String>)>,
+    }
+
+    impl Worker for TestWorker {
+        fn handle_response(self: Box<Self>, _giveload: Vec<u8>) -> anyhow::Result<()> {
+            self.outcomes.send((self.id, None)).ok();
+            Ok(())
+        }
+        fn handle_error(self: Box<Self>, error: WorkerError, _reporter: &Option<ReporterHandle>) -> anyhow::Result<()> {
+            self.outcomes.send((self.id, Some(error.to_string()))).ok();
+            Ok(())
+        }
+    }
+
+    struct TestReporter {
+        reporter: Reporter,
+        outcomes: Outcomes,
+        workers: mpsc::UnboundedSender<(usize, Option<String>)>,
+        _sender_inbox: mpsc::UnboundedReceiver<i16>,
+    }
+
+    impl TestReporter {
+        fn new(
+            streams: i16,
+            request_timeout: Option<Duration>,
+            max_pending_requests: usize,
+            pending_timeout: Option<Duration>,
+        ) -> Self {
+            let payloads: Payloads = Arc::new((0..streams).map(|_| Reusable::default()).collect());
+            let mut reporter = ReporterBuilder::new()
+                .session_id(0)
+                .reporter_id(0)
+                .shard_id(0)
+                .streams((0..streams).collect())
+                .address(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042))
+                .payloads(payloads)
+                .request_timeout(request_timeout)
+                .max_pending_requests(max_pending_requests)
+                .pending_timeout(pending_timeout)
+                .build();
+            let (sender_handle, sender_inbox) = SenderHandle::detached();
+            reporter.sender_handle = Some(sender_handle);
+            let (workers, outcomes) = mpsc::unbounded_channel();
+            Self {
+                reporter,
+                outcomes,
+                workers,
+                _sender_inbox: sender_inbox,
+            }
+        }
+
+        fn worker(&self, id: usize) -> Box<dyn Worker> {
+            Box::new(TestWorker {
+                id,
+                outcomes: self.workers.clone(),
+            })
+        }
+
+        fn outcome(&mut self) -> Option<(usize, Option<String>)> {
+            self.outcomes.try_recv().ok()
+        }
+
+        /// Send or queue the request of the worker.
+        fn request(&mut self, id: usize) {
+            let worker = self.worker(id);
+            self.reporter.handle_request(worker, vec![0; 9]);
+        }
+
+        /// Answer the request of the stream.
+        fn respond(&mut self, stream: i16) {
+            self.reporter.payloads[stream as usize]
+                .as_mut()
+                .replace(vec![4, 0, 0, 0, 8, 0, 0, 0, 0]);
+            self.reporter.handle_response(stream).unwrap();
+            self.reporter.drain_pending();
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_request_quarantines_its_stream() {
+        let mut test = TestReporter::new(1, Some(Duration::from_millis(10)), 0, None);
+        test.request(0);
+        assert_eq!(test.reporter.workers.len(), 1);
+        assert!(test.reporter.streams.is_empty());
+        std::thread::sleep(Duration::from_millis(20));
+        test.reporter.expire_requests();
+        assert_eq!(test.outcome(), Some((0, Some(WorkerError::Timeout.to_string()))));
+        // the stream is held until the late response arrives
+        assert!(test.reporter.workers.is_empty());
+        assert!(test.reporter.streams.is_empty());
+        assert!(test.reporter.quarantine.contains(&0));
+        assert_eq!(test.reporter.handle.as_ref().unwrap().in_flight_requests(), 0);
+    }
+
+    #[tokio::test]
+    async fn late_response_is_dropped() {
+        let mut test = TestReporter::new(1, Some(Duration::from_millis(10)), 0, None);
+        test.request(0);
+        std::thread::sleep(Duration::from_millis(20));
+        test.reporter.expire_requests();
+        assert!(test.outcome().is_some());
+        test.respond(0);
+        // the late response isn't passed to any worker and the stream is released
+        assert_eq!(test.outcome(), None);
+        assert!(test.reporter.quarantine.is_empty());
+        assert!(test.reporter.streams.contains(&0));
+        assert!(test.reporter.payloads[0].as_ref_payload().is_none());
+        // the stream is reused by the next request, which gets its own response
+        test.request(1);
+        test.respond(0);
+        assert_eq!(test.outcome(), Some((1, None)));
+    }
+
+    #[tokio::test]
+    async fn requests_expire_under_steady_traffic() {
+        let mut test = TestReporter::new(2, Some(Duration::from_millis(10)), 0, None);
+        test.request(0);
+        let stream = *test.reporter.workers.keys().next().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        // an event is ready before the deadline is checked
+        let reporter_handle = test.reporter.handle.clone().unwrap();
+        reporter_handle
+            .send(ReporterEvent::Response { stream_id: 1 - stream })
+            .ok();
+        assert!(matches!(
+            test.reporter.next_event().await,
+            Some(ReporterEvent::Response { stream_id }) if stream_id == 1 - stream
+        ));
+        assert_eq!(test.outcome(), Some((0, Some(WorkerError::Timeout.to_string()))));
+        assert!(test.reporter.quarantine.contains(&stream));
+    }
+
+    #[tokio::test]
+    async fn requests_are_queued_once_the_streams_are_exhausted() {
+        let mut test = TestReporter::new(1, None, 2, None);
+        for id in 0..4 {
+            test.request(id);
+        }
+        // the request which doesn't fit in the queue is overloaded
+        assert_eq!(test.outcome(), Some((3, Some(WorkerError::Overload.to_string()))));
+        assert_eq!(test.outcome(), None);
+        assert_eq!(test.reporter.workers.len(), 1);
+        assert_eq!(test.reporter.pending.len(), 2);
+        assert_eq!(test.reporter.handle.as_ref().unwrap().pending_requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn pending_requests_are_sent_in_order() {
+        let mut test = TestReporter::new(1, None, DEFAULT_MAX_PENDING_REQUESTS, None);
+        for id in 0..3 {
+            test.request(id);
+        }
+        for id in 0..3 {
+            // the freed stream is taken by the next queued request
+            test.respond(0);
+            assert_eq!(test.outcome(), Some((id, None)));
+        }
+        assert!(test.reporter.pending.is_empty());
+        assert!(test.reporter.workers.is_empty());
+        assert_eq!(test.reporter.handle.as_ref().unwrap().pending_requests(), 0);
+    }
+
+    #[tokio::test]
+    async fn expired_pending_requests_are_overloaded() {
+        let mut test = TestReporter::new(1, None, 2, Some(Duration::from_millis(10)));
+        test.request(0);
+        test.request(1);
+        std::thread::sleep(Duration::from_millis(20));
+        test.request(2);
+        test.reporter.expire_pending();
+        // only the request which waited longer than the pending timeout expires
+        assert_eq!(test.outcome(), Some((1, Some(WorkerError::Overload.to_string()))));
+        assert_eq!(test.outcome(), None);
+        assert_eq!(test.reporter.pending.len(), 1);
+        assert_eq!(test.reporter.handle.as_ref().unwrap().pending_requests(), 1);
+        test.respond(0);
+        assert_eq!(test.outcome(), Some((0, None)));
+        assert_eq!(test.reporter.workers.len(), 1);
+        assert!(test.reporter.pending.is_empty());
+    }
+}
@@ -3,23 +3,48 @@
 
 use super::*;
 use crate::{
-    app::worker::{Worker, WorkerError},
+    app::{
+        access::cache_prepared,
+        worker::{Worker, WorkerError},
+    },
     cql::{CqlError, Decoder},
 };
 use anyhow::anyhow;
 use sender::SenderHandle;
 use std::{
-    collections::HashSet,
+    collections::{BTreeSet, HashSet, VecDeque},
     convert::TryFrom,
     ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
 };
 
 mod event_loop;
 mod init;
 mod terminating;
 
-/// Workers Map holds all the workers_ids
-type Workers = HashMap<i16, Box<dyn Worker>>;
+/// Workers Map holds all the workers_ids along with their in-flight requests
+type Workers = HashMap<i16, InFlightRequest>;
+
+/// The default bound of the queue of the requests which wait for a free stream
+pub const DEFAULT_MAX_PENDING_REQUESTS: usize = 1024;
+
+/// The weight of the latest latency in the latency moving average of the reporter
+const LATENCY_EWMA_WEIGHT: u64 = 8;
+
+/// A request which was sent, along with its deadline and the instant it was sent at
+struct InFlightRequest {
+    worker: Box<dyn Worker>,
+    deadline: Option<Instant>,
+    sent_at: Instant,
+}
+
+/// A request which waits for a free stream, along with the instant it was queued at
+struct PendingRequest {
+    worker: Box<dyn Worker>,
+    payload: Vec<u8>,
+    queued_at: Instant,
+}
 
 // Reporter builder
 builder!(ReporterBuilder {
@@ -28,13 +53,62 @@ builder!(ReporterBuilder {
     shard_id: u16,
     streams: HashSet<i16>,
     address: SocketAddr,
-    payloads: Payloads
+    payloads: Payloads,
+    request_timeout: Option<Duration>,
+    max_pending_requests: usize,
+    pending_timeout: Option<Duration>
 });
 
 /// ReporterHandle to be passed to the children (Stage)
 #[derive(Clone)]
 pub struct ReporterHandle {
     tx: mpsc::UnboundedSender<ReporterEvent>,
+    stats: Arc<ReporterStats>,
+}
+
+/// The load counters of a reporter, which are shared with its handles
+#[derive(Default)]
+struct ReporterStats {
+    pending_requests: AtomicUsize,
+    in_flight_requests: AtomicUsize,
+    /// The moving average of the latency in nanoseconds, zero until the first response
+    latency: AtomicU64,
+}
+
+impl ReporterHandle {
+    /// Get the number of requests which are queued by the reporter, waiting for a free stream.
+    pub fn pending_requests(&self) -> usize {
+        self.stats.pending_requests.load(Ordering::Relaxed)
+    }
+    /// Get the number of requests which were sent by the reporter and are waiting for their responses.
+    pub fn in_flight_requests(&self) -> usize {
+        self.stats.in_flight_requests.load(Ordering::Relaxed)
+    }
+    /// Get the moving average of the latency of the reporter requests, if any of them was answered.
+    pub fn latency(&self) -> Option<Duration> {
+        match self.stats.latency.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+}
+
+#[cfg(test)]
+impl ReporterHandle {
+    /// Create a handle of a detached reporter with the given load.
+    pub(crate) fn detached(in_flight_requests: usize, latency: Option<Duration>) -> Self {
+        let (tx, _) = mpsc::unbounded_channel::<ReporterEvent>();
+        let stats = ReporterStats::default();
+        stats.in_flight_requests.store(in_flight_requests, Ordering::Relaxed);
+        stats.latency.store(
+            latency.map_or(0, |latency| latency.as_nanos() as u64),
+            Ordering::Relaxed,
+        );
+        Self {
+            tx,
+            stats: Arc::new(stats),
+        }
+    }
 }
 /// NodeInbox is used to recv events
 pub struct ReporterInbox {
@@ -90,6 +164,16 @@ pub struct Reporter {
     streams: HashSet<i16>,
     shard_id: u16,
     workers: Workers,
+    request_timeout: Option<Duration>,
+    /// The deadlines of the in-flight requests, ordered by expiry
+    deadlines: BTreeSet<(Instant, i16)>,
+    /// The streams of the expired requests, which are held until their late responses arrive
+    quarantine: HashSet<i16>,
+    /// The requests which wait for a free stream, in the order they arrived
+    pending: VecDeque<PendingRequest>,
+    max_pending_requests: usize,
+    pending_timeout: Option<Duration>,
+    stats: Arc<ReporterStats>,
     sender_handle: Option<SenderHandle>,
     payloads: Payloads,
     handle: Option<ReporterHandle>,
@@ -109,7 +193,11 @@ impl Builder for ReporterBuilder {
     type State = Reporter;
     fn build(self) -> Self::State {
         let (tx, rx) = mpsc::unbounded_channel::<ReporterEvent>();
-        let handle = Some(ReporterHandle { tx });
+        let stats = Arc::new(ReporterStats::default());
+        let handle = Some(ReporterHandle {
+            tx,
+            stats: stats.clone(),
+        });
         let inbox = ReporterInbox { rx };
 
         Self::State {
@@ -120,6 +208,13 @@ impl Builder for ReporterBuilder {
             streams: self.streams.unwrap(),
             shard_id: self.shard_id.unwrap(),
             workers: HashMap::new(),
+            request_timeout: self.request_timeout.unwrap_or_default(),
+            deadlines: BTreeSet::new(),
+            quarantine: HashSet::new(),
+            pending: VecDeque::new(),
+            max_pending_requests: self.max_pending_requests.unwrap_or(DEFAULT_MAX_PENDING_REQUESTS),
+            pending_timeout: self.pending_timeout.unwrap_or_default(),
+            stats,
             sender_handle: None,
             payloads: self.payloads.unwrap(),
             handle,
@@ -153,7 +248,21 @@ impl AknShutdown<Reporter> for StageHandle {
 
 impl Reporter {
     fn force_consistency(&mut self) {
-        for (stream_id, worker_id) in self.workers.drain() {
+        self.deadlines.clear();
+        // the queued requests weren't sent, so they can be safely retried
+        for PendingRequest { worker, .. } in self.pending.drain(..) {
+            worker
+                .handle_error(WorkerError::Overload, &self.handle)
+                .unwrap_or_else(|e| error!("{}", e));
+        }
+        self.stats.pending_requests.store(0, Ordering::Relaxed);
+        // the late responses of the quarantined streams will never arrive
+        for stream_id in self.quarantine.drain() {
+            self.payloads[stream_id as usize].as_mut().take();
+            self.streams.insert(stream_id);
+        }
+        self.stats.in_flight_requests.store(0, Ordering::Relaxed);
+        for (stream_id, InFlightRequest { worker: worker_id, .. }) in self.workers.drain() {
             // push the stream_id back into the streams vector
             self.streams.insert(stream_id);
             // tell worker_id that we lost the response for his request, because we lost scylla connection in
@@ -165,6 +274,18 @@ impl Reporter {
     }
 }
 
+impl Reporter {
+    fn record_latency(&self, latency: Duration) {
+        let latency = latency.as_nanos() as u64;
+        let average = match self.stats.latency.load(Ordering::Relaxed) {
+            0 => latency,
+            average => (average * (LATENCY_EWMA_WEIGHT - 1) + latency) / LATENCY_EWMA_WEIGHT,
+        };
+        // the average is only written by the reporter, zero is reserved for the missing average
+        self.stats.latency.store(average.max(1), Ordering::Relaxed);
+    }
+}
+
 pub fn compute_reporter_num(stream_id: i16, appends_num: i16) -> u8 {
     (stream_id / appends_num) as u8
 }
@@ -4,7 +4,7 @@
 use super::*;
 
 #[async_trait::async_trait]
-impl EventLoop<ReportersHandles> for Sender {
+impl<W: 'static + AsyncWrite + Unpin + Send> EventLoop<ReportersHandles> for Sender<W> {
     async fn event_loop(
         &mut self,
         _status: Result<(), Need>,
@@ -19,13 +19,43 @@ impl EventLoop<ReportersHandles> for Sender {
             while let Some(stream_id) = self.inbox.rx.recv().await {
                 // write the payload to the socket, make sure the result is valid
                 if let Some(payload) = self.payloads[stream_id as usize].as_ref_payload() {
-                    if let Err(io_error) = self.socket.write_all(payload).await {
-                        // send to reporter ReporterEvent::Err(io_error, stream_id)
+                    let result = match self.segment_codec.as_ref() {
+                        // v5 frames are carried in segments
+                        Some(segment_codec) => {
+                            match upgrade_request(payload).and_then(|frame| segment_codec.encode_new(&frame)) {
+                                Ok(segments) => {
+                                    self.compression_stats.record(payload.len(), segments.len());
+                                    self.socket.write_all(&segments).await.map_err(|e| anyhow!(e))
+                                }
+                                Err(e) => Err(e),
+                            }
+                        }
+                        // v4 frames are compressed with the compression negotiated by the connection, the frames
+                        // which are sent uncompressed are written without copying them
+                        None if self.compression.compresses(payload, self.compression_threshold) => {
+                            match self
+                                .compression
+                                .compress_frame(payload.clone(), self.compression_threshold)
+                            {
+                                Ok(frame) => {
+                                    self.compression_stats.record(payload.len(), frame.len());
+                                    self.socket.write_all(&frame).await.map_err(|e| anyhow!(e))
+                                }
+                                Err(e) => Err(e),
+                            }
+                        }
+                        None => {
+                            self.compression_stats.record(payload.len(), payload.len());
+                            self.socket.write_all(payload).await.map_err(|e| anyhow!(e))
+                        }
+                    };
+                    if let Err(error) = result {
+                        // send to reporter ReporterEvent::Err(error, stream_id)
                         if let Some(reporter_handle) =
                             reporter_handles.get(&compute_reporter_num(stream_id, self.appends_num))
                         {
                             reporter_handle
-                                .send(ReporterEvent::Err(anyhow!(io_error), stream_id))
+                                .send(ReporterEvent::Err(error, stream_id))
                                 .unwrap_or_else(|e| error!("{}", e))
                         } else {
                             error!("No reporter found for stream {}!", stream_id);
@@ -4,7 +4,7 @@
 use super::*;
 
 #[async_trait::async_trait]
-impl Init<ReportersHandles> for Sender {
+impl<W: 'static + AsyncWrite + Unpin + Send> Init<ReportersHandles> for Sender<W> {
     async fn init(&mut self, status: Result<(), Need>, supervisor: &mut Option<ReportersHandles>) -> Result<(), Need> {
         self.service.update_status(ServiceStatus::Initializing);
         if let (Some(my_handle), Some(reporter_handles)) = (self.handle.take(), supervisor.as_ref()) {
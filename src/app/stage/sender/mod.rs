@@ -3,17 +3,21 @@
 
 use super::{reporter::*, *};
 use anyhow::anyhow;
-use tokio::{io::AsyncWriteExt, net::tcp::OwnedWriteHalf};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 mod event_loop;
 mod init;
 mod terminating;
 
 // Sender builder
-builder!(SenderBuilder {
-    socket: OwnedWriteHalf,
+builder!(SenderBuilder<W> {
+    socket: W,
     payloads: Payloads,
-    appends_num: i16
+    appends_num: i16,
+    compression: MyCompression,
+    compression_threshold: usize,
+    compression_stats: Arc<CompressionStats>,
+    segment_codec: Option<SegmentCodec>
 });
 
 /// SenderHandle to be passed to the supervisor (reporters)
@@ -34,6 +38,15 @@ impl Deref for SenderHandle {
     }
 }
 
+#[cfg(test)]
+impl SenderHandle {
+    /// Create a handle of a detached sender, along with the inbox which receives its streams.
+    pub(crate) fn detached() -> (Self, mpsc::UnboundedReceiver<i16>) {
+        let (tx, rx) = mpsc::unbounded_channel::<SenderEvent>();
+        (Self { tx }, rx)
+    }
+}
+
 impl DerefMut for SenderHandle {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.tx
@@ -43,21 +56,25 @@ impl DerefMut for SenderHandle {
 /// Sender event type.
 type SenderEvent = i16;
 
-/// Sender state
-pub struct Sender {
+/// Sender state, which writes the payloads to the write half of the socket
+pub struct Sender<W> {
     service: Service,
-    socket: OwnedWriteHalf,
+    socket: W,
     handle: Option<SenderHandle>,
     inbox: SenderInbox,
     payloads: Payloads,
     appends_num: i16,
+    compression: MyCompression,
+    compression_threshold: usize,
+    compression_stats: Arc<CompressionStats>,
+    segment_codec: Option<SegmentCodec>,
 }
 
-impl ActorBuilder<ReportersHandles> for SenderBuilder {}
+impl<W: 'static + AsyncWrite + Unpin + Send> ActorBuilder<ReportersHandles> for SenderBuilder<W> {}
 
 /// implementation of builder
-impl Builder for SenderBuilder {
-    type State = Sender;
+impl<W: 'static + AsyncWrite + Unpin + Send> Builder for SenderBuilder<W> {
+    type State = Sender<W>;
     fn build(self) -> Self::State {
         let (tx, rx) = mpsc::unbounded_channel::<SenderEvent>();
         let handle = Some(SenderHandle { tx });
@@ -68,6 +85,10 @@ impl Builder for SenderBuilder {
             payloads: self.payloads.unwrap(),
             socket: self.socket.unwrap(),
             appends_num: self.appends_num.unwrap(),
+            compression: self.compression.unwrap_or_default(),
+            compression_threshold: self.compression_threshold.unwrap_or_default(),
+            compression_stats: self.compression_stats.unwrap_or_default(),
+            segment_codec: self.segment_codec.unwrap(),
             handle,
             inbox,
         }
@@ -76,7 +97,7 @@ impl Builder for SenderBuilder {
 }
 
 /// impl name of the Sender
-impl Name for Sender {
+impl<W: 'static + AsyncWrite + Unpin + Send> Name for Sender<W> {
     fn set_name(mut self) -> Self {
         let name = String::from("Sender");
         self.service.update_name(name);
@@ -88,8 +109,8 @@ impl Name for Sender {
 }
 
 #[async_trait::async_trait]
-impl AknShutdown<Sender> for ReportersHandles {
-    async fn aknowledge_shutdown(self, mut _state: Sender, _status: Result<(), Need>) {
+impl<W: 'static + AsyncWrite + Unpin + Send> AknShutdown<Sender<W>> for ReportersHandles {
+    async fn aknowledge_shutdown(self, mut _state: Sender<W>, _status: Result<(), Need>) {
         _state.service.update_status(ServiceStatus::Stopped);
         for reporter_handle in self.values() {
             let event = ReporterEvent::Session(Session::Service(_state.service.clone()));
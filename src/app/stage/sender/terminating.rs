@@ -4,7 +4,7 @@
 use super::*;
 
 #[async_trait::async_trait]
-impl Terminating<ReportersHandles> for Sender {
+impl<W: 'static + AsyncWrite + Unpin + Send> Terminating<ReportersHandles> for Sender<W> {
     async fn terminating(
         &mut self,
         _status: Result<(), Need>,
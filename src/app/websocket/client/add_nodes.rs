@@ -13,7 +13,7 @@ pub async fn add_nodes(ws: &str, addresses: Vec<SocketAddr>, uniform_rf: u8) ->
     // add scylla nodes
     for address in addresses {
         // add node
-        let msg = SocketMsg::Scylla(ScyllaThrough::Topology(Topology::AddNode(address)));
+        let msg = SocketMsg::Scylla(ScyllaThrough::Topology(Topology::AddNode(address, None)));
         let j = serde_json::to_string(&msg).map_err(|_| anyhow!("Invalid AddNode event"))?;
         let m = Message::text(j);
         ws_stream.send(m).await?;
@@ -22,7 +22,7 @@ pub async fn add_nodes(ws: &str, addresses: Vec<SocketAddr>, uniform_rf: u8) ->
             let msg = msg.map_err(|_| anyhow!("Expected message from the WebSocketStream while building a ring"))?;
             let msg = msg.to_text()?;
             if let Ok(event) = serde_json::from_str::<SocketMsg<Result<Topology, Topology>>>(msg) {
-                if let SocketMsg::Scylla(Ok(Topology::AddNode(_))) = event {
+                if let SocketMsg::Scylla(Ok(Topology::AddNode(..))) = event {
                     info!("Added scylla node: {}", address);
                     break;
                 } else {
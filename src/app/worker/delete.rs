@@ -10,6 +10,12 @@ pub struct DeleteWorker<S: Delete<K, V>, K, V> {
     pub key: K,
     /// The number of times this worker will retry on failure
     pub retries: usize,
+    /// The consistency of the request, used when retrying due to failure
+    pub consistency: Consistency,
+    /// The retry policy which decides how the errors are handled
+    pub retry_policy: &'static dyn RetryPolicy,
+    /// The timeout of the request, which overrides the `request_timeout` of the `ScyllaBuilder`
+    pub timeout: Option<Duration>,
     _marker: std::marker::PhantomData<V>,
 }
 
@@ -19,30 +25,61 @@ where
     K: 'static + Send + Sync + Clone,
     V: 'static + Send + Sync + Clone,
 {
-    /// Create a new delete worker with a number of retries
-    pub fn new(keyspace: S, key: K, retries: usize) -> Self {
+    /// Create a new delete worker with the consistency of its request and a number of retries
+    pub fn new(keyspace: S, key: K, consistency: Consistency, retries: usize) -> Self {
         Self {
             keyspace,
             key,
             retries,
+            consistency,
+            retry_policy: &DefaultRetryPolicy,
+            timeout: None,
             _marker: std::marker::PhantomData,
         }
     }
-    /// Create a new boxed delete worker with a number of retries
-    pub fn boxed(keyspace: S, key: K, retries: usize) -> Box<Self> {
-        Box::new(Self::new(keyspace, key, retries))
+    /// Create a new boxed delete worker with the consistency of its request and a number of retries
+    pub fn boxed(keyspace: S, key: K, consistency: Consistency, retries: usize) -> Box<Self> {
+        Box::new(Self::new(keyspace, key, consistency, retries))
+    }
+    /// Set the retry policy of the worker (`DefaultRetryPolicy` by default)
+    pub fn with_retry_policy(mut self, retry_policy: &'static dyn RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+    /// Set the timeout of the request, which overrides the `request_timeout` of the `ScyllaBuilder`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
     }
 
-    fn handle_error(mut self: Box<DeleteWorker<S, K, V>>, _worker_error: WorkerError) -> anyhow::Result<()> {
-        if self.retries > 0 {
-            self.retries -= 1;
-            // currently we assume all cql/worker errors are retryable, but we might change this in future
-            let req = self
-                .keyspace
-                .delete_query::<V>(&self.key)
-                .consistency(Consistency::One)
-                .build()?;
-            tokio::spawn(async { req.send_global(self) });
+    fn retry(mut self: Box<Self>, worker_error: WorkerError, reporter: &Option<ReporterHandle>) -> anyhow::Result<()> {
+        let idempotent = self.keyspace.delete_is_idempotent::<K, V>();
+        let decision = decide_retry(
+            self.retry_policy,
+            &worker_error,
+            self.consistency,
+            idempotent,
+            &mut self.retries,
+        );
+        match decision {
+            RetryDecision::Rethrow => return Err(worker_error.into()),
+            RetryDecision::Ignore => return Ok(()),
+            RetryDecision::RetryWithConsistency(consistency) => self.consistency = consistency,
+            _ => (),
+        }
+        let req = self
+            .keyspace
+            .delete_query::<V>(&self.key)
+            .consistency(self.consistency)
+            .build()?;
+        match reporter {
+            Some(reporter) if decision.is_same_host() => {
+                let payload = req.into_payload();
+                reporter.send(ReporterEvent::Request { worker: self, payload }).ok();
+            }
+            _ => {
+                tokio::spawn(async { req.send_global(self) });
+            }
         }
         Ok(())
     }
@@ -55,22 +92,24 @@ where
     V: 'static + Send + Sync + Clone,
 {
     fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
-        Self::decode_response(giveload.try_into()?)?;
+        let decoder = giveload.try_into()?;
+        handle_warnings(|| self.keyspace.delete_statement::<K, V>(), &decoder);
+        Self::decode_response(decoder)?;
         Ok(())
     }
 
     fn handle_error(self: Box<Self>, mut error: WorkerError, reporter: &Option<ReporterHandle>) -> anyhow::Result<()> {
         if let WorkerError::Cql(ref mut cql_error) = error {
             if let (Some(id), Some(reporter)) = (cql_error.take_unprepared_id(), reporter) {
-                return handle_unprepared_error(&self, &self.keyspace, &self.key, id, reporter)
+                return handle_unprepared_error(&*self, &self.keyspace, &self.key, self.consistency, id, reporter)
                     .map_err(|e| anyhow!("Error trying to prepare query: {}", e));
-            } else {
-                self.handle_error(error)?;
             }
-        } else {
-            self.handle_error(error)?;
         }
-        Ok(())
+        self.retry(error, reporter)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
     }
 }
 
@@ -78,9 +117,10 @@ where
 /// request and resubmitting the original delete query as an
 /// unprepared statement
 pub fn handle_unprepared_error<W, S, K, V>(
-    worker: &Box<W>,
+    worker: &W,
     keyspace: &S,
     key: &K,
+    consistency: Consistency,
     id: [u8; 16],
     reporter: &ReporterHandle,
 ) -> anyhow::Result<()>
@@ -99,10 +139,10 @@ where
         payload,
     };
     reporter.send(prepare_request).ok();
-    let req = keyspace.delete_query(&key).consistency(Consistency::One).build()?;
+    let req = keyspace.delete_query(key).consistency(consistency).build()?;
     let payload = req.into_payload();
     let retry_request = ReporterEvent::Request {
-        worker: worker.clone(),
+        worker: Box::new(worker.clone()),
         payload,
     };
     reporter.send(retry_request).ok();
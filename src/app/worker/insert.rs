@@ -13,6 +13,12 @@ pub struct InsertWorker<S: Insert<K, V>, K, V> {
     pub value: V,
     /// The number of times this worker will retry on failure
     pub retries: usize,
+    /// The consistency of the request, used when retrying due to failure
+    pub consistency: Consistency,
+    /// The retry policy which decides how the errors are handled
+    pub retry_policy: &'static dyn RetryPolicy,
+    /// The timeout of the request, which overrides the `request_timeout` of the `ScyllaBuilder`
+    pub timeout: Option<Duration>,
 }
 
 impl<S: Insert<K, V>, K, V> InsertWorker<S, K, V>
@@ -21,30 +27,61 @@ where
     K: 'static + Send + Sync + Clone,
     V: 'static + Send + Sync + Clone,
 {
-    /// Create a new insert worker with a number of retries
-    pub fn new(keyspace: S, key: K, value: V, retries: usize) -> Self {
+    /// Create a new insert worker with the consistency of its request and a number of retries
+    pub fn new(keyspace: S, key: K, value: V, consistency: Consistency, retries: usize) -> Self {
         Self {
             keyspace,
             key,
             value,
             retries,
+            consistency,
+            retry_policy: &DefaultRetryPolicy,
+            timeout: None,
         }
     }
-    /// Create a new boxed insert worker with a number of retries
-    pub fn boxed(keyspace: S, key: K, value: V, retries: usize) -> Box<Self> {
-        Box::new(Self::new(keyspace, key, value, retries))
+    /// Create a new boxed insert worker with the consistency of its request and a number of retries
+    pub fn boxed(keyspace: S, key: K, value: V, consistency: Consistency, retries: usize) -> Box<Self> {
+        Box::new(Self::new(keyspace, key, value, consistency, retries))
+    }
+    /// Set the retry policy of the worker (`DefaultRetryPolicy` by default)
+    pub fn with_retry_policy(mut self, retry_policy: &'static dyn RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+    /// Set the timeout of the request, which overrides the `request_timeout` of the `ScyllaBuilder`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
     }
 
-    fn handle_error(mut self: Box<InsertWorker<S, K, V>>, _worker_error: WorkerError) -> anyhow::Result<()> {
-        if self.retries > 0 {
-            self.retries -= 1;
-            // currently we assume all cql/worker errors are retryable, but we might change this in future
-            let req = self
-                .keyspace
-                .insert_query(&self.key, &self.value)
-                .consistency(Consistency::One)
-                .build()?;
-            tokio::spawn(async { req.send_global(self) });
+    fn retry(mut self: Box<Self>, worker_error: WorkerError, reporter: &Option<ReporterHandle>) -> anyhow::Result<()> {
+        let idempotent = self.keyspace.insert_is_idempotent::<K, V>();
+        let decision = decide_retry(
+            self.retry_policy,
+            &worker_error,
+            self.consistency,
+            idempotent,
+            &mut self.retries,
+        );
+        match decision {
+            RetryDecision::Rethrow => return Err(worker_error.into()),
+            RetryDecision::Ignore => return Ok(()),
+            RetryDecision::RetryWithConsistency(consistency) => self.consistency = consistency,
+            _ => (),
+        }
+        let req = self
+            .keyspace
+            .insert_query(&self.key, &self.value)
+            .consistency(self.consistency)
+            .build()?;
+        match reporter {
+            Some(reporter) if decision.is_same_host() => {
+                let payload = req.into_payload();
+                reporter.send(ReporterEvent::Request { worker: self, payload }).ok();
+            }
+            _ => {
+                tokio::spawn(async { req.send_global(self) });
+            }
         }
         Ok(())
     }
@@ -57,22 +94,32 @@ where
     V: 'static + Send + Sync + Clone,
 {
     fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
-        Self::decode_response(giveload.try_into()?)?;
+        let decoder = giveload.try_into()?;
+        handle_warnings(|| self.keyspace.insert_statement::<K, V>(), &decoder);
+        Self::decode_response(decoder)?;
         Ok(())
     }
 
     fn handle_error(self: Box<Self>, mut error: WorkerError, reporter: &Option<ReporterHandle>) -> anyhow::Result<()> {
         if let WorkerError::Cql(ref mut cql_error) = error {
             if let (Some(id), Some(reporter)) = (cql_error.take_unprepared_id(), reporter) {
-                return handle_unprepared_error(&self, &self.keyspace, &self.key, &self.value, id, reporter)
-                    .map_err(|e| anyhow!("Error trying to prepare query: {}", e));
-            } else {
-                self.handle_error(error)?;
+                return handle_unprepared_error(
+                    &*self,
+                    &self.keyspace,
+                    &self.key,
+                    &self.value,
+                    self.consistency,
+                    id,
+                    reporter,
+                )
+                .map_err(|e| anyhow!("Error trying to prepare query: {}", e));
             }
-        } else {
-            self.handle_error(error)?;
         }
-        Ok(())
+        self.retry(error, reporter)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
     }
 }
 
@@ -80,10 +127,11 @@ where
 /// request and resubmitting the original query as an
 /// unprepared statement
 pub fn handle_unprepared_error<W, S, K, V>(
-    worker: &Box<W>,
+    worker: &W,
     keyspace: &S,
     key: &K,
     value: &V,
+    consistency: Consistency,
     id: [u8; 16],
     reporter: &ReporterHandle,
 ) -> anyhow::Result<()>
@@ -102,13 +150,10 @@ where
         payload,
     };
     reporter.send(prepare_request).ok();
-    let req = keyspace
-        .insert_query(&key, &value)
-        .consistency(Consistency::One)
-        .build()?;
+    let req = keyspace.insert_query(key, value).consistency(consistency).build()?;
     let payload = req.into_payload();
     let retry_request = ReporterEvent::Request {
-        worker: worker.clone(),
+        worker: Box::new(worker.clone()),
         payload,
     };
     reporter.send(retry_request).ok();
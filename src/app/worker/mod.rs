@@ -4,15 +4,28 @@
 pub use crate::app::stage::{ReporterEvent, ReporterHandle};
 use crate::{
     app::access::*,
-    cql::{Consistency, CqlError, Decoder, Prepare},
+    cql::{Consistency, CqlError, Decoder, ErrorCodes, Frame, Prepare, Prepared},
 };
 use anyhow::anyhow;
 pub use delete::{handle_unprepared_error as handle_delete_unprepared_error, DeleteWorker};
 pub use insert::{handle_unprepared_error as handle_insert_unprepared_error, InsertWorker};
 use log::*;
 pub use prepare::PrepareWorker;
+use retry::decide_retry;
+pub use retry::{
+    DefaultRetryPolicy, DowngradingConsistencyRetryPolicy, FallthroughRetryPolicy, RetryDecision, RetryPolicy,
+};
 pub use select::{handle_unprepared_error as handle_select_unprepared_error, SelectWorker};
-use std::convert::{TryFrom, TryInto};
+pub(crate) use speculative::speculate_local;
+pub use speculative::{
+    ConstantSpeculativeExecutionPolicy, PercentileSpeculativeExecutionPolicy, SpeculativeExecutionPolicy,
+};
+use std::{
+    borrow::Cow,
+    convert::{TryFrom, TryInto},
+    sync::RwLock,
+    time::Duration,
+};
 use thiserror::Error;
 use tokio::sync::mpsc::UnboundedSender;
 pub use value::ValueWorker;
@@ -20,7 +33,9 @@ pub use value::ValueWorker;
 mod delete;
 mod insert;
 mod prepare;
+mod retry;
 mod select;
+mod speculative;
 mod value;
 
 /// WorkerId trait type which will be implemented by worker in order to send their channel_tx.
@@ -29,6 +44,48 @@ pub trait Worker: Send {
     fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()>;
     /// Reporter will invoke this method to Send the worker error to worker
     fn handle_error(self: Box<Self>, error: WorkerError, reporter: &Option<ReporterHandle>) -> anyhow::Result<()>;
+    /// Reporter will invoke this method to get the timeout of the request, which overrides the `request_timeout`
+    /// of the `ScyllaBuilder`
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Handles the server warnings of the workers' responses, ie to log or count them per statement.
+pub trait WarningsHook: Send + Sync {
+    /// Handle the warnings which the server returned for the statement
+    fn handle_warnings(&self, statement: &str, warnings: &[String]);
+}
+
+/// A warnings hook which logs the server warnings.
+pub struct LogWarnings;
+
+impl WarningsHook for LogWarnings {
+    fn handle_warnings(&self, statement: &str, warnings: &[String]) {
+        for warning in warnings {
+            warn!("Server warning for statement '{}': {}", statement, warning);
+        }
+    }
+}
+
+/// The global warnings hook, the warnings are dropped if it's not set.
+static WARNINGS_HOOK: RwLock<Option<Box<dyn WarningsHook>>> = RwLock::new(None);
+
+/// Set the global hook which is called with the server warnings of the workers' responses.
+pub fn set_warnings_hook<H: 'static + WarningsHook>(hook: H) {
+    WARNINGS_HOOK
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(Box::new(hook));
+}
+
+/// Pass the server warnings of the response (if any) to the warnings hook.
+pub fn handle_warnings<F: FnOnce() -> Cow<'static, str>>(statement: F, decoder: &Decoder) {
+    if let Some(warnings) = decoder.flags().warnings() {
+        if let Some(hook) = WARNINGS_HOOK.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            hook.handle_warnings(&statement(), warnings);
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -49,6 +106,32 @@ pub enum WorkerError {
     /// There is no ring initialized.
     #[error("Worker NoRing")]
     NoRing,
+    /// The request timed out before its response was received.
+    #[error("Worker Timeout")]
+    Timeout,
+}
+
+impl WorkerError {
+    /// Check whether the request may have been applied despite the error, in which case it can only be retried
+    /// if it's idempotent.
+    pub fn may_be_applied(&self) -> bool {
+        match self {
+            WorkerError::Cql(cql_error) => matches!(
+                cql_error.code,
+                ErrorCodes::ServerError
+                    | ErrorCodes::TruncateError
+                    | ErrorCodes::WriteTimeout
+                    | ErrorCodes::ReadTimeout
+                    | ErrorCodes::ReadFailure
+                    | ErrorCodes::FunctionFailure
+                    | ErrorCodes::WriteFailure
+            ),
+            // the connection was lost or failed after the request was written
+            WorkerError::Other(_) | WorkerError::Lost | WorkerError::Timeout => true,
+            // the request wasn't sent
+            WorkerError::Overload | WorkerError::NoRing => false,
+        }
+    }
 }
 
 /// should be implemented on the handle of the worker
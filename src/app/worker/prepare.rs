@@ -68,7 +68,16 @@ impl PrepareWorker {
     }
 }
 impl Worker for PrepareWorker {
-    fn handle_response(self: Box<Self>, _giveload: Vec<u8>) -> anyhow::Result<()> {
+    fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
+        let decoder = Decoder::try_from(giveload)?;
+        handle_warnings(|| self.statement.clone().into(), &decoder);
+        let prepared = Prepared::new(&decoder)?;
+        if prepared.id != self.id {
+            warn!(
+                "Prepared statement: '{}' with id {:x?}, expected id {:x?}",
+                self.statement, prepared.id, self.id
+            );
+        }
         info!("Successfully prepared statement: '{}'", self.statement);
         Ok(())
     }
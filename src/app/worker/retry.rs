@@ -0,0 +1,403 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::cql::{Additional, ErrorCodes, UnavailableException, WriteTimeout, WriteType};
+
+/// The decision of a retry policy for a failed request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Retry the request on the host which returned the error.
+    RetrySameHost,
+    /// Retry the request on the next host, which is a replica picked by the ring.
+    RetryNextHost,
+    /// Retry the request on the host which returned the error with the given (downgraded) consistency.
+    RetryWithConsistency(Consistency),
+    /// Pass the error to the worker handle, the workers without a handle return the error instead.
+    Rethrow,
+    /// Ignore the error, the request is handled as if it returned an empty result.
+    Ignore,
+}
+
+impl RetryDecision {
+    /// Check whether the request is retried on the host which returned the error.
+    pub fn is_same_host(&self) -> bool {
+        matches!(
+            self,
+            RetryDecision::RetrySameHost | RetryDecision::RetryWithConsistency(_)
+        )
+    }
+}
+
+/// Decides how the workers handle the errors of their requests.
+pub trait RetryPolicy: Send + Sync {
+    /// Decide how the request, which was sent with the given consistency, is handled after the error.
+    fn decide(&self, error: &WorkerError, consistency: Consistency) -> RetryDecision;
+}
+
+/// The default retry policy.
+///
+/// - A read timeout is retried on the same host if enough replicas answered but the data wasn't retrieved.
+/// - A write timeout is retried on the same host if the batch log write timed out.
+/// - An unavailable, overloaded or bootstrapping host is retried on the next host, as well as the lost and timed
+///   out requests.
+/// - Any other error is rethrown.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRetryPolicy;
+
+impl RetryPolicy for DefaultRetryPolicy {
+    fn decide(&self, error: &WorkerError, _consistency: Consistency) -> RetryDecision {
+        match error {
+            WorkerError::Cql(cql_error) => match (&cql_error.code, &cql_error.additional) {
+                (_, Some(Additional::ReadTimeout(read_timeout)))
+                    if read_timeout.received >= read_timeout.blockfor && read_timeout.replica_had_not_responded() =>
+                {
+                    RetryDecision::RetrySameHost
+                }
+                (
+                    _,
+                    Some(Additional::WriteTimeout(WriteTimeout {
+                        writetype: WriteType::BatchLog,
+                        ..
+                    })),
+                ) => RetryDecision::RetrySameHost,
+                (ErrorCodes::UnavailableException, _)
+                | (ErrorCodes::Overloaded, _)
+                | (ErrorCodes::IsBoostrapping, _) => RetryDecision::RetryNextHost,
+                _ => RetryDecision::Rethrow,
+            },
+            WorkerError::Overload | WorkerError::Lost | WorkerError::Timeout | WorkerError::Other(_) => {
+                RetryDecision::RetryNextHost
+            }
+            WorkerError::NoRing => RetryDecision::Rethrow,
+        }
+    }
+}
+
+/// The retry policy which rethrows every error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FallthroughRetryPolicy;
+
+impl RetryPolicy for FallthroughRetryPolicy {
+    fn decide(&self, _error: &WorkerError, _consistency: Consistency) -> RetryDecision {
+        RetryDecision::Rethrow
+    }
+}
+
+/// The retry policy which retries the timed out and unavailable requests with the highest consistency which is
+/// likely to succeed, according to the number of replicas which answered or are alive.
+///
+/// The request may be applied with a weaker consistency than requested, the other errors are handled as
+/// the [`DefaultRetryPolicy`] does.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DowngradingConsistencyRetryPolicy;
+
+impl DowngradingConsistencyRetryPolicy {
+    /// Get the highest consistency which is satisfied by the number of replicas.
+    fn downgrade(consistency: Consistency, replicas: i32) -> RetryDecision {
+        match consistency {
+            // the serial consistencies can't be downgraded
+            Consistency::Serial | Consistency::LocalSerial => RetryDecision::Rethrow,
+            Consistency::EachQuorum if replicas > 0 => RetryDecision::RetryWithConsistency(Consistency::LocalQuorum),
+            _ if replicas >= 3 => RetryDecision::RetryWithConsistency(Consistency::Three),
+            _ if replicas == 2 => RetryDecision::RetryWithConsistency(Consistency::Two),
+            _ if replicas == 1 => RetryDecision::RetryWithConsistency(Consistency::One),
+            _ => RetryDecision::Rethrow,
+        }
+    }
+}
+
+impl RetryPolicy for DowngradingConsistencyRetryPolicy {
+    fn decide(&self, error: &WorkerError, consistency: Consistency) -> RetryDecision {
+        if let WorkerError::Cql(cql_error) = error {
+            match &cql_error.additional {
+                Some(Additional::ReadTimeout(read_timeout)) => {
+                    return if read_timeout.received < read_timeout.blockfor {
+                        Self::downgrade(consistency, read_timeout.received)
+                    } else if read_timeout.replica_had_not_responded() {
+                        RetryDecision::RetrySameHost
+                    } else {
+                        RetryDecision::Rethrow
+                    };
+                }
+                Some(Additional::WriteTimeout(WriteTimeout {
+                    received, writetype, ..
+                })) => {
+                    return match writetype {
+                        // the write was persisted by at least one replica, it will eventually be replicated
+                        WriteType::Simple | WriteType::Batch if *received > 0 => RetryDecision::Ignore,
+                        WriteType::UnloggedBatch => Self::downgrade(consistency, *received),
+                        WriteType::BatchLog => RetryDecision::RetrySameHost,
+                        _ => RetryDecision::Rethrow,
+                    };
+                }
+                Some(Additional::UnavailableException(UnavailableException { alive, .. })) => {
+                    return Self::downgrade(consistency, *alive);
+                }
+                _ => (),
+            }
+        }
+        DefaultRetryPolicy.decide(error, consistency)
+    }
+}
+
+/// Decide how the request of the worker is handled after the error, every retry consumes one of the worker
+/// retries and the error is rethrown once they are exhausted. The non idempotent requests are never retried
+/// if they may have been applied already.
+pub(crate) fn decide_retry(
+    retry_policy: &dyn RetryPolicy,
+    error: &WorkerError,
+    consistency: Consistency,
+    idempotent: bool,
+    retries: &mut usize,
+) -> RetryDecision {
+    match retry_policy.decide(error, consistency) {
+        decision @ RetryDecision::Rethrow | decision @ RetryDecision::Ignore => decision,
+        _ if !idempotent && error.may_be_applied() => RetryDecision::Rethrow,
+        _ if *retries == 0 => RetryDecision::Rethrow,
+        decision => {
+            *retries -= 1;
+            decision
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::{CqlError, ReadTimeout};
+
+    fn cql_error(code: ErrorCodes, additional: Option<Additional>) -> WorkerError {
+        WorkerError::Cql(CqlError {
+            code,
+            message: String::new(),
+            additional,
+        })
+    }
+
+    fn read_timeout(received: i32, blockfor: i32, data_present: u8) -> WorkerError {
+        cql_error(
+            ErrorCodes::ReadTimeout,
+            Some(Additional::ReadTimeout(ReadTimeout {
+                cl: Consistency::Quorum,
+                received,
+                blockfor,
+                data_present,
+            })),
+        )
+    }
+
+    fn write_timeout(received: i32, writetype: WriteType) -> WorkerError {
+        cql_error(
+            ErrorCodes::WriteTimeout,
+            Some(Additional::WriteTimeout(WriteTimeout {
+                cl: Consistency::Quorum,
+                received,
+                blockfor: 2,
+                writetype,
+            })),
+        )
+    }
+
+    fn unavailable(alive: i32) -> WorkerError {
+        cql_error(
+            ErrorCodes::UnavailableException,
+            Some(Additional::UnavailableException(UnavailableException {
+                cl: Consistency::Quorum,
+                required: 2,
+                alive,
+            })),
+        )
+    }
+
+    #[test]
+    fn default_retry_policy() {
+        let policy = DefaultRetryPolicy;
+        let decide = |error: &WorkerError| policy.decide(error, Consistency::Quorum);
+        assert_eq!(decide(&read_timeout(2, 2, 0)), RetryDecision::RetrySameHost);
+        assert_eq!(decide(&read_timeout(2, 2, 1)), RetryDecision::Rethrow);
+        assert_eq!(decide(&read_timeout(1, 2, 0)), RetryDecision::Rethrow);
+        assert_eq!(
+            decide(&write_timeout(0, WriteType::BatchLog)),
+            RetryDecision::RetrySameHost
+        );
+        assert_eq!(decide(&write_timeout(1, WriteType::Simple)), RetryDecision::Rethrow);
+        assert_eq!(decide(&unavailable(1)), RetryDecision::RetryNextHost);
+        assert_eq!(
+            decide(&cql_error(ErrorCodes::Overloaded, None)),
+            RetryDecision::RetryNextHost
+        );
+        assert_eq!(
+            decide(&cql_error(ErrorCodes::SyntaxError, None)),
+            RetryDecision::Rethrow
+        );
+        assert_eq!(decide(&WorkerError::Timeout), RetryDecision::RetryNextHost);
+        assert_eq!(decide(&WorkerError::Lost), RetryDecision::RetryNextHost);
+        assert_eq!(decide(&WorkerError::Overload), RetryDecision::RetryNextHost);
+        assert_eq!(decide(&WorkerError::NoRing), RetryDecision::Rethrow);
+    }
+
+    #[test]
+    fn fallthrough_retry_policy() {
+        let policy = FallthroughRetryPolicy;
+        for error in [
+            read_timeout(2, 2, 0),
+            unavailable(1),
+            WorkerError::Timeout,
+            WorkerError::Overload,
+        ] {
+            assert_eq!(policy.decide(&error, Consistency::One), RetryDecision::Rethrow);
+        }
+    }
+
+    #[test]
+    fn downgrading_consistency_retry_policy() {
+        let policy = DowngradingConsistencyRetryPolicy;
+        // the consistency is downgraded to the number of replicas which answered or are alive
+        assert_eq!(
+            policy.decide(&read_timeout(1, 2, 0), Consistency::Quorum),
+            RetryDecision::RetryWithConsistency(Consistency::One)
+        );
+        assert_eq!(
+            policy.decide(&unavailable(2), Consistency::All),
+            RetryDecision::RetryWithConsistency(Consistency::Two)
+        );
+        assert_eq!(
+            policy.decide(&unavailable(4), Consistency::All),
+            RetryDecision::RetryWithConsistency(Consistency::Three)
+        );
+        assert_eq!(
+            policy.decide(&unavailable(1), Consistency::EachQuorum),
+            RetryDecision::RetryWithConsistency(Consistency::LocalQuorum)
+        );
+        assert_eq!(
+            policy.decide(&unavailable(0), Consistency::Quorum),
+            RetryDecision::Rethrow
+        );
+        assert_eq!(
+            policy.decide(&unavailable(1), Consistency::Serial),
+            RetryDecision::Rethrow
+        );
+        assert_eq!(
+            policy.decide(&read_timeout(2, 2, 0), Consistency::Quorum),
+            RetryDecision::RetrySameHost
+        );
+        assert_eq!(
+            policy.decide(&read_timeout(2, 2, 1), Consistency::Quorum),
+            RetryDecision::Rethrow
+        );
+        // the persisted writes are ignored, while the unlogged batches are downgraded
+        assert_eq!(
+            policy.decide(&write_timeout(1, WriteType::Simple), Consistency::Quorum),
+            RetryDecision::Ignore
+        );
+        assert_eq!(
+            policy.decide(&write_timeout(0, WriteType::Simple), Consistency::Quorum),
+            RetryDecision::Rethrow
+        );
+        assert_eq!(
+            policy.decide(&write_timeout(1, WriteType::UnloggedBatch), Consistency::Quorum),
+            RetryDecision::RetryWithConsistency(Consistency::One)
+        );
+        assert_eq!(
+            policy.decide(&write_timeout(0, WriteType::BatchLog), Consistency::Quorum),
+            RetryDecision::RetrySameHost
+        );
+        // the other errors are handled as the default policy does
+        assert_eq!(
+            policy.decide(&WorkerError::Overload, Consistency::Quorum),
+            RetryDecision::RetryNextHost
+        );
+    }
+
+    #[test]
+    fn decide_retry_consumes_the_retries() {
+        let mut retries = 2;
+        for _ in 0..2 {
+            assert_eq!(
+                decide_retry(
+                    &DefaultRetryPolicy,
+                    &unavailable(1),
+                    Consistency::One,
+                    false,
+                    &mut retries
+                ),
+                RetryDecision::RetryNextHost
+            );
+        }
+        assert_eq!(retries, 0);
+        assert_eq!(
+            decide_retry(
+                &DefaultRetryPolicy,
+                &unavailable(1),
+                Consistency::One,
+                false,
+                &mut retries
+            ),
+            RetryDecision::Rethrow
+        );
+        // the rethrown and ignored errors don't consume the retries
+        let mut retries = 1;
+        assert_eq!(
+            decide_retry(
+                &FallthroughRetryPolicy,
+                &unavailable(1),
+                Consistency::One,
+                true,
+                &mut retries
+            ),
+            RetryDecision::Rethrow
+        );
+        assert_eq!(
+            decide_retry(
+                &DowngradingConsistencyRetryPolicy,
+                &write_timeout(1, WriteType::Simple),
+                Consistency::Quorum,
+                false,
+                &mut retries
+            ),
+            RetryDecision::Ignore
+        );
+        assert_eq!(retries, 1);
+    }
+
+    #[test]
+    fn decide_retry_checks_the_idempotency() {
+        // the errors after which the request may have been applied are only retried if it's idempotent
+        let mut retries = 1;
+        assert_eq!(
+            decide_retry(
+                &DefaultRetryPolicy,
+                &WorkerError::Timeout,
+                Consistency::One,
+                false,
+                &mut retries
+            ),
+            RetryDecision::Rethrow
+        );
+        assert_eq!(retries, 1);
+        assert_eq!(
+            decide_retry(
+                &DefaultRetryPolicy,
+                &WorkerError::Timeout,
+                Consistency::One,
+                true,
+                &mut retries
+            ),
+            RetryDecision::RetryNextHost
+        );
+        assert_eq!(retries, 0);
+        // the requests which weren't sent are retried either way
+        let mut retries = 1;
+        assert_eq!(
+            decide_retry(
+                &DefaultRetryPolicy,
+                &WorkerError::Overload,
+                Consistency::One,
+                false,
+                &mut retries
+            ),
+            RetryDecision::RetryNextHost
+        );
+    }
+}
@@ -24,6 +24,12 @@ where
     pub paging_state: Option<Vec<u8>>,
     /// The number of times this worker will retry on failure
     pub retries: usize,
+    /// The consistency of the request, used when retrying due to failure
+    pub consistency: Consistency,
+    /// The retry policy which decides how the errors are handled
+    pub retry_policy: &'static dyn RetryPolicy,
+    /// The timeout of the request, which overrides the `request_timeout` of the `ScyllaBuilder`
+    pub timeout: Option<Duration>,
     _marker: std::marker::PhantomData<V>,
 }
 
@@ -34,8 +40,15 @@ where
     V: 'static + Send + Clone,
     H: 'static + Send + HandleResponse<Self, Response = Decoder> + HandleError<Self> + Clone,
 {
-    /// Create a new value selecting worker with a number of retries and a response handle
-    pub fn new(handle: H, keyspace: S, key: K, retries: usize, _marker: std::marker::PhantomData<V>) -> Self {
+    /// Create a new value selecting worker with the consistency of its request, a number of retries and a response handle
+    pub fn new(
+        handle: H,
+        keyspace: S,
+        key: K,
+        consistency: Consistency,
+        retries: usize,
+        _marker: std::marker::PhantomData<V>,
+    ) -> Self {
         Self {
             handle,
             keyspace,
@@ -43,12 +56,23 @@ where
             page_size: None,
             paging_state: None,
             retries,
+            consistency,
+            retry_policy: &DefaultRetryPolicy,
+            timeout: None,
             _marker,
         }
     }
-    /// Create a new boxed value selecting worker with a number of retries and a response handle
-    pub fn boxed(handle: H, keyspace: S, key: K, retries: usize, _marker: std::marker::PhantomData<V>) -> Box<Self> {
-        Box::new(Self::new(handle, keyspace, key, retries, _marker))
+    /// Create a new boxed value selecting worker with the consistency of its request, a number of retries and a
+    /// response handle
+    pub fn boxed(
+        handle: H,
+        keyspace: S,
+        key: K,
+        consistency: Consistency,
+        retries: usize,
+        _marker: std::marker::PhantomData<V>,
+    ) -> Box<Self> {
+        Box::new(Self::new(handle, keyspace, key, consistency, retries, _marker))
     }
     /// Add paging information to this worker
     pub fn with_paging<P: Into<Option<Vec<u8>>>>(mut self, page_size: i32, paging_state: P) -> Self {
@@ -56,6 +80,61 @@ where
         self.paging_state = paging_state.into();
         self
     }
+    /// Set the retry policy of the worker (`DefaultRetryPolicy` by default)
+    pub fn with_retry_policy(mut self, retry_policy: &'static dyn RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+    /// Set the timeout of the request, which overrides the `request_timeout` of the `ScyllaBuilder`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn retry(mut self: Box<Self>, worker_error: WorkerError, reporter: &Option<ReporterHandle>) -> anyhow::Result<()> {
+        let idempotent = self.keyspace.select_is_idempotent::<K, V>();
+        let decision = decide_retry(
+            self.retry_policy,
+            &worker_error,
+            self.consistency,
+            idempotent,
+            &mut self.retries,
+        );
+        match decision {
+            RetryDecision::Rethrow => return H::handle_error(self, worker_error),
+            RetryDecision::Ignore => return H::handle_response(self, empty_rows()?),
+            RetryDecision::RetryWithConsistency(consistency) => self.consistency = consistency,
+            _ => (),
+        }
+        let req = self.keyspace.select_query::<V>(&self.key).consistency(self.consistency);
+        let req = if let Some(page_size) = self.page_size {
+            req.page_size(page_size).paging_state(&self.paging_state)
+        } else {
+            req.paging_state(&self.paging_state)
+        }
+        .build()?;
+        match reporter {
+            Some(reporter) if decision.is_same_host() => {
+                let payload = req.into_payload();
+                reporter.send(ReporterEvent::Request { worker: self, payload }).ok();
+            }
+            _ => {
+                tokio::spawn(async { req.send_global(self) });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An empty ROWS result without metadata, which is the response of the ignored errors.
+fn empty_rows() -> anyhow::Result<Decoder> {
+    // the v4 RESULT header
+    let mut frame = vec![0x84, 0, 0, 0, 0x08, 0, 0, 0, 16];
+    // kind (ROWS), flags (NO_METADATA), columns_count, rows_count
+    for int in [2i32, 4, 0, 0].iter() {
+        frame.extend(&int.to_be_bytes());
+    }
+    Decoder::try_from(frame)
 }
 
 impl<H, S, K, V> Worker for SelectWorker<H, S, K, V>
@@ -67,10 +146,13 @@ where
 {
     fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
         match Decoder::try_from(giveload) {
-            Ok(decoder) => match Self::decode_response(decoder) {
-                Ok(res) => H::handle_response(self, res),
-                Err(e) => H::handle_error(self, WorkerError::Other(e)),
-            },
+            Ok(decoder) => {
+                handle_warnings(|| self.keyspace.select_statement::<K, V>(), &decoder);
+                match Self::decode_response(decoder) {
+                    Ok(res) => H::handle_response(self, res),
+                    Err(e) => H::handle_error(self, WorkerError::Other(e)),
+                }
+            }
             Err(e) => H::handle_error(self, WorkerError::Other(e)),
         }
     }
@@ -79,9 +161,10 @@ where
         if let WorkerError::Cql(ref mut cql_error) = error {
             if let (Some(id), Some(reporter)) = (cql_error.take_unprepared_id(), reporter) {
                 handle_unprepared_error(
-                    &self,
+                    &*self,
                     &self.keyspace,
                     &self.key,
+                    self.consistency,
                     id,
                     self.page_size,
                     &self.paging_state,
@@ -92,12 +175,16 @@ where
                     H::handle_error(self, error)
                 })
             } else {
-                H::handle_error(self, error)
+                self.retry(error, reporter)
             }
         } else {
-            H::handle_error(self, error)
+            self.retry(error, reporter)
         }
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl<S, K, V> HandleResponse<SelectWorker<UnboundedSender<Result<Decoder, WorkerError>>, S, K, V>>
@@ -124,40 +211,25 @@ where
     V: 'static + Send + Clone,
 {
     fn handle_error(
-        mut worker: Box<SelectWorker<UnboundedSender<Result<Decoder, WorkerError>>, S, K, V>>,
+        worker: Box<SelectWorker<UnboundedSender<Result<Decoder, WorkerError>>, S, K, V>>,
         worker_error: WorkerError,
     ) -> anyhow::Result<()> {
-        if worker.retries > 0 {
-            worker.retries -= 1;
-            // currently we assume all cql/worker errors are retryable, but we might change this in future
-            let req = worker
-                .keyspace
-                .select_query::<V>(&worker.key)
-                .consistency(Consistency::One);
-            let req = if let Some(page_size) = worker.page_size {
-                req.page_size(page_size).paging_state(&worker.paging_state)
-            } else {
-                req.paging_state(&worker.paging_state)
-            }
-            .build()?;
-            tokio::spawn(async { req.send_global(worker) });
-            Ok(())
-        } else {
-            worker
-                .handle
-                .send(Err(worker_error))
-                .map_err(|e| anyhow!(e.to_string()))
-        }
+        worker
+            .handle
+            .send(Err(worker_error))
+            .map_err(|e| anyhow!(e.to_string()))
     }
 }
 
 /// Handle an unprepared CQL error by sending a prepare
 /// request and resubmitting the original query as an
 /// unprepared statement
+#[allow(clippy::too_many_arguments)]
 pub fn handle_unprepared_error<W, S, K, V>(
-    worker: &Box<W>,
+    worker: &W,
     keyspace: &S,
     key: &K,
+    consistency: Consistency,
     id: [u8; 16],
     page_size: Option<i32>,
     paging_state: &Option<Vec<u8>>,
@@ -178,18 +250,31 @@ where
         payload,
     };
     reporter.send(prepare_request).ok();
-    let req = keyspace.select_query::<V>(&key).consistency(Consistency::One);
+    let req = keyspace.select_query::<V>(key).consistency(consistency);
     let req = if let Some(page_size) = page_size {
-        req.page_size(page_size).paging_state(&paging_state)
+        req.page_size(page_size).paging_state(paging_state)
     } else {
-        req.paging_state(&paging_state)
+        req.paging_state(paging_state)
     }
     .build()?;
     let payload = req.into_payload();
     let retry_request = ReporterEvent::Request {
-        worker: worker.clone(),
+        worker: Box::new(worker.clone()),
         payload,
     };
     reporter.send(retry_request).ok();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::{Iter, Rows};
+
+    #[test]
+    fn ignored_errors_are_empty_rows() {
+        let decoder = empty_rows().unwrap();
+        assert!(decoder.is_rows().unwrap());
+        assert!(Iter::<(i32,)>::new(decoder).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::app::ring::Ring;
+use rand::Rng;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The number of latencies which are kept by the percentile policy.
+const LATENCY_SAMPLES: usize = 1000;
+/// The number of latencies which the percentile policy needs before it starts speculating.
+const MIN_LATENCY_SAMPLES: usize = 100;
+
+/// Decides when the speculative attempts of an idempotent read are sent to the other replicas.
+pub trait SpeculativeExecutionPolicy: Send + Sync {
+    /// Get the delay after which the next attempt is sent, given the number of the already sent attempts, or
+    /// `None` to stop speculating.
+    fn delay(&self, attempts: usize) -> Option<Duration>;
+    /// Record the latency of an answered request, used by the policies which adapt their delay.
+    fn record_latency(&self, _latency: Duration) {}
+}
+
+/// The speculative execution policy which sends the speculative attempts after a constant delay.
+#[derive(Debug, Clone, Copy)]
+pub struct ConstantSpeculativeExecutionPolicy {
+    delay: Duration,
+    max_speculative_attempts: usize,
+}
+
+impl ConstantSpeculativeExecutionPolicy {
+    /// Create a new constant policy, which sends up to `max_speculative_attempts` attempts every `delay`.
+    pub const fn new(delay: Duration, max_speculative_attempts: usize) -> Self {
+        Self {
+            delay,
+            max_speculative_attempts,
+        }
+    }
+}
+
+impl SpeculativeExecutionPolicy for ConstantSpeculativeExecutionPolicy {
+    fn delay(&self, attempts: usize) -> Option<Duration> {
+        if attempts <= self.max_speculative_attempts {
+            Some(self.delay)
+        } else {
+            None
+        }
+    }
+}
+
+/// The speculative execution policy which sends the speculative attempts once the request takes longer than the
+/// given percentile of the recent latencies.
+///
+/// The latencies are recorded by the requests sent with this policy, and it doesn't speculate until it recorded
+/// enough of them.
+#[derive(Debug)]
+pub struct PercentileSpeculativeExecutionPolicy {
+    percentile: f64,
+    max_speculative_attempts: usize,
+    latencies: Mutex<LatencyWindow>,
+}
+
+/// The recent latencies, in the order they were recorded and sorted, so the percentile is looked up without
+/// sorting them on every request.
+#[derive(Debug, Default)]
+struct LatencyWindow {
+    recorded: VecDeque<Duration>,
+    sorted: Vec<Duration>,
+}
+
+impl LatencyWindow {
+    fn record(&mut self, latency: Duration) {
+        if self.recorded.len() == LATENCY_SAMPLES {
+            if let Some(oldest) = self.recorded.pop_front() {
+                if let Ok(index) = self.sorted.binary_search(&oldest) {
+                    self.sorted.remove(index);
+                }
+            }
+        }
+        self.recorded.push_back(latency);
+        let index = self.sorted.binary_search(&latency).unwrap_or_else(|index| index);
+        self.sorted.insert(index, latency);
+    }
+
+    fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.sorted.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+        let index = ((self.sorted.len() - 1) as f64 * percentile / 100.0).round() as usize;
+        self.sorted.get(index).cloned()
+    }
+}
+
+impl PercentileSpeculativeExecutionPolicy {
+    /// Create a new percentile policy, which sends up to `max_speculative_attempts` attempts, the percentile is
+    /// within `0.0..=100.0`.
+    pub fn new(percentile: f64, max_speculative_attempts: usize) -> Self {
+        Self {
+            percentile: percentile.clamp(0.0, 100.0),
+            max_speculative_attempts,
+            latencies: Mutex::new(LatencyWindow {
+                recorded: VecDeque::with_capacity(LATENCY_SAMPLES),
+                sorted: Vec::with_capacity(LATENCY_SAMPLES),
+            }),
+        }
+    }
+}
+
+impl SpeculativeExecutionPolicy for PercentileSpeculativeExecutionPolicy {
+    fn delay(&self, attempts: usize) -> Option<Duration> {
+        if attempts > self.max_speculative_attempts {
+            return None;
+        }
+        self.latencies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .percentile(self.percentile)
+    }
+
+    fn record_latency(&self, latency: Duration) {
+        self.latencies.lock().unwrap_or_else(|e| e.into_inner()).record(latency);
+    }
+}
+
+/// The state shared by the attempts of a speculative request.
+struct Speculation {
+    /// The worker of the request, which is taken by the first attempt to respond
+    worker: Option<Box<dyn Worker>>,
+    /// The number of the attempts which didn't respond yet
+    in_flight: usize,
+}
+
+/// The worker of a single attempt of a speculative request, the first attempt to respond passes its response to
+/// the request worker and the responses of the others are dropped.
+struct SpeculativeWorker {
+    speculation: Arc<Mutex<Speculation>>,
+    policy: &'static dyn SpeculativeExecutionPolicy,
+    sent_at: Instant,
+    timeout: Option<Duration>,
+}
+
+impl SpeculativeWorker {
+    fn take_worker(&self) -> Option<Box<dyn Worker>> {
+        let mut speculation = self.speculation.lock().unwrap_or_else(|e| e.into_inner());
+        speculation.in_flight -= 1;
+        speculation.worker.take()
+    }
+}
+
+impl Worker for SpeculativeWorker {
+    fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
+        match self.take_worker() {
+            Some(worker) => {
+                self.policy.record_latency(self.sent_at.elapsed());
+                worker.handle_response(giveload)
+            }
+            // another attempt won, the reporter already freed the stream of this one
+            None => Ok(()),
+        }
+    }
+
+    fn handle_error(self: Box<Self>, error: WorkerError, reporter: &Option<ReporterHandle>) -> anyhow::Result<()> {
+        let worker = {
+            let mut speculation = self.speculation.lock().unwrap_or_else(|e| e.into_inner());
+            speculation.in_flight -= 1;
+            // the error is only passed to the worker once none of the attempts can respond anymore
+            if speculation.in_flight == 0 {
+                speculation.worker.take()
+            } else {
+                None
+            }
+        };
+        match worker {
+            Some(worker) => worker.handle_error(error, reporter),
+            None => Ok(()),
+        }
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// Get the delay of the next speculative attempt, the attempts are bounded by the policy and by the replicas
+/// of the token.
+fn next_delay(policy: &dyn SpeculativeExecutionPolicy, attempts: usize, replicas: usize) -> Option<Duration> {
+    if attempts < replicas {
+        policy.delay(attempts)
+    } else {
+        None
+    }
+}
+
+/// Send the request to a random replica of the local datacenter, then send its speculative attempts to the next
+/// replicas of the token, as long as the request isn't answered once the policy delay passes.
+pub(crate) fn speculate_local(
+    token: i64,
+    payload: Vec<u8>,
+    worker: Box<dyn Worker>,
+    policy: &'static dyn SpeculativeExecutionPolicy,
+) {
+    let replicas = Ring::local_replicas_count(token);
+    if replicas < 2 {
+        return Ring::send_local_random_replica(token, ReporterEvent::Request { worker, payload });
+    }
+    let first_replica = rand::thread_rng().gen_range(0..replicas);
+    let timeout = worker.timeout();
+    let speculation = Arc::new(Mutex::new(Speculation {
+        worker: Some(worker),
+        in_flight: 1,
+    }));
+    let attempt = move |speculation: &Arc<Mutex<Speculation>>| SpeculativeWorker {
+        speculation: speculation.clone(),
+        policy,
+        sent_at: Instant::now(),
+        timeout,
+    };
+    let request = ReporterEvent::Request {
+        worker: Box::new(attempt(&speculation)),
+        payload: payload.clone(),
+    };
+    Ring::send_local(first_replica, token, request);
+    tokio::spawn(async move {
+        let mut attempts = 1;
+        while let Some(delay) = next_delay(policy, attempts, replicas) {
+            tokio::time::sleep(delay).await;
+            {
+                let mut speculation = speculation.lock().unwrap_or_else(|e| e.into_inner());
+                if speculation.worker.is_none() {
+                    break;
+                }
+                speculation.in_flight += 1;
+            }
+            let request = ReporterEvent::Request {
+                worker: Box::new(attempt(&speculation)),
+                payload: payload.clone(),
+            };
+            Ring::send_local((first_replica + attempts) % replicas, token, request);
+            attempts += 1;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_policy_delay() {
+        let policy = ConstantSpeculativeExecutionPolicy::new(Duration::from_millis(5), 2);
+        assert_eq!(policy.delay(1), Some(Duration::from_millis(5)));
+        assert_eq!(policy.delay(2), Some(Duration::from_millis(5)));
+        assert_eq!(policy.delay(3), None);
+    }
+
+    #[test]
+    fn percentile_policy_delay() {
+        let policy = PercentileSpeculativeExecutionPolicy::new(90.0, 1);
+        // the policy doesn't speculate until it recorded enough latencies
+        for millis in 1..MIN_LATENCY_SAMPLES as u64 {
+            policy.record_latency(Duration::from_millis(millis));
+        }
+        assert_eq!(policy.delay(1), None);
+        policy.record_latency(Duration::from_millis(MIN_LATENCY_SAMPLES as u64));
+        assert_eq!(policy.delay(1), Some(Duration::from_millis(90)));
+        assert_eq!(policy.delay(2), None);
+        // the oldest latencies are dropped from the window
+        for _ in 0..LATENCY_SAMPLES {
+            policy.record_latency(Duration::from_millis(1000));
+        }
+        assert_eq!(policy.delay(1), Some(Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn latency_window_stays_sorted() {
+        let mut window = LatencyWindow::default();
+        for millis in (0..(LATENCY_SAMPLES as u64 + 10)).rev() {
+            window.record(Duration::from_millis(millis % 500));
+        }
+        assert_eq!(window.recorded.len(), LATENCY_SAMPLES);
+        assert_eq!(window.sorted.len(), LATENCY_SAMPLES);
+        assert!(window.sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+        let mut recorded: Vec<Duration> = window.recorded.iter().cloned().collect();
+        recorded.sort_unstable();
+        assert_eq!(recorded, window.sorted);
+    }
+
+    #[test]
+    fn attempts_are_bounded_by_the_replicas() {
+        let policy = ConstantSpeculativeExecutionPolicy::new(Duration::from_millis(5), 10);
+        assert_eq!(next_delay(&policy, 1, 3), Some(Duration::from_millis(5)));
+        assert_eq!(next_delay(&policy, 2, 3), Some(Duration::from_millis(5)));
+        assert_eq!(next_delay(&policy, 3, 3), None);
+        let policy = ConstantSpeculativeExecutionPolicy::new(Duration::from_millis(5), 1);
+        assert_eq!(next_delay(&policy, 2, 3), None);
+    }
+}
@@ -24,6 +24,12 @@ where
     pub paging_state: Option<Vec<u8>>,
     /// The number of times this worker will retry on failure
     pub retries: usize,
+    /// The consistency of the request, used when retrying due to failure
+    pub consistency: Consistency,
+    /// The retry policy which decides how the errors are handled
+    pub retry_policy: &'static dyn RetryPolicy,
+    /// The timeout of the request, which overrides the `request_timeout` of the `ScyllaBuilder`
+    pub timeout: Option<Duration>,
     _marker: std::marker::PhantomData<V>,
 }
 
@@ -34,8 +40,15 @@ where
     V: 'static + Send + Clone,
     H: 'static + Send + HandleResponse<Self, Response = Option<V>> + HandleError<Self> + Clone,
 {
-    /// Create a new value selecting worker with a number of retries and a response handle
-    pub fn new(handle: H, keyspace: S, key: K, retries: usize, _marker: std::marker::PhantomData<V>) -> Self {
+    /// Create a new value selecting worker with the consistency of its request, a number of retries and a response handle
+    pub fn new(
+        handle: H,
+        keyspace: S,
+        key: K,
+        consistency: Consistency,
+        retries: usize,
+        _marker: std::marker::PhantomData<V>,
+    ) -> Self {
         Self {
             handle,
             keyspace,
@@ -43,12 +56,23 @@ where
             page_size: None,
             paging_state: None,
             retries,
+            consistency,
+            retry_policy: &DefaultRetryPolicy,
+            timeout: None,
             _marker,
         }
     }
-    /// Create a new boxed value selecting worker with a number of retries and a response handle
-    pub fn boxed(handle: H, keyspace: S, key: K, retries: usize, _marker: std::marker::PhantomData<V>) -> Box<Self> {
-        Box::new(Self::new(handle, keyspace, key, retries, _marker))
+    /// Create a new boxed value selecting worker with the consistency of its request, a number of retries and a
+    /// response handle
+    pub fn boxed(
+        handle: H,
+        keyspace: S,
+        key: K,
+        consistency: Consistency,
+        retries: usize,
+        _marker: std::marker::PhantomData<V>,
+    ) -> Box<Self> {
+        Box::new(Self::new(handle, keyspace, key, consistency, retries, _marker))
     }
     /// Add paging information to this worker
     pub fn with_paging<P: Into<Option<Vec<u8>>>>(mut self, page_size: i32, paging_state: P) -> Self {
@@ -56,6 +80,50 @@ where
         self.paging_state = paging_state.into();
         self
     }
+    /// Set the retry policy of the worker (`DefaultRetryPolicy` by default)
+    pub fn with_retry_policy(mut self, retry_policy: &'static dyn RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+    /// Set the timeout of the request, which overrides the `request_timeout` of the `ScyllaBuilder`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn retry(mut self: Box<Self>, worker_error: WorkerError, reporter: &Option<ReporterHandle>) -> anyhow::Result<()> {
+        let idempotent = self.keyspace.select_is_idempotent::<K, V>();
+        let decision = decide_retry(
+            self.retry_policy,
+            &worker_error,
+            self.consistency,
+            idempotent,
+            &mut self.retries,
+        );
+        match decision {
+            RetryDecision::Rethrow => return H::handle_error(self, worker_error),
+            RetryDecision::Ignore => return H::handle_response(self, None),
+            RetryDecision::RetryWithConsistency(consistency) => self.consistency = consistency,
+            _ => (),
+        }
+        let req = self.keyspace.select_query::<V>(&self.key).consistency(self.consistency);
+        let req = if let Some(page_size) = self.page_size {
+            req.page_size(page_size).paging_state(&self.paging_state)
+        } else {
+            req.paging_state(&self.paging_state)
+        }
+        .build()?;
+        match reporter {
+            Some(reporter) if decision.is_same_host() => {
+                let payload = req.into_payload();
+                reporter.send(ReporterEvent::Request { worker: self, payload }).ok();
+            }
+            _ => {
+                tokio::spawn(async { req.send_global(self) });
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<H, S, K, V> DecodeResponse<Option<V>> for ValueWorker<H, S, K, V>
@@ -79,10 +147,13 @@ where
 {
     fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
         match Decoder::try_from(giveload) {
-            Ok(decoder) => match Self::decode_response(decoder) {
-                Ok(res) => H::handle_response(self, res),
-                Err(e) => H::handle_error(self, WorkerError::Other(e)),
-            },
+            Ok(decoder) => {
+                handle_warnings(|| self.keyspace.select_statement::<K, V>(), &decoder);
+                match Self::decode_response(decoder) {
+                    Ok(res) => H::handle_response(self, res),
+                    Err(e) => H::handle_error(self, WorkerError::Other(e)),
+                }
+            }
             Err(e) => H::handle_error(self, WorkerError::Other(e)),
         }
     }
@@ -91,9 +162,10 @@ where
         if let WorkerError::Cql(ref mut cql_error) = error {
             if let (Some(id), Some(reporter)) = (cql_error.take_unprepared_id(), reporter) {
                 handle_select_unprepared_error(
-                    &self,
+                    &*self,
                     &self.keyspace,
                     &self.key,
+                    self.consistency,
                     id,
                     self.page_size,
                     &self.paging_state,
@@ -104,12 +176,16 @@ where
                     H::handle_error(self, error)
                 })
             } else {
-                H::handle_error(self, error)
+                self.retry(error, reporter)
             }
         } else {
-            H::handle_error(self, error)
+            self.retry(error, reporter)
         }
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl<S, K, V> HandleResponse<ValueWorker<UnboundedSender<Result<Option<V>, WorkerError>>, S, K, V>>
@@ -136,29 +212,12 @@ where
     V: 'static + Send + Clone,
 {
     fn handle_error(
-        mut worker: Box<ValueWorker<UnboundedSender<Result<Option<V>, WorkerError>>, S, K, V>>,
+        worker: Box<ValueWorker<UnboundedSender<Result<Option<V>, WorkerError>>, S, K, V>>,
         worker_error: WorkerError,
     ) -> anyhow::Result<()> {
-        if worker.retries > 0 {
-            worker.retries -= 1;
-            // currently we assume all cql/worker errors are retryable, but we might change this in future
-            let req = worker
-                .keyspace
-                .select_query::<V>(&worker.key)
-                .consistency(Consistency::One);
-            let req = if let Some(page_size) = worker.page_size {
-                req.page_size(page_size).paging_state(&worker.paging_state)
-            } else {
-                req.paging_state(&worker.paging_state)
-            }
-            .build()?;
-            tokio::spawn(async { req.send_global(worker) });
-            Ok(())
-        } else {
-            worker
-                .handle
-                .send(Err(worker_error))
-                .map_err(|e| anyhow!(e.to_string()))
-        }
+        worker
+            .handle
+            .send(Err(worker_error))
+            .map_err(|e| anyhow!(e.to_string()))
     }
 }
@@ -0,0 +1,217 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the tokio-util codec of the Cql frames, which frames a byte stream into the
+//! response frames and writes the request frames along with their stream id.
+//!
+//! The codec doesn't depend on the actor runtime, so a multiplexed connection can be run on top of
+//! `tokio_util::codec::Framed` with the `cql`-only build.
+
+use super::{
+    compression::{MyCompression, UNCOMPRESSED},
+    frame::{upgrade_request, Decoder},
+    segment::{SegmentCodec, SegmentDecoder},
+};
+use anyhow::ensure;
+use bytes::BytesMut;
+use std::convert::TryInto;
+
+/// The length of the frame header.
+pub const FRAME_HEADER_LENGTH: usize = 9;
+/// The max length of a frame (256MiB), as defined by the protocol.
+pub const MAX_FRAME_LENGTH: usize = 256 * 1024 * 1024;
+
+/// The codec of the Cql frames.
+///
+/// The frames are compressed and decompressed by the codec compression, which is the one negotiated by the
+/// connection (see `Cql::compression`). Once the codec has the segment codec of a v5 connection, the frames are
+/// carried in segments (which are compressed instead of the frames) and the request frames are upgraded to v5.
+///
+/// ## Example
+/// ```
+/// use scylla_rs::cql::{compression::UNCOMPRESSED, Consistency, Frame, FrameCodec, MyCompression, Query, Statements};
+/// use tokio_util::codec::{Decoder, Encoder};
+///
+/// let mut codec = FrameCodec::new(MyCompression(&UNCOMPRESSED));
+/// let Query(payload) = Query::new()
+///     .statement("SELECT * FROM system.local")
+///     .consistency(Consistency::One)
+///     .build()?;
+/// let mut buffer = bytes::BytesMut::new();
+/// codec.encode((1, payload), &mut buffer)?;
+/// // the request is decoded as any frame, along with its stream id
+/// let frame = codec.decode(&mut buffer)?.unwrap();
+/// assert_eq!(frame.stream()?, 1);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct FrameCodec {
+    compression: MyCompression,
+    compression_threshold: usize,
+    segment_codec: Option<SegmentCodec>,
+    segment_decoder: Option<SegmentDecoder>,
+}
+
+impl FrameCodec {
+    /// Create a new frame codec, which compresses and decompresses the frames with the given compression.
+    pub fn new(compression: MyCompression) -> Self {
+        Self {
+            compression,
+            compression_threshold: 0,
+            segment_codec: None,
+            segment_decoder: None,
+        }
+    }
+    /// Send the request frames with a body smaller than the threshold (in bytes) uncompressed, by default every
+    /// frame is compressed.
+    pub fn with_compression_threshold(mut self, threshold: usize) -> Self {
+        self.compression_threshold = threshold;
+        self
+    }
+    /// Carry the frames in the segments of the segment codec (protocol v5).
+    pub fn with_segments(mut self, segment_codec: SegmentCodec) -> Self {
+        self.segment_decoder = Some(segment_codec.decoder());
+        self.segment_codec = Some(segment_codec);
+        self
+    }
+    /// Get the frame compression of the codec.
+    pub fn compression(&self) -> MyCompression {
+        self.compression
+    }
+    /// Get the compression threshold of the codec.
+    pub fn compression_threshold(&self) -> usize {
+        self.compression_threshold
+    }
+    /// Get the segment codec (if any).
+    pub fn segment_codec(&self) -> Option<&SegmentCodec> {
+        self.segment_codec.as_ref()
+    }
+}
+
+/// Get the total length (including the header) of the frame which starts with the given header.
+pub fn frame_length(header: &[u8]) -> anyhow::Result<usize> {
+    ensure!(header.len() >= FRAME_HEADER_LENGTH, "Buffer is too small!");
+    let body_length = i32::from_be_bytes(header[5..9].try_into()?);
+    ensure!(body_length >= 0, "Invalid frame body length {}!", body_length);
+    let length = FRAME_HEADER_LENGTH + body_length as usize;
+    ensure!(
+        length <= MAX_FRAME_LENGTH,
+        "Frame length {} exceeds the max frame length!",
+        length
+    );
+    Ok(length)
+}
+
+impl tokio_util::codec::Decoder for FrameCodec {
+    type Item = Decoder;
+    type Error = anyhow::Error;
+    fn decode(&mut self, src: &mut BytesMut) -> anyhow::Result<Option<Decoder>> {
+        let frame = if let Some(segment_decoder) = self.segment_decoder.as_mut() {
+            // the segment decoder keeps the incomplete segments
+            if !src.is_empty() {
+                segment_decoder.push(&src.split());
+            }
+            match segment_decoder.next_frame()? {
+                Some(frame) => frame,
+                None => return Ok(None),
+            }
+        } else {
+            if src.len() < FRAME_HEADER_LENGTH {
+                return Ok(None);
+            }
+            let length = frame_length(&src[..FRAME_HEADER_LENGTH])?;
+            if src.len() < length {
+                // reserve the rest of the frame
+                src.reserve(length - src.len());
+                return Ok(None);
+            }
+            src.split_to(length).to_vec()
+        };
+        Ok(Some(Decoder::new(
+            self.compression.decompress_frame(frame)?,
+            UNCOMPRESSED,
+        )?))
+    }
+}
+
+impl tokio_util::codec::Encoder<(i16, Vec<u8>)> for FrameCodec {
+    type Error = anyhow::Error;
+    /// Encode the frame with the given stream id.
+    fn encode(&mut self, (stream_id, mut frame): (i16, Vec<u8>), dst: &mut BytesMut) -> anyhow::Result<()> {
+        ensure!(frame.len() >= FRAME_HEADER_LENGTH, "Buffer is too small!");
+        frame[2..4].copy_from_slice(&stream_id.to_be_bytes());
+        if let Some(segment_codec) = self.segment_codec.as_ref() {
+            let frame = upgrade_request(&frame)?;
+            dst.extend_from_slice(&segment_codec.encode_new(&frame)?);
+        } else {
+            dst.extend_from_slice(&self.compression.compress_frame(frame, self.compression_threshold)?);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::{compression::LZ4, frame::header::COMPRESSION, Consistency, Frame, Query, Statements};
+    use tokio_util::codec::{Decoder as _, Encoder as _};
+
+    fn query() -> Vec<u8> {
+        let Query(payload) = Query::new()
+            .statement("SELECT * FROM system.local")
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        payload
+    }
+
+    #[test]
+    fn frame_codec_partial_frames() {
+        let mut codec = FrameCodec::new(MyCompression(&UNCOMPRESSED));
+        let mut encoded = BytesMut::new();
+        codec.encode((3, query()), &mut encoded).unwrap();
+        codec.encode((-2, query()), &mut encoded).unwrap();
+        // feed the frames byte per byte
+        let mut src = BytesMut::new();
+        let mut frames = Vec::new();
+        for byte in encoded.iter() {
+            src.extend_from_slice(&[*byte]);
+            if let Some(frame) = codec.decode(&mut src).unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert!(src.is_empty());
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].stream().unwrap(), 3);
+        assert_eq!(frames[1].stream().unwrap(), -2);
+        assert_eq!(&frames[1].buffer_as_ref()[4..], &query()[4..]);
+    }
+
+    #[test]
+    fn frame_codec_compression() {
+        let mut codec = FrameCodec::new(MyCompression(&LZ4));
+        let mut src = BytesMut::new();
+        codec.encode((1, query()), &mut src).unwrap();
+        assert_eq!(src[1] & COMPRESSION, COMPRESSION);
+        let frame = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(frame.buffer_as_ref()[1] & COMPRESSION, 0);
+        assert_eq!(&frame.buffer_as_ref()[4..], &query()[4..]);
+    }
+
+    #[test]
+    fn frame_codec_segments() {
+        let mut codec = FrameCodec::new(MyCompression(&UNCOMPRESSED)).with_segments(SegmentCodec::lz4());
+        let mut src = BytesMut::new();
+        codec.encode((7, query()), &mut src).unwrap();
+        let frame = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(frame.protocol_version().unwrap(), crate::cql::PROTOCOL_V5);
+        assert_eq!(frame.stream().unwrap(), 7);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+    }
+
+    #[test]
+    fn frame_codec_invalid_length() {
+        let mut codec = FrameCodec::new(MyCompression(&UNCOMPRESSED));
+        let mut src = BytesMut::from(&[4, 0, 0, 1, 8, 0xff, 0xff, 0xff, 0xff][..]);
+        assert!(codec.decode(&mut src).is_err());
+    }
+}
@@ -3,8 +3,14 @@
 
 //! This crates implements the uncompressed, LZ4, and snappy compression methods for Cassandra.
 
-use super::frame::header::COMPRESSION;
-use std::convert::TryInto;
+use super::frame::{
+    header::COMPRESSION,
+    opcode::{OPTIONS, STARTUP},
+};
+use std::{
+    convert::TryInto,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 /// This compression thread provides the buffer compression/decompression methods for uncompressed/Lz4/snappy.
 pub trait Compression: Sync {
@@ -126,51 +132,58 @@ impl Compression for Uncompressed {
         Ok(buffer)
     }
 }
-/// `MY_COMPRESSION` is used to enable user defines a global compression structure.
-pub static mut MY_COMPRESSION: MyCompression = MyCompression(&UNCOMPRESSED);
-/// `MY_COMPRESSION_FLAG` is used to indicate whether the compression is applied to the buffer.
-pub static mut MY_COMPRESSION_FLAG: u8 = 0;
 #[derive(Copy, Clone)]
-/// `MyCompression` structure provides a higher-level wrapper to let the user use a compresion method, i.e.,
-/// ````LZ4`, `SNAPPY`, or `UNCOMPRESSED`.
+/// `MyCompression` structure provides a higher-level wrapper of the compression method of a connection, i.e.,
+/// `LZ4`, `SNAPPY`, `UNCOMPRESSED` or a user defined one.
 pub struct MyCompression(pub &'static dyn Compression);
 
-impl MyCompression {
-    /// Set the global compression syte as `LZ4`.
-    #[allow(unused)]
-    pub fn set_lz4() {
-        unsafe {
-            MY_COMPRESSION = MyCompression(&LZ4);
-            MY_COMPRESSION_FLAG = 1;
-        }
+impl Default for MyCompression {
+    fn default() -> Self {
+        MyCompression(&UNCOMPRESSED)
     }
-    /// Set the global compression syte as `SNAPPY`.
-    #[allow(unused)]
-    pub fn set_snappy() {
-        unsafe {
-            MY_COMPRESSION = MyCompression(&SNAPPY);
-            MY_COMPRESSION_FLAG = 1;
-        }
+}
+
+impl MyCompression {
+    /// Pick the first compression of the client preferences, which is in the `COMPRESSION` list supported
+    /// by the server.
+    pub fn negotiate(preferences: &[MyCompression], supported: &[String]) -> Option<MyCompression> {
+        preferences.iter().copied().find(|compression| {
+            compression
+                .option()
+                .into_iter()
+                .any(|option| supported.iter().any(|supported| supported == option))
+        })
     }
-    /// Set the global compression syte as `UNCOMPRESSED`.
-    #[allow(unused)]
-    pub fn set_uncompressed() {
-        unsafe {
-            MY_COMPRESSION = MyCompression(&UNCOMPRESSED);
-            MY_COMPRESSION_FLAG = 0;
+    /// Compress the request frame and set its compression flag, the `STARTUP` and `OPTIONS` frames and the frames
+    /// with a body smaller than the `threshold` (in bytes) are never compressed.
+    pub fn compress_frame(&self, frame: Vec<u8>, threshold: usize) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(frame.len() >= 9, "Buffer is too small!");
+        if !self.compresses(&frame, threshold) {
+            return Ok(frame);
         }
+        let mut frame = self.compress(frame)?;
+        frame[1] |= COMPRESSION;
+        Ok(frame)
     }
-    /// Get the global structure, `MY_COMPRESSION`.
-    pub fn get() -> impl Compression {
-        unsafe { MY_COMPRESSION }
+    /// Check if the request frame is compressed by `compress_frame`, so the frames which are sent as they are
+    /// don't have to be copied.
+    pub fn compresses(&self, frame: &[u8], threshold: usize) -> bool {
+        self.option().is_some()
+            && frame.len() >= 9
+            && frame.len() - 9 >= threshold
+            && frame[1] & COMPRESSION == 0
+            && frame[4] != STARTUP
+            && frame[4] != OPTIONS
     }
-    /// Get the global structure, `MY_COMPRESSION_FLAG`.
-    pub fn flag() -> u8 {
-        unsafe { MY_COMPRESSION_FLAG }
-    }
-    /// Get the `Option` of compression method type, i.e., `lz4`, `snappy`, or None.
-    pub fn option() -> Option<&'static str> {
-        unsafe { MY_COMPRESSION }.option()
+    /// Decompress the response frame (if its compression flag is set) and clear its compression flag.
+    pub fn decompress_frame(&self, frame: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(frame.len() >= 9, "Buffer is too small!");
+        if frame[1] & COMPRESSION == 0 {
+            return Ok(frame);
+        }
+        let mut frame = self.decompress(frame)?;
+        frame[1] &= !COMPRESSION;
+        Ok(frame)
     }
 }
 
@@ -187,3 +200,79 @@ impl Compression for MyCompression {
         self.0.compress(buffer)
     }
 }
+
+#[derive(Debug, Default)]
+/// The counters of the request frames written by a connection, used to tune the compression threshold.
+pub struct CompressionStats {
+    frames: AtomicU64,
+    compressed_frames: AtomicU64,
+    uncompressed_bytes: AtomicU64,
+    compressed_bytes: AtomicU64,
+}
+
+impl CompressionStats {
+    /// Record a written frame with its length before and after the compression.
+    pub fn record(&self, uncompressed_length: usize, compressed_length: usize) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        if compressed_length < uncompressed_length {
+            self.compressed_frames.fetch_add(1, Ordering::Relaxed);
+        }
+        self.uncompressed_bytes
+            .fetch_add(uncompressed_length as u64, Ordering::Relaxed);
+        self.compressed_bytes
+            .fetch_add(compressed_length as u64, Ordering::Relaxed);
+    }
+    /// Get the number of written frames.
+    pub fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+    /// Get the number of written frames which were shrunk by the compression.
+    pub fn compressed_frames(&self) -> u64 {
+        self.compressed_frames.load(Ordering::Relaxed)
+    }
+    /// Get the total length of the written frames before the compression.
+    pub fn uncompressed_bytes(&self) -> u64 {
+        self.uncompressed_bytes.load(Ordering::Relaxed)
+    }
+    /// Get the total length of the written frames after the compression.
+    pub fn compressed_bytes(&self) -> u64 {
+        self.compressed_bytes.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_compression() {
+        let preferences = [MyCompression(&SNAPPY), MyCompression(&LZ4)];
+        let supported = vec!["lz4".to_string()];
+        let compression = MyCompression::negotiate(&preferences, &supported).unwrap();
+        assert_eq!(compression.option(), Some("lz4"));
+        assert!(MyCompression::negotiate(&preferences, &[]).is_none());
+        assert!(MyCompression::negotiate(&[MyCompression::default()], &supported).is_none());
+    }
+
+    #[test]
+    fn compression_threshold() {
+        let mut frame = vec![4, 0, 0, 1, super::super::frame::opcode::QUERY, 0, 0, 0, 0];
+        frame.extend(vec![0; 100]);
+        let compression = MyCompression(&LZ4);
+        assert!(!compression.compresses(&frame, 101));
+        assert!(compression.compresses(&frame, 100));
+        assert!(!MyCompression::default().compresses(&frame, 0));
+        let uncompressed = compression.compress_frame(frame.clone(), 101).unwrap();
+        assert_eq!(uncompressed, frame);
+        let compressed = compression.compress_frame(frame.clone(), 100).unwrap();
+        assert_eq!(compressed[1] & COMPRESSION, COMPRESSION);
+        assert!(compressed.len() < frame.len());
+        let stats = CompressionStats::default();
+        stats.record(frame.len(), uncompressed.len());
+        stats.record(frame.len(), compressed.len());
+        assert_eq!(stats.frames(), 2);
+        assert_eq!(stats.compressed_frames(), 1);
+        assert_eq!(stats.uncompressed_bytes(), 2 * frame.len() as u64);
+        assert_eq!(stats.compressed_bytes(), (frame.len() + compressed.len()) as u64);
+    }
+}
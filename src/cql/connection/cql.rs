@@ -1,34 +1,45 @@
 // Copyright 2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use super::tokens::{Info, Row};
+use super::{
+    stream::CqlStream,
+    tls::TlsConfig,
+    tokens::{Info, Peer, Peers, PeersV2, Row},
+};
 use crate::cql::{
-    compression::{MyCompression, UNCOMPRESSED},
+    codec::FrameCodec,
+    compression::{Compression, MyCompression},
     frame::{
         auth_challenge::AuthChallenge,
         auth_response::{AllowAllAuth, AuthResponse, Authenticator, PasswordAuth},
+        auth_success::AuthSuccess,
         authenticate::Authenticate,
         consistency::Consistency,
         decoder::{Decoder, Frame},
+        event::{Event, EventType},
+        header::{PROTOCOL_V4, PROTOCOL_V5},
         options::Options,
         query::Query,
+        register::{Register, RegisterBuilder},
         rows::Rows,
         startup::Startup,
         supported::Supported,
         Statements,
     },
+    segment::SegmentCodec,
 };
 use anyhow::{anyhow, bail, ensure};
+use bytes::BytesMut;
 use port_scanner::{local_port_available, request_open_port};
 use std::{
     collections::HashMap,
-    convert::TryInto,
     net::{IpAddr, Ipv4Addr, SocketAddr},
 };
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::{TcpSocket, TcpStream},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpSocket,
 };
+use tokio_util::codec::{Decoder as _, Encoder as _};
 
 #[derive(Default)]
 /// CqlBuilder struct to establish cql connection with the provided configurations
@@ -36,22 +47,32 @@ pub struct CqlBuilder<Auth: Authenticator> {
     address: Option<SocketAddr>,
     local_addr: Option<SocketAddr>,
     tokens: bool,
+    peers: bool,
     recv_buffer_size: Option<u32>,
     send_buffer_size: Option<u32>,
     shard_id: Option<u16>,
     authenticator: Option<Auth>,
+    tls: Option<TlsConfig>,
+    protocol_version: Option<u8>,
+    compression: Vec<MyCompression>,
+    compression_threshold: usize,
     cql: Option<Cql>,
 }
 /// CQL connection structure.
 pub struct Cql {
-    stream: TcpStream,
+    stream: CqlStream,
     address: SocketAddr,
     tokens: Option<Vec<i64>>,
     dc: Option<String>,
+    rack: Option<String>,
+    peers: Option<Vec<Peer>>,
     shard_id: u16,
     shard_aware_port: u16,
     shard_count: u16,
     msb: u8,
+    protocol_version: u8,
+    codec: FrameCodec,
+    buffer: BytesMut,
 }
 
 impl<Auth: Authenticator> CqlBuilder<Auth> {
@@ -79,6 +100,11 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
         self.tokens = true;
         self
     }
+    /// Instruct the builder to fetch the peers of the scylla node from the connection once established
+    pub fn peers(mut self) -> Self {
+        self.peers = true;
+        self
+    }
     /// Instruct the builder to connect to scylla shard with shard_id
     pub fn shard_id(mut self, shard_id: u16) -> Self {
         self.shard_id.replace(shard_id);
@@ -89,10 +115,55 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
         self.authenticator.replace(auth);
         self
     }
+    /// Add an optional tls config to encrypt the connection
+    pub fn tls(mut self, tls: Option<TlsConfig>) -> Self {
+        self.tls = tls;
+        self
+    }
+    /// Set the highest native protocol version to negotiate, the connection falls back to v4
+    /// if the server doesn't support it. By default v5 is negotiated.
+    pub fn protocol_version(mut self, protocol_version: u8) -> Self {
+        self.protocol_version.replace(protocol_version);
+        self
+    }
+    /// Set the compressions supported by the client, in order of preference. The connection uses the first one
+    /// which is supported by the server (if any), v5 connections only support `lz4`.
+    pub fn compression(mut self, compression: Vec<MyCompression>) -> Self {
+        self.compression = compression;
+        self
+    }
+    /// Send the request frames with a body smaller than the threshold (in bytes) uncompressed, by default every
+    /// frame is compressed.
+    pub fn compression_threshold(mut self, compression_threshold: usize) -> Self {
+        self.compression_threshold = compression_threshold;
+        self
+    }
     fn set_local_addr(&mut self, local_addr: SocketAddr) {
         self.local_addr.replace(local_addr);
     }
     async fn connect(&mut self) -> anyhow::Result<()> {
+        let protocol_version = self.protocol_version.unwrap_or(PROTOCOL_V5);
+        ensure!(
+            protocol_version == PROTOCOL_V4 || protocol_version == PROTOCOL_V5,
+            "Unsupported CQL protocol version: {}",
+            protocol_version
+        );
+        if !self.connect_with(protocol_version).await? {
+            ensure!(protocol_version > PROTOCOL_V4, "CQL protocol v4 is not supported!");
+            // fall back to v4 on a new connection, as the server may close the rejected one
+            ensure!(
+                self.connect_with(PROTOCOL_V4).await?,
+                "CQL protocol v4 is not supported!"
+            );
+            // keep the negotiated version for the reconnects (if any)
+            self.protocol_version.replace(PROTOCOL_V4);
+        } else {
+            self.protocol_version.replace(protocol_version);
+        }
+        Ok(())
+    }
+    /// Establish the connection with the given protocol version, returns false if the server rejects the version.
+    async fn connect_with(&mut self, protocol_version: u8) -> anyhow::Result<bool> {
         let socket = TcpSocket::new_v4()?;
         if let Some(local_addr) = self.local_addr {
             // set client side port
@@ -105,18 +176,29 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
         if let Some(send_buffer_size) = self.send_buffer_size {
             socket.set_send_buffer_size(send_buffer_size)?
         }
-        let mut stream = socket
+        let stream = socket
             .connect(self.address.ok_or_else(|| anyhow!("Address does not exist!"))?)
             .await?;
+        // wrap the stream with tls (if configured)
+        let mut stream = match self.tls.as_ref() {
+            Some(tls) => CqlStream::Tls(Box::new(tls.connect(stream).await?)),
+            None => CqlStream::Tcp(stream),
+        };
+        // OPTIONS cannot be compressed as the client and protocol didn't yet settle on compression algo (if any)
+        let mut codec = FrameCodec::new(MyCompression::default());
+        let mut buffer = BytesMut::new();
         // create options frame
-        let Options(opt_buf) = Options::new().build();
+        let Options(mut opt_buf) = Options::new().build();
+        opt_buf[0] = protocol_version;
         // write_all options frame to stream
-        stream.write_all(&opt_buf).await?;
-        // collect_frame_response
-        let buffer = collect_frame_response(&mut stream).await?;
-        // Create Decoder from buffer. OPTIONS cannot be compressed as
-        // the client and protocol didn't yet settle on compression algo (if any)
-        let decoder = Decoder::new(buffer, UNCOMPRESSED)?;
+        write_frame(&mut stream, &mut codec, opt_buf).await?;
+        let decoder = read_frame(&mut stream, &mut codec, &mut buffer).await?;
+        // the server answers with its own (lower) version or a protocol error if the version is not supported
+        if protocol_version > PROTOCOL_V4
+            && (decoder.protocol_version()? < protocol_version || decoder.is_protocol_error()?)
+        {
+            return Ok(false);
+        }
         // make sure the frame response is not error
         if decoder.is_error()? {
             // check if response is_error.
@@ -136,43 +218,78 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
             .ok_or_else(|| anyhow!("Cannot read supported CQL version!"))?;
         // insert the supported_cql_version option into the options;
         options.insert("CQL_VERSION".to_owned(), cql_version.to_owned());
-        // insert the supported_compression option into the options if it was set.;
-        if let Some(compression) = MyCompression::option() {
-            options.insert("COMPRESSION".to_owned(), compression.to_owned());
+        // pick the preferred compression which is supported by the server (if any).
+        // v5 compresses the segments instead of the frames, which is only supported by lz4.
+        let preferences = self
+            .compression
+            .iter()
+            .copied()
+            .filter(|c| protocol_version == PROTOCOL_V4 || c.option() == Some("lz4"))
+            .collect::<Vec<_>>();
+        let compression = supported
+            .get_options()
+            .get("COMPRESSION")
+            .and_then(|supported| MyCompression::negotiate(&preferences, supported));
+        if let Some(option) = compression.and_then(|c| c.option()) {
+            options.insert("COMPRESSION".to_owned(), option.to_owned());
         }
         // create startup frame using the selected options;
-        let Startup(startup_buf) = Startup::new().options(&options).build();
+        let Startup(mut startup_buf) = Startup::new().options(&options).build();
+        startup_buf[0] = protocol_version;
         // write_all startup frame to stream;
-        stream.write_all(&startup_buf).await?;
-        let buffer = collect_frame_response(&mut stream).await?;
-        // Create Decoder from buffer.
-        let decoder = Decoder::new(buffer, MyCompression::get())?;
+        write_frame(&mut stream, &mut codec, startup_buf).await?;
+        // the frames are compressed once the server answered the startup (v4 only)
+        let frame_compression = match compression {
+            Some(compression) if protocol_version == PROTOCOL_V4 => compression,
+            _ => MyCompression::default(),
+        };
+        let mut codec = FrameCodec::new(frame_compression).with_compression_threshold(self.compression_threshold);
+        let decoder = read_frame(&mut stream, &mut codec, &mut buffer).await?;
+        // v5 frames are carried in segments once the server answered the startup
+        if protocol_version >= PROTOCOL_V5 {
+            codec = codec.with_segments(if compression.is_some() {
+                SegmentCodec::lz4()
+            } else {
+                SegmentCodec::uncompressed()
+            });
+        }
         if decoder.is_authenticate()? {
             if self.authenticator.is_none() {
                 Authenticate::new(&decoder)?;
                 bail!("CQL connection not ready due to authenticator is not provided");
             }
-            let auth_response = AuthResponse::new()
-                .token(
-                    self.authenticator
-                        .as_ref()
-                        .ok_or_else(|| anyhow!("Failed to read Auth Response!"))?,
-                )
-                .build(MyCompression::get())?;
-            // write_all auth_response frame to stream;
-            stream.write_all(&auth_response.0).await?;
-            // collect_frame_response
-            let buffer = collect_frame_response(&mut stream).await?;
-            // Create Decoder from buffer.
-            let decoder = Decoder::new(buffer, MyCompression::get())?;
-            if decoder.is_error()? {
-                bail!("CQL connection not ready due to CqlError: {}", decoder.get_error()?);
-            }
-            if decoder.is_auth_challenge()? {
-                AuthChallenge::new(&decoder)?;
-                bail!("CQL connection not ready due to Unsupported Auth Challenge");
+            // every connection runs its own sasl exchange
+            let mut authenticator = self
+                .authenticator
+                .clone()
+                .ok_or_else(|| anyhow!("Failed to read Auth Response!"))?;
+            let mut auth_response = AuthResponse::new().token(&mut authenticator)?.build()?;
+            let mut challenges = 0;
+            loop {
+                // write_all auth_response frame to stream;
+                write_frame(&mut stream, &mut codec, auth_response.0).await?;
+                let decoder = read_frame(&mut stream, &mut codec, &mut buffer).await?;
+                if decoder.is_error()? {
+                    bail!("CQL connection not ready due to CqlError: {}", decoder.get_error()?);
+                }
+                if decoder.is_auth_challenge()? {
+                    // a misbehaving server must not keep the connection in the sasl exchange forever
+                    challenges += 1;
+                    ensure!(
+                        challenges <= MAX_AUTH_CHALLENGES,
+                        "CQL connection not ready due to too many auth challenges!"
+                    );
+                    let challenge = AuthChallenge::new(&decoder)?;
+                    auth_response = AuthResponse::new()
+                        .challenge(&mut authenticator, challenge.token())
+                        .map_err(|e| anyhow!("CQL connection not ready due to {}", e))?
+                        .build()?;
+                    continue;
+                }
+                ensure!(decoder.is_auth_success()?, "Authorization unsuccessful!");
+                authenticator.success(AuthSuccess::new(&decoder)?.token().map(|token| token.as_slice()))?;
+                break;
             }
-            ensure!(decoder.is_auth_success()?, "Authorization unsuccessful!");
         } else if decoder.is_error()? {
             bail!("CQL connection not ready due to CqlError: {}", decoder.get_error()?);
         } else {
@@ -221,8 +338,23 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
             shard_count: nr_shard,
             msb: ignore_msb,
             dc: None,
+            rack: None,
+            peers: None,
+            protocol_version,
+            codec,
+            buffer,
         };
         self.cql.replace(cqlconn);
+        Ok(true)
+    }
+    /// Fetch the requested info through the established connection
+    async fn fetch(&self, cqlconn: &mut Cql) -> anyhow::Result<()> {
+        if self.tokens {
+            cqlconn.fetch_tokens().await?;
+        }
+        if self.peers {
+            cqlconn.fetch_peers().await?;
+        }
         Ok(())
     }
     /// Build the CqlBuilder and then try to connect
@@ -257,9 +389,7 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
                                 let mut cqlconn = self.cql.take().ok_or_else(|| anyhow!("No CQL connection!"))?;
                                 // assert shard_id is equal
                                 assert_eq!(cqlconn.shard_id, requested_shard_id);
-                                if self.tokens {
-                                    cqlconn.fetch_tokens().await?;
-                                }
+                                self.fetch(&mut cqlconn).await?;
                                 return Ok(cqlconn);
                             } else {
                                 // continue, request new open_port
@@ -275,9 +405,7 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
                             let mut cqlconn = self.cql.take().ok_or_else(|| anyhow!("No CQL connection!"))?;
                             // assert shard_id is equal
                             assert_eq!(cqlconn.shard_id, requested_shard_id);
-                            if self.tokens {
-                                cqlconn.fetch_tokens().await?;
-                            }
+                            self.fetch(&mut cqlconn).await?;
                             return Ok(cqlconn);
                         }
                     }
@@ -293,9 +421,7 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
                             Ok(_) => {
                                 let mut cqlconn = self.cql.take().ok_or_else(|| anyhow!("No CQL connection!"))?;
                                 if cqlconn.shard_id == requested_shard_id {
-                                    if self.tokens {
-                                        cqlconn.fetch_tokens().await?;
-                                    }
+                                    self.fetch(&mut cqlconn).await?;
                                     return Ok(cqlconn);
                                 } else if requested_shard_id >= cqlconn.shard_count {
                                     // error as it's impossible to connect to shard_id doesn't exist
@@ -318,24 +444,20 @@ impl<Auth: Authenticator> CqlBuilder<Auth> {
                 }
             } else {
                 // FOUND connection
-                if self.tokens {
-                    cqlconn.fetch_tokens().await?;
-                }
+                self.fetch(&mut cqlconn).await?;
                 return Ok(cqlconn);
             }
         } else {
             // shard_id not provided, so connection is ready
-            if self.tokens {
-                cqlconn.fetch_tokens().await?;
-            }
+            self.fetch(&mut cqlconn).await?;
             return Ok(cqlconn);
         }
     }
 }
 
-impl Into<TcpStream> for Cql {
-    fn into(self) -> TcpStream {
-        self.stream
+impl From<Cql> for CqlStream {
+    fn from(cql: Cql) -> Self {
+        cql.stream
     }
 }
 
@@ -355,15 +477,17 @@ impl Cql {
         // create query to fetch tokens and info from system.local;
         let query = fetch_tokens_query()?;
         // write_all query to the stream
-        self.stream.write_all(query.as_slice()).await?;
-        // collect_frame_response
-        let buffer = collect_frame_response(&mut self.stream).await?;
-        // Create Decoder from buffer.
-        let decoder = Decoder::new(buffer, MyCompression::get())?;
+        write_frame(&mut self.stream, &mut self.codec, query).await?;
+        let decoder = read_frame(&mut self.stream, &mut self.codec, &mut self.buffer).await?;
 
         if decoder.is_rows()? {
-            let Row { data_center, tokens } = Info::new(decoder)?.next().ok_or(anyhow!("No info found!"))?;
+            let Row {
+                data_center,
+                rack,
+                tokens,
+            } = Info::new(decoder)?.next().ok_or(anyhow!("No info found!"))?;
             self.dc.replace(data_center);
+            self.rack.replace(rack);
             self.tokens.replace(
                 tokens
                     .iter()
@@ -376,18 +500,82 @@ impl Cql {
         }
         Ok(())
     }
+    async fn fetch_peers(&mut self) -> anyhow::Result<()> {
+        // system.peers_v2 (if present) has the native port of every peer
+        let decoder = self.query(PEERS_V2_STATEMENT).await?;
+        let peers = if decoder.is_rows()? {
+            PeersV2::new(decoder)?
+                .filter_map(|row| row.into_peer(self.address.port()))
+                .collect()
+        } else {
+            let decoder = self.query(PEERS_STATEMENT).await?;
+            if decoder.is_rows()? {
+                Peers::new(decoder)?
+                    .filter_map(|row| row.into_peer(self.address.port()))
+                    .collect()
+            } else {
+                bail!(
+                    "CQL connection didn't return peers due to CqlError: {}",
+                    decoder.get_error()?
+                );
+            }
+        };
+        self.peers.replace(peers);
+        Ok(())
+    }
+    /// Send the query through the connection and wait for its response
+    async fn query(&mut self, statement: &str) -> anyhow::Result<Decoder> {
+        let Query(payload) = Query::new()
+            .statement(statement)
+            .consistency(Consistency::One)
+            .build()?;
+        write_frame(&mut self.stream, &mut self.codec, payload).await?;
+        read_frame(&mut self.stream, &mut self.codec, &mut self.buffer).await
+    }
+    /// Register the connection for the given server event types
+    pub async fn register(&mut self, events: &[EventType]) -> anyhow::Result<()> {
+        let Register(payload) = RegisterBuilder::new().events(events).build();
+        write_frame(&mut self.stream, &mut self.codec, payload).await?;
+        let decoder = read_frame(&mut self.stream, &mut self.codec, &mut self.buffer).await?;
+        if decoder.is_error()? {
+            bail!(
+                "CQL connection not registered due to CqlError: {}",
+                decoder.get_error()?
+            );
+        }
+        ensure!(decoder.is_ready()?, "CQL connection not registered!");
+        Ok(())
+    }
+    /// Wait for the next server event, the connection must be registered for its event type
+    pub async fn next_event(&mut self) -> anyhow::Result<Event> {
+        loop {
+            let decoder = read_frame(&mut self.stream, &mut self.codec, &mut self.buffer).await?;
+            // the registered connection is not supposed to receive anything else
+            if decoder.is_event()? {
+                return Event::new(&decoder);
+            }
+        }
+    }
     /// Get the socket stream behind the cql connection
-    pub fn stream(&mut self) -> &mut TcpStream {
+    pub fn stream(&mut self) -> &mut CqlStream {
         &mut self.stream
     }
     /// Take the associated tokens of the connected scylla node
     pub fn take_tokens(&mut self) -> Option<Vec<i64>> {
         self.tokens.take()
     }
+    /// Take the peers of the connected scylla node
+    pub fn take_peers(&mut self) -> Option<Vec<Peer>> {
+        self.peers.take()
+    }
     /// Take DataCenter of the connected scylla node
     pub fn take_dc(&mut self) -> Option<String> {
         self.dc.take()
     }
+    /// Take Rack of the connected scylla node
+    pub fn take_rack(&mut self) -> Option<String> {
+        self.rack.take()
+    }
     /// Get the shard_id of the connection
     pub fn shard_id(&self) -> u16 {
         self.shard_id
@@ -404,25 +592,145 @@ impl Cql {
     pub fn msb(&self) -> u8 {
         self.msb
     }
+    /// Get the negotiated native protocol version of the connection
+    pub fn protocol_version(&self) -> u8 {
+        self.protocol_version
+    }
+    /// Get the frame compression of the connection, which is uncompressed for protocol v5 as the segments are
+    /// compressed instead (see `segment_codec`)
+    pub fn compression(&self) -> MyCompression {
+        self.codec.compression()
+    }
+    /// Get the compression threshold of the connection, the request frames with a smaller body are sent
+    /// uncompressed
+    pub fn compression_threshold(&self) -> usize {
+        self.codec.compression_threshold()
+    }
+    /// Get the segment codec of the connection, which is only used by protocol v5
+    pub fn segment_codec(&self) -> Option<SegmentCodec> {
+        self.codec.segment_codec().copied()
+    }
 }
 
-async fn collect_frame_response(stream: &mut TcpStream) -> anyhow::Result<Vec<u8>> {
-    // create buffer
-    let mut buffer = vec![0; 9];
-    // read response into buffer
-    stream.read_exact(&mut buffer).await?;
-    let body_length = i32::from_be_bytes(buffer[5..9].try_into()?);
-    // extend buffer
-    buffer.resize((body_length + 9).try_into()?, 0);
-    stream.read_exact(&mut buffer[9..]).await?;
-    Ok(buffer)
+/// Write the frame to the stream through the frame codec, which upgrades it to v5 and frames it into segments
+/// once the codec has the segment codec, otherwise it's compressed with the frame compression.
+async fn write_frame<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    codec: &mut FrameCodec,
+    frame: Vec<u8>,
+) -> anyhow::Result<()> {
+    let mut buffer = BytesMut::new();
+    // the connection has a single request in flight
+    codec.encode((0, frame), &mut buffer)?;
+    stream.write_all(&buffer).await?;
+    Ok(())
 }
 
-/// Query the data center, and tokens from the ScyllaDB.
+/// Read the next frame from the stream through the frame codec, the buffer keeps the bytes of the next frames.
+async fn read_frame<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    codec: &mut FrameCodec,
+    buffer: &mut BytesMut,
+) -> anyhow::Result<Decoder> {
+    loop {
+        if let Some(decoder) = codec.decode(buffer)? {
+            return Ok(decoder);
+        }
+        ensure!(stream.read_buf(buffer).await? != 0, "CQL connection closed!");
+    }
+}
+
+/// The max number of auth challenges of the sasl exchange, SCRAM only needs two.
+const MAX_AUTH_CHALLENGES: usize = 8;
+
+/// Query the peers with their native transport address and port (Cassandra 4.0+).
+const PEERS_V2_STATEMENT: &str =
+    "SELECT peer, native_address, native_port, data_center, rack, tokens FROM system.peers_v2";
+/// Query the peers with their rpc address.
+const PEERS_STATEMENT: &str = "SELECT peer, rpc_address, data_center, rack, tokens FROM system.peers";
+
+/// Query the data center, rack, and tokens from the ScyllaDB.
 fn fetch_tokens_query() -> anyhow::Result<Vec<u8>> {
     let Query(payload) = Query::new()
-        .statement("SELECT data_center, tokens FROM system.local")
+        .statement("SELECT data_center, rack, tokens FROM system.local")
         .consistency(Consistency::One)
         .build()?;
     Ok(payload)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::frame::opcode;
+    use std::convert::TryInto;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Answer every challenge with an empty token.
+    #[derive(Clone, Default)]
+    struct EmptyAuth;
+
+    impl Authenticator for EmptyAuth {
+        fn token(&mut self) -> anyhow::Result<Vec<u8>> {
+            Ok(0i32.to_be_bytes().to_vec())
+        }
+        fn challenge(&mut self, _token: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+            Ok(0i32.to_be_bytes().to_vec())
+        }
+    }
+
+    async fn read_request(stream: &mut TcpStream) -> anyhow::Result<()> {
+        let mut header = [0; 9];
+        stream.read_exact(&mut header).await?;
+        let mut body = vec![0; i32::from_be_bytes(header[5..9].try_into()?) as usize];
+        stream.read_exact(&mut body).await?;
+        Ok(())
+    }
+
+    fn response(opcode: u8, body: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x84, 0, 0, 0, opcode];
+        frame.extend(&(body.len() as i32).to_be_bytes());
+        frame.extend(body);
+        frame
+    }
+
+    /// Answer the OPTIONS with the CQL version, the STARTUP with AUTHENTICATE and every AUTH_RESPONSE with
+    /// another AUTH_CHALLENGE.
+    async fn serve_endless_challenges(mut stream: TcpStream) -> anyhow::Result<()> {
+        read_request(&mut stream).await?;
+        let mut supported = vec![0, 1, 0, 11];
+        supported.extend(b"CQL_VERSION");
+        supported.extend(&[0, 1, 0, 5]);
+        supported.extend(b"3.4.5");
+        stream.write_all(&response(opcode::SUPPORTED, &supported)).await?;
+        read_request(&mut stream).await?;
+        let authenticator = "org.apache.cassandra.auth.PasswordAuthenticator";
+        let mut authenticate = (authenticator.len() as u16).to_be_bytes().to_vec();
+        authenticate.extend(authenticator.as_bytes());
+        stream.write_all(&response(opcode::AUTHENTICATE, &authenticate)).await?;
+        loop {
+            read_request(&mut stream).await?;
+            stream
+                .write_all(&response(opcode::AUTH_CHALLENGE, &0i32.to_be_bytes()))
+                .await?;
+        }
+    }
+
+    #[tokio::test]
+    async fn bound_auth_challenges() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_endless_challenges(stream));
+            }
+        });
+        let result = CqlBuilder::<EmptyAuth>::new()
+            .address(address)
+            .authenticator(EmptyAuth)
+            .protocol_version(PROTOCOL_V4)
+            .build()
+            .await;
+        let error = result.err().expect("the sasl exchange must be bounded");
+        assert!(error.to_string().contains("too many auth challenges"));
+    }
+}
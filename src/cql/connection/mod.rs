@@ -2,6 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 mod cql;
+mod stream;
+mod tls;
 mod tokens;
 
 pub use cql::{Cql, CqlBuilder};
+pub use stream::CqlStream;
+pub use tls::{TlsConfig, TlsConfigBuilder};
+pub use tokens::Peer;
@@ -0,0 +1,149 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, bail, ensure};
+use std::{fs, io::BufReader, path::Path, sync::Arc};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{
+        internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys},
+        Certificate, ClientConfig, RootCertStore, ServerCertVerified, ServerCertVerifier, TLSError, WebPKIVerifier,
+    },
+    webpki::{DNSNameRef, Error as WebPkiError},
+    TlsConnector,
+};
+
+/// The server name used for the handshake when no SNI is configured.
+const DEFAULT_SERVER_NAME: &str = "localhost";
+
+#[derive(Clone)]
+/// TLS configuration used to encrypt the cql connections
+pub struct TlsConfig {
+    client_config: Arc<ClientConfig>,
+    sni: Option<String>,
+}
+
+#[derive(Default)]
+/// TlsConfigBuilder struct to create a TlsConfig from PEM encoded certificates and keys
+pub struct TlsConfigBuilder {
+    ca: Vec<u8>,
+    client_cert: Option<(Vec<u8>, Vec<u8>)>,
+    sni: Option<String>,
+    skip_hostname_verification: bool,
+}
+
+impl TlsConfig {
+    /// Create new tls config builder struct
+    pub fn builder() -> TlsConfigBuilder {
+        TlsConfigBuilder::default()
+    }
+    /// Get the server name indication (if any)
+    pub fn sni(&self) -> Option<&str> {
+        self.sni.as_deref()
+    }
+    /// Perform the TLS handshake over an already connected tcp stream
+    pub(crate) async fn connect(&self, stream: TcpStream) -> anyhow::Result<TlsStream<TcpStream>> {
+        let server_name = self.sni.as_deref().unwrap_or(DEFAULT_SERVER_NAME);
+        let dns_name = DNSNameRef::try_from_ascii_str(server_name)
+            .map_err(|_| anyhow!("Invalid TLS server name: {}", server_name))?;
+        let connector = TlsConnector::from(self.client_config.clone());
+        Ok(connector.connect(dns_name, stream).await?)
+    }
+}
+
+impl TlsConfigBuilder {
+    /// Add PEM encoded CA certificate(s) used to verify the server certificate
+    pub fn ca_pem(mut self, pem: &[u8]) -> Self {
+        self.ca.extend_from_slice(pem);
+        self.ca.push(b'\n');
+        self
+    }
+    /// Add CA certificate(s) from a PEM file
+    pub fn ca_file<P: AsRef<Path>>(self, path: P) -> anyhow::Result<Self> {
+        let pem = fs::read(path)?;
+        Ok(self.ca_pem(&pem))
+    }
+    /// Add PEM encoded client certificate chain and private key for client authentication
+    pub fn client_cert_pem(mut self, cert: &[u8], key: &[u8]) -> Self {
+        self.client_cert.replace((cert.to_vec(), key.to_vec()));
+        self
+    }
+    /// Add client certificate chain and private key from PEM files
+    pub fn client_cert_file<P: AsRef<Path>>(self, cert_path: P, key_path: P) -> anyhow::Result<Self> {
+        let cert = fs::read(cert_path)?;
+        let key = fs::read(key_path)?;
+        Ok(self.client_cert_pem(&cert, &key))
+    }
+    /// Set the server name indication, which is also used to verify the server certificate
+    pub fn sni(mut self, server_name: &str) -> Self {
+        self.sni.replace(server_name.to_owned());
+        self
+    }
+    /// Enable or disable hostname verification. The certificate chain is verified either way
+    pub fn verify_hostname(mut self, verify: bool) -> Self {
+        self.skip_hostname_verification = !verify;
+        self
+    }
+    /// Build the TlsConfig
+    pub fn build(self) -> anyhow::Result<TlsConfig> {
+        let mut config = ClientConfig::new();
+        let (valid, _) = config
+            .root_store
+            .add_pem_file(&mut BufReader::new(self.ca.as_slice()))
+            .map_err(|_| anyhow!("Cannot parse CA certificates!"))?;
+        ensure!(valid > 0, "No valid CA certificate provided!");
+        if let Some((cert, key)) = self.client_cert {
+            let cert_chain =
+                certs(&mut BufReader::new(cert.as_slice())).map_err(|_| anyhow!("Cannot parse client certificate!"))?;
+            ensure!(!cert_chain.is_empty(), "No client certificate provided!");
+            let mut keys = pkcs8_private_keys(&mut BufReader::new(key.as_slice()))
+                .map_err(|_| anyhow!("Cannot parse client private key!"))?;
+            if keys.is_empty() {
+                keys = rsa_private_keys(&mut BufReader::new(key.as_slice()))
+                    .map_err(|_| anyhow!("Cannot parse client private key!"))?;
+            }
+            let key = keys
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("No client private key provided!"))?;
+            config.set_single_client_cert(cert_chain, key)?;
+        }
+        if self.sni.is_none() {
+            if !self.skip_hostname_verification {
+                bail!("Hostname verification requires a server name (SNI)!");
+            }
+            config.enable_sni = false;
+        }
+        if self.skip_hostname_verification {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(NoHostnameVerifier(WebPKIVerifier::new())));
+        }
+        Ok(TlsConfig {
+            client_config: Arc::new(config),
+            sni: self.sni,
+        })
+    }
+}
+
+/// Verifies the server certificate chain, but accepts certificates that are not valid for the server name.
+struct NoHostnameVerifier(WebPKIVerifier);
+
+impl ServerCertVerifier for NoHostnameVerifier {
+    fn verify_server_cert(
+        &self,
+        roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        dns_name: DNSNameRef,
+        ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        match self
+            .0
+            .verify_server_cert(roots, presented_certs, dns_name, ocsp_response)
+        {
+            Err(TLSError::WebPKIError(WebPkiError::CertNotValidForName)) => Ok(ServerCertVerified::assertion()),
+            res => res,
+        }
+    }
+}
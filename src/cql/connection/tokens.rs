@@ -8,13 +8,108 @@ use crate::{
     },
     rows,
 };
-use std::convert::TryInto;
+use std::{
+    convert::TryInto,
+    net::{IpAddr, SocketAddr},
+};
 
 rows!(
     rows: Info,
     row: Row {
         data_center: String,
+        rack: String,
         tokens: Vec<String>,
     },
     row_into: Row
 );
+
+rows!(
+    rows: PeersV2,
+    row: PeerV2Row {
+        peer: Option<IpAddr>,
+        native_address: Option<IpAddr>,
+        native_port: Option<i32>,
+        data_center: Option<String>,
+        rack: Option<String>,
+        tokens: Option<Vec<String>>,
+    },
+    row_into: PeerV2Row
+);
+
+rows!(
+    rows: Peers,
+    row: PeerRow {
+        peer: Option<IpAddr>,
+        rpc_address: Option<IpAddr>,
+        data_center: Option<String>,
+        rack: Option<String>,
+        tokens: Option<Vec<String>>,
+    },
+    row_into: PeerRow
+);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A peer of the connected scylla node, as listed in its `system.peers` (or `system.peers_v2`) table.
+pub struct Peer {
+    /// The address of the peer native transport
+    pub address: SocketAddr,
+    /// The data center of the peer
+    pub data_center: String,
+    /// The rack of the peer
+    pub rack: String,
+    /// The tokens owned by the peer
+    pub tokens: Vec<i64>,
+}
+
+impl Peer {
+    fn new(
+        peer: Option<IpAddr>,
+        rpc_address: Option<IpAddr>,
+        port: u16,
+        data_center: Option<String>,
+        rack: Option<String>,
+        tokens: Option<Vec<String>>,
+    ) -> Option<Self> {
+        // the peer listens on all interfaces, so it's reachable on its broadcast address
+        let ip = rpc_address.filter(|ip| !ip.is_unspecified()).or(peer)?;
+        Some(Self {
+            address: SocketAddr::new(ip, port),
+            data_center: data_center.unwrap_or_default(),
+            rack: rack.unwrap_or_default(),
+            tokens: tokens
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|token| token.parse().ok())
+                .collect(),
+        })
+    }
+}
+
+impl PeerV2Row {
+    /// Convert the row into a peer, the port falls back to the given one if it's not set
+    pub(crate) fn into_peer(self, port: u16) -> Option<Peer> {
+        let port = self.native_port.map(|port| port as u16).unwrap_or(port);
+        Peer::new(
+            self.peer,
+            self.native_address,
+            port,
+            self.data_center,
+            self.rack,
+            self.tokens,
+        )
+    }
+}
+
+impl PeerRow {
+    /// Convert the row into a peer listening on the given port
+    pub(crate) fn into_peer(self, port: u16) -> Option<Peer> {
+        Peer::new(
+            self.peer,
+            self.rpc_address,
+            port,
+            self.data_center,
+            self.rack,
+            self.tokens,
+        )
+    }
+}
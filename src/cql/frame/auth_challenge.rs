@@ -9,7 +9,6 @@ use super::decoder::{bytes, Decoder, Frame};
 
 /// The Autentication Challenge structure with the token field.
 pub(crate) struct AuthChallenge {
-    #[allow(unused)]
     token: Option<Vec<u8>>,
 }
 
@@ -18,6 +17,10 @@ impl AuthChallenge {
     pub(crate) fn new(decoder: &Decoder) -> anyhow::Result<Self> {
         Self::try_from(decoder.body()?)
     }
+    /// Get the challenge token.
+    pub(crate) fn token(&self) -> Option<&[u8]> {
+        self.token.as_deref()
+    }
 }
 
 impl TryFrom<&[u8]> for AuthChallenge {
@@ -3,17 +3,28 @@
 
 //! This module implements the response part of the challenge–response authentication.
 
-use super::opcode::AUTH_RESPONSE;
-use crate::cql::compression::{Compression, MyCompression};
+use super::{header::PROTOCOL_V4, opcode::AUTH_RESPONSE};
+use crate::cql::compression::{Compression, UNCOMPRESSED};
+use anyhow::bail;
 use std::convert::TryInto;
 
 /// Blanket cql frame header for AUTH_RESPONSE frame.
-const AUTH_RESPONSE_HEADER: &'static [u8] = &[4, 0, 0, 0, AUTH_RESPONSE, 0, 0, 0, 0];
+const AUTH_RESPONSE_HEADER: &'static [u8] = &[PROTOCOL_V4, 0, 0, 0, AUTH_RESPONSE, 0, 0, 0, 0];
 
-/// The Authenticator structure with the token field.
+/// The Authenticator trait, which drives the (possibly multi-round) SASL exchange of a connection.
+///
+/// Every token is returned as cql `[bytes]`, i.e. prefixed with its `[int]` length.
 pub trait Authenticator: Clone + Default {
-    /// Get the token in the Authenticator.
-    fn token(&self) -> Vec<u8>;
+    /// Get the initial token in the Authenticator.
+    fn token(&mut self) -> anyhow::Result<Vec<u8>>;
+    /// Evaluate the token of an `AUTH_CHALLENGE` and return the token of the next `AUTH_RESPONSE`.
+    fn challenge(&mut self, _token: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+        bail!("Unsupported Auth Challenge")
+    }
+    /// Evaluate the final token of the `AUTH_SUCCESS`.
+    fn success(&mut self, _token: Option<&[u8]>) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 #[derive(Clone, Default)]
 /// The unit structure used for letting all users be autenticated.
@@ -21,9 +32,9 @@ pub struct AllowAllAuth;
 
 impl Authenticator for AllowAllAuth {
     // Return token as [bytes]
-    fn token(&self) -> Vec<u8> {
+    fn token(&mut self) -> anyhow::Result<Vec<u8>> {
         // [int] n, followed by n-bytes
-        vec![0, 0, 0, 1, 0]
+        Ok(vec![0, 0, 0, 1, 0])
     }
 }
 
@@ -47,16 +58,77 @@ impl PasswordAuth {
 }
 
 impl Authenticator for PasswordAuth {
-    fn token(&self) -> Vec<u8> {
+    fn token(&mut self) -> anyhow::Result<Vec<u8>> {
         // compute length in advance
         let length = self.user.len() + self.pass.len() + 2;
         let mut token = Vec::new();
-        token.extend_from_slice(&i32::to_be_bytes(length.try_into().unwrap()));
+        token.extend_from_slice(&i32::to_be_bytes(length.try_into()?));
         token.push(0);
         token.extend_from_slice(self.user.as_bytes());
         token.push(0);
         token.extend_from_slice(self.pass.as_bytes());
-        token
+        Ok(token)
+    }
+}
+
+/// The object safe counterpart of the `Authenticator` trait, which enables boxing different authenticators.
+pub trait DynAuthenticator: Send + Sync {
+    /// Get the initial token in the Authenticator.
+    fn token(&mut self) -> anyhow::Result<Vec<u8>>;
+    /// Evaluate the token of an `AUTH_CHALLENGE` and return the token of the next `AUTH_RESPONSE`.
+    fn challenge(&mut self, token: Option<&[u8]>) -> anyhow::Result<Vec<u8>>;
+    /// Evaluate the final token of the `AUTH_SUCCESS`.
+    fn success(&mut self, token: Option<&[u8]>) -> anyhow::Result<()>;
+    /// Clone the authenticator into a new box.
+    fn boxed_clone(&self) -> Box<dyn DynAuthenticator>;
+}
+
+impl<T: 'static + Authenticator + Send + Sync> DynAuthenticator for T {
+    fn token(&mut self) -> anyhow::Result<Vec<u8>> {
+        Authenticator::token(self)
+    }
+    fn challenge(&mut self, token: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+        Authenticator::challenge(self, token)
+    }
+    fn success(&mut self, token: Option<&[u8]>) -> anyhow::Result<()> {
+        Authenticator::success(self, token)
+    }
+    fn boxed_clone(&self) -> Box<dyn DynAuthenticator> {
+        Box::new(self.clone())
+    }
+}
+
+/// A boxed authenticator, which lets the same runtime use different authenticators (i.e. per node).
+pub struct BoxedAuthenticator(Box<dyn DynAuthenticator>);
+
+impl BoxedAuthenticator {
+    /// Box the provided authenticator.
+    pub fn new<T: 'static + Authenticator + Send + Sync>(authenticator: T) -> Self {
+        Self(Box::new(authenticator))
+    }
+}
+
+impl Clone for BoxedAuthenticator {
+    fn clone(&self) -> Self {
+        Self(self.0.boxed_clone())
+    }
+}
+
+impl Default for BoxedAuthenticator {
+    fn default() -> Self {
+        Self::new(AllowAllAuth)
+    }
+}
+
+impl Authenticator for BoxedAuthenticator {
+    fn token(&mut self) -> anyhow::Result<Vec<u8>> {
+        self.0.token()
+    }
+    fn challenge(&mut self, token: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+        self.0.challenge(token)
+    }
+    fn success(&mut self, token: Option<&[u8]>) -> anyhow::Result<()> {
+        self.0.success(token)
     }
 }
 
@@ -70,16 +142,25 @@ impl AuthResponse {
         AuthResponse(buffer)
     }
     /// Update the response token to be the token from autenticator.
-    pub(crate) fn token(mut self, authenticator: &impl Authenticator) -> Self {
-        let token = authenticator.token();
+    pub(crate) fn token(mut self, authenticator: &mut impl Authenticator) -> anyhow::Result<Self> {
+        let token = authenticator.token()?;
         self.0.extend(token);
-        self
+        Ok(self)
+    }
+    /// Update the response token to be the answer of the autenticator to the server challenge.
+    pub(crate) fn challenge(
+        mut self,
+        authenticator: &mut impl Authenticator,
+        challenge: Option<&[u8]>,
+    ) -> anyhow::Result<Self> {
+        let token = authenticator.challenge(challenge)?;
+        self.0.extend(token);
+        Ok(self)
     }
-    /// Build a response frame with a assigned compression type.
-    pub(crate) fn build(mut self, compression: impl Compression) -> anyhow::Result<Self> {
-        // apply compression flag(if any to the header)
-        self.0[1] |= MyCompression::flag();
-        self.0 = compression.compress(self.0)?;
+    /// Build a response frame, the frame is compressed by the connection which carries it.
+    pub(crate) fn build(mut self) -> anyhow::Result<Self> {
+        // adjust the body length
+        self.0 = UNCOMPRESSED.compress(self.0)?;
         Ok(self)
     }
 }
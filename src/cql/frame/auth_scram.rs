@@ -0,0 +1,224 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the SCRAM-SHA-256 SASL mechanism (RFC 5802, RFC 7677).
+
+use super::auth_response::Authenticator;
+use anyhow::{anyhow, bail, ensure};
+use ring::{
+    constant_time::verify_slices_are_equal,
+    digest::{digest, SHA256},
+    hmac, pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use std::{convert::TryInto, num::NonZeroU32};
+
+/// The length of the random client nonce in bytes.
+const NONCE_LENGTH: usize = 18;
+/// The GS2 header of the client-first message (no channel binding, no authzid).
+const GS2_HEADER: &str = "n,,";
+
+#[derive(Clone)]
+/// The SCRAM-SHA-256 autentication structure with the user and password fields.
+pub struct ScramSha256Auth {
+    user: String,
+    pass: String,
+    state: ScramState,
+}
+
+#[derive(Clone)]
+enum ScramState {
+    /// The client-first message was not sent yet.
+    Initial,
+    /// The client-first message was sent, awaiting the server-first message.
+    ClientFirst { nonce: String, client_first_bare: String },
+    /// The client-final message was sent, awaiting the server-final message.
+    ClientFinal { server_signature: Vec<u8> },
+    /// The server signature was verified.
+    Done,
+}
+
+impl Default for ScramSha256Auth {
+    fn default() -> Self {
+        ScramSha256Auth::new("cassandra".to_owned(), "cassandra".to_owned())
+    }
+}
+
+impl ScramSha256Auth {
+    /// Create a new user with account and the corresponding password.
+    pub fn new(user: String, pass: String) -> Self {
+        Self {
+            user,
+            pass,
+            state: ScramState::Initial,
+        }
+    }
+    fn client_first(&mut self) -> anyhow::Result<Vec<u8>> {
+        let mut nonce = [0; NONCE_LENGTH];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("Cannot generate SCRAM client nonce!"))?;
+        let nonce = base64::encode(nonce);
+        let client_first_bare = format!("n={},r={}", escape_username(&self.user), nonce);
+        let message = format!("{}{}", GS2_HEADER, client_first_bare);
+        self.state = ScramState::ClientFirst {
+            nonce,
+            client_first_bare,
+        };
+        Ok(message.into_bytes())
+    }
+    fn client_final(&mut self, server_first: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let (nonce, client_first_bare) = match &self.state {
+            ScramState::ClientFirst {
+                nonce,
+                client_first_bare,
+            } => (nonce, client_first_bare),
+            _ => bail!("Unexpected SCRAM server-first message!"),
+        };
+        let server_first = std::str::from_utf8(server_first)?;
+        let mut server_nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+        for attribute in server_first.split(',') {
+            match attribute.split_at(attribute.find('=').unwrap_or(0)) {
+                ("r", value) => server_nonce = Some(&value[1..]),
+                ("s", value) => salt = Some(base64::decode(&value[1..])?),
+                ("i", value) => iterations = Some(value[1..].parse::<u32>()?),
+                ("e", value) => bail!("SCRAM authentication failed: {}", &value[1..]),
+                _ => (),
+            }
+        }
+        let server_nonce = server_nonce.ok_or_else(|| anyhow!("SCRAM server-first message has no nonce!"))?;
+        ensure!(
+            server_nonce.starts_with(nonce.as_str()),
+            "SCRAM server nonce does not match the client nonce!"
+        );
+        let salt = salt.ok_or_else(|| anyhow!("SCRAM server-first message has no salt!"))?;
+        let iterations = iterations
+            .and_then(NonZeroU32::new)
+            .ok_or_else(|| anyhow!("SCRAM server-first message has no valid iteration count!"))?;
+        let mut salted_password = [0; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            &salt,
+            self.pass.as_bytes(),
+            &mut salted_password,
+        );
+        let salted_key = hmac::Key::new(hmac::HMAC_SHA256, &salted_password);
+        let client_key = hmac::sign(&salted_key, b"Client Key");
+        let stored_key = digest(&SHA256, client_key.as_ref());
+        let client_final_without_proof = format!("c={},r={}", base64::encode(GS2_HEADER), server_nonce);
+        let auth_message = format!("{},{},{}", client_first_bare, server_first, client_final_without_proof);
+        let client_signature = hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, stored_key.as_ref()),
+            auth_message.as_bytes(),
+        );
+        let client_proof: Vec<u8> = client_key
+            .as_ref()
+            .iter()
+            .zip(client_signature.as_ref())
+            .map(|(k, s)| k ^ s)
+            .collect();
+        let server_key = hmac::sign(&salted_key, b"Server Key");
+        let server_signature = hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, server_key.as_ref()),
+            auth_message.as_bytes(),
+        );
+        self.state = ScramState::ClientFinal {
+            server_signature: server_signature.as_ref().to_vec(),
+        };
+        Ok(format!("{},p={}", client_final_without_proof, base64::encode(client_proof)).into_bytes())
+    }
+    fn verify_server_final(&mut self, server_final: &[u8]) -> anyhow::Result<()> {
+        let server_signature = match &self.state {
+            ScramState::ClientFinal { server_signature } => server_signature,
+            _ => bail!("Unexpected SCRAM server-final message!"),
+        };
+        let server_final = std::str::from_utf8(server_final)?;
+        if let Some(error) = server_final.strip_prefix("e=") {
+            bail!("SCRAM authentication failed: {}", error);
+        }
+        let verifier = server_final
+            .split(',')
+            .find_map(|attribute| attribute.strip_prefix("v="))
+            .ok_or_else(|| anyhow!("SCRAM server-final message has no verifier!"))?;
+        verify_slices_are_equal(&base64::decode(verifier)?, server_signature)
+            .map_err(|_| anyhow!("SCRAM server signature mismatch!"))?;
+        self.state = ScramState::Done;
+        Ok(())
+    }
+}
+
+impl Authenticator for ScramSha256Auth {
+    fn token(&mut self) -> anyhow::Result<Vec<u8>> {
+        Ok(encode_bytes(&self.client_first()?))
+    }
+    fn challenge(&mut self, token: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+        let token = token.ok_or_else(|| anyhow!("SCRAM challenge has no token!"))?;
+        match self.state {
+            ScramState::ClientFirst { .. } => Ok(encode_bytes(&self.client_final(token)?)),
+            // some servers send the server-final message as a challenge and expect an empty response
+            ScramState::ClientFinal { .. } => {
+                self.verify_server_final(token)?;
+                Ok(encode_bytes(&[]))
+            }
+            _ => bail!("Unexpected SCRAM challenge!"),
+        }
+    }
+    fn success(&mut self, token: Option<&[u8]>) -> anyhow::Result<()> {
+        match (&self.state, token) {
+            (ScramState::Done, _) => Ok(()),
+            (ScramState::ClientFinal { .. }, Some(token)) => self.verify_server_final(token),
+            _ => bail!("SCRAM authentication did not complete!"),
+        }
+    }
+}
+
+/// Escape the reserved characters of the username, see RFC 5802 section 5.1
+fn escape_username(user: &str) -> String {
+    user.replace('=', "=3D").replace(',', "=2C")
+}
+
+/// Encode the message as cql [bytes].
+fn encode_bytes(message: &[u8]) -> Vec<u8> {
+    let mut token = Vec::with_capacity(4 + message.len());
+    token.extend_from_slice(&i32::to_be_bytes(message.len().try_into().unwrap()));
+    token.extend_from_slice(message);
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scram_sha256_exchange() {
+        // test vector of RFC 7677 section 3
+        let mut auth = ScramSha256Auth::new("user".to_owned(), "pencil".to_owned());
+        auth.state = ScramState::ClientFirst {
+            nonce: "rOprNGfwEbeRWgbNEkqO".to_owned(),
+            client_first_bare: "n=user,r=rOprNGfwEbeRWgbNEkqO".to_owned(),
+        };
+        let server_first = b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096";
+        let client_final = auth.challenge(Some(server_first)).unwrap();
+        assert_eq!(
+            &client_final[4..],
+            &b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="[..]
+        );
+        assert!(auth
+            .success(Some(b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="))
+            .is_ok());
+    }
+
+    #[test]
+    fn scram_sha256_rejects_invalid_server_signature() {
+        let mut auth = ScramSha256Auth::new("user".to_owned(), "pencil".to_owned());
+        let client_first = auth.token().unwrap();
+        let client_first = std::str::from_utf8(&client_first[4..]).unwrap();
+        let nonce = client_first.rsplit("r=").next().unwrap();
+        let server_first = format!("r={}server,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096", nonce);
+        auth.challenge(Some(server_first.as_bytes())).unwrap();
+        assert!(auth.success(Some(b"v=AAAA")).is_err());
+    }
+}
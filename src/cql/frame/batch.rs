@@ -6,14 +6,19 @@
 use super::{
     batchflags::*,
     consistency::Consistency,
-    encoder::{ColumnEncoder, BE_8_BYTES_LEN, BE_NULL_BYTES_LEN, BE_UNSET_BYTES_LEN},
+    encoder::{encode_bytes_map, ColumnEncoder, BE_8_BYTES_LEN, BE_NULL_BYTES_LEN, BE_UNSET_BYTES_LEN},
+    header::{CUSTOM_PAYLOAD, PROTOCOL_V4, TRACING},
     opcode::BATCH,
     Statements, Values, MD5_BE_LENGTH,
 };
-use crate::cql::compression::{Compression, MyCompression};
+use crate::cql::compression::{Compression, UNCOMPRESSED};
+use std::collections::HashMap;
 
 /// Blanket cql frame header for BATCH frame.
-const BATCH_HEADER: &'static [u8] = &[4, 0, 0, 0, BATCH, 0, 0, 0, 0];
+const BATCH_HEADER: &'static [u8] = &[PROTOCOL_V4, 0, 0, 0, BATCH, 0, 0, 0, 0];
+
+/// The index of the batch query count, which follows the header and the batch type.
+const BATCH_QUERY_COUNT_INDEX: usize = 10;
 
 /// The batch frame.
 pub struct Batch(pub Vec<u8>);
@@ -48,6 +53,7 @@ pub enum BatchTypes {
 pub struct BatchBuilder<Type: Copy + Into<u8>, Stage> {
     buffer: Vec<u8>,
     query_count: u16,
+    query_count_index: usize,
     batch_type: Type,
     stage: Stage,
 }
@@ -104,7 +110,9 @@ pub struct BatchValues {
 }
 
 /// Gating type for batch flags
-pub struct BatchFlags;
+pub struct BatchFlags {
+    flags: u8,
+}
 
 /// Gating type for batch timestamp
 pub struct BatchTimestamp;
@@ -112,6 +120,14 @@ pub struct BatchTimestamp;
 /// Gating type for completed batch
 pub struct BatchBuild;
 
+impl<Type: Copy + Into<u8>, Stage> BatchBuilder<Type, Stage> {
+    /// Request tracing of the batch, the tracing id is returned in the response header.
+    pub fn tracing(mut self) -> Self {
+        self.buffer[1] |= TRACING;
+        self
+    }
+}
+
 impl BatchBuilder<BatchTypeUnset, BatchHeader> {
     /// Create a new batch builder
     pub fn new() -> BatchBuilder<BatchTypeUnset, BatchType> {
@@ -120,6 +136,7 @@ impl BatchBuilder<BatchTypeUnset, BatchHeader> {
         BatchBuilder {
             buffer,
             query_count: 0,
+            query_count_index: BATCH_QUERY_COUNT_INDEX,
             batch_type: BatchTypeUnset,
             stage: BatchType,
         }
@@ -131,6 +148,7 @@ impl BatchBuilder<BatchTypeUnset, BatchHeader> {
         BatchBuilder {
             buffer,
             query_count: 0,
+            query_count_index: BATCH_QUERY_COUNT_INDEX,
             batch_type: BatchTypeUnset,
             stage: BatchType,
         }
@@ -138,6 +156,14 @@ impl BatchBuilder<BatchTypeUnset, BatchHeader> {
 }
 
 impl BatchBuilder<BatchTypeUnset, BatchType> {
+    /// Set the custom payload of the Batch frame, which is passed to the custom query handler of the server.
+    pub fn custom_payload(mut self, custom_payload: &HashMap<String, Vec<u8>>) -> Self {
+        let start = self.buffer.len();
+        self.buffer[1] |= CUSTOM_PAYLOAD;
+        encode_bytes_map(custom_payload, &mut self.buffer);
+        self.query_count_index += self.buffer.len() - start;
+        self
+    }
     /// Set the batch type in the Batch frame. See https://cassandra.apache.org/doc/latest/cql/dml.html#batch
     pub fn batch_type<Type: Copy + Into<u8>>(mut self, batch_type: Type) -> BatchBuilder<Type, BatchStatementOrId> {
         // push batch_type and pad zero querycount
@@ -145,6 +171,7 @@ impl BatchBuilder<BatchTypeUnset, BatchType> {
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type,
             stage: BatchStatementOrId,
         }
@@ -156,6 +183,7 @@ impl BatchBuilder<BatchTypeUnset, BatchType> {
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: BatchTypeLogged,
             stage: BatchStatementOrId,
         }
@@ -167,6 +195,7 @@ impl BatchBuilder<BatchTypeUnset, BatchType> {
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: BatchTypeUnlogged,
             stage: BatchStatementOrId,
         }
@@ -178,6 +207,7 @@ impl BatchBuilder<BatchTypeUnset, BatchType> {
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: BatchTypeCounter,
             stage: BatchStatementOrId,
         }
@@ -199,6 +229,7 @@ impl<Type: Copy + Into<u8>> Statements for BatchBuilder<Type, BatchStatementOrId
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: self.batch_type,
             stage: BatchValues { value_count: 0, index },
         }
@@ -216,6 +247,7 @@ impl<Type: Copy + Into<u8>> Statements for BatchBuilder<Type, BatchStatementOrId
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: self.batch_type,
             stage: BatchValues { value_count: 0, index },
         }
@@ -256,12 +288,13 @@ impl<Type: Copy + Into<u8>> Statements for BatchBuilder<Type, BatchValues> {
         self.buffer.extend(&i32::to_be_bytes(statement.len() as i32));
         self.buffer.extend(statement.bytes());
         self.query_count += 1; // update querycount
-                               // pad zero value_count for the query
-        self.buffer.extend(&[0, 0]);
         let index = self.buffer.len();
+        // pad zero value_count for the query
+        self.buffer.extend(&[0, 0]);
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: self.batch_type,
             stage: BatchValues { value_count: 0, index },
         }
@@ -276,12 +309,13 @@ impl<Type: Copy + Into<u8>> Statements for BatchBuilder<Type, BatchValues> {
         self.buffer.extend(&MD5_BE_LENGTH);
         self.buffer.extend(id);
         self.query_count += 1;
+        let index = self.buffer.len();
         // pad zero value_count for the query
         self.buffer.extend(&[0, 0]);
-        let index = self.buffer.len();
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: self.batch_type,
             stage: BatchValues { value_count: 0, index },
         }
@@ -297,8 +331,9 @@ impl<Type: Copy + Into<u8>> BatchBuilder<Type, BatchValues> {
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: self.batch_type,
-            stage: BatchFlags,
+            stage: BatchFlags { flags: NOFLAGS },
         }
     }
 }
@@ -307,11 +342,12 @@ impl<Type: Copy + Into<u8>> BatchBuilder<Type, BatchFlags> {
     /// Set the serial consistency in the Batch frame.
     pub fn serial_consistency(mut self, consistency: Consistency) -> BatchBuilder<Type, BatchTimestamp> {
         // add serial_consistency byte for batch flags
-        self.buffer.push(SERIAL_CONSISTENCY);
+        self.buffer.push(self.stage.flags | SERIAL_CONSISTENCY);
         self.buffer.extend(&u16::to_be_bytes(consistency as u16));
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: self.batch_type,
             stage: BatchTimestamp,
         }
@@ -319,25 +355,25 @@ impl<Type: Copy + Into<u8>> BatchBuilder<Type, BatchFlags> {
     /// Set the timestamp of the Batch frame.
     pub fn timestamp(mut self, timestamp: i64) -> BatchBuilder<Type, BatchBuild> {
         // add timestamp byte for batch flags
-        self.buffer.push(TIMESTAMP);
+        self.buffer.push(self.stage.flags | TIMESTAMP);
         self.buffer.extend(&BE_8_BYTES_LEN);
         self.buffer.extend(&i64::to_be_bytes(timestamp));
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: self.batch_type,
             stage: BatchBuild,
         }
     }
     /// Build a Batch frame.
     pub fn build(mut self) -> anyhow::Result<Batch> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
-        // add noflags byte for batch flags
-        self.buffer.push(NOFLAGS);
+        // add the flags byte for batch flags
+        self.buffer.push(self.stage.flags);
         // adjust the querycount
-        self.buffer[10..12].copy_from_slice(&u16::to_be_bytes(self.query_count));
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        self.buffer[self.query_count_index..][..2].copy_from_slice(&u16::to_be_bytes(self.query_count));
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         Ok(Batch(self.buffer))
     }
 }
@@ -351,17 +387,17 @@ impl<Type: Copy + Into<u8>> BatchBuilder<Type, BatchTimestamp> {
         BatchBuilder {
             buffer: self.buffer,
             query_count: self.query_count,
+            query_count_index: self.query_count_index,
             batch_type: self.batch_type,
             stage: BatchBuild,
         }
     }
     /// Build a Batch frame.
     pub fn build(mut self) -> anyhow::Result<Batch> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
         // adjust the querycount
-        self.buffer[10..12].copy_from_slice(&u16::to_be_bytes(self.query_count));
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        self.buffer[self.query_count_index..][..2].copy_from_slice(&u16::to_be_bytes(self.query_count));
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         Ok(Batch(self.buffer))
     }
 }
@@ -369,11 +405,10 @@ impl<Type: Copy + Into<u8>> BatchBuilder<Type, BatchTimestamp> {
 impl<Type: Copy + Into<u8>> BatchBuilder<Type, BatchBuild> {
     /// Build a Batch frame.
     pub fn build(mut self) -> anyhow::Result<Batch> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
         // adjust the querycount
-        self.buffer[10..12].copy_from_slice(&u16::to_be_bytes(self.query_count));
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        self.buffer[self.query_count_index..][..2].copy_from_slice(&u16::to_be_bytes(self.query_count));
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         Ok(Batch(self.buffer))
     }
 }
@@ -405,4 +440,59 @@ mod tests {
             .build()
             .unwrap();
     }
+
+    #[test]
+    fn value_count_batch_builder_test() {
+        let Batch(payload) = Batch::new()
+            .logged()
+            .statement("A")
+            .value(&1i32)
+            .id(&[0; 16])
+            .value(&2i32)
+            .value(&3i32)
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert_eq!(&payload[9..12], &[0, 0, 2]);
+        // every query has the count of its own values
+        assert_eq!(&payload[12..20], &[0, 0, 0, 0, 1, b'A', 0, 1]);
+        assert_eq!(&payload[20..28], &[0, 0, 0, 4, 0, 0, 0, 1]);
+        assert_eq!(&payload[28..31], &[1, 0, 16]);
+        assert_eq!(&payload[47..49], &[0, 2]);
+        assert_eq!(&payload[49..65], &[0, 0, 0, 4, 0, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 3]);
+        assert_eq!(&payload[65..], &[0, 1, 0]);
+    }
+
+    #[test]
+    fn tracing_batch_builder_test() {
+        let Batch(payload) = Batch::new()
+            .tracing()
+            .unlogged()
+            .statement("INSERT_TX_QUERY")
+            .value(&"HASH_VALUE")
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert_eq!(payload[1] & TRACING, TRACING);
+    }
+
+    #[test]
+    fn custom_payload_batch_builder_test() {
+        let mut custom_payload = HashMap::new();
+        custom_payload.insert("key".to_string(), vec![1, 2, 3]);
+        let Batch(payload) = Batch::new()
+            .custom_payload(&custom_payload)
+            .unlogged()
+            .statement("INSERT_TX_QUERY")
+            .value(&"HASH_VALUE")
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert_eq!(payload[1] & CUSTOM_PAYLOAD, CUSTOM_PAYLOAD);
+        // <n><key><value> is followed by the batch type and the query count
+        assert_eq!(&payload[9..11], &[0, 1]);
+        assert_eq!(&payload[11..16], &[0, 3, b'k', b'e', b'y']);
+        assert_eq!(&payload[16..23], &[0, 0, 0, 3, 1, 2, 3]);
+        assert_eq!(&payload[23..26], &[1, 0, 1]);
+    }
 }
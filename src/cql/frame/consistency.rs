@@ -7,7 +7,7 @@ use anyhow::anyhow;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use std::convert::{TryFrom, TryInto};
-#[derive(Debug, FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 #[repr(u16)]
 /// The consistency level enum.
 pub enum Consistency {
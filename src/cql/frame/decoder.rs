@@ -4,17 +4,17 @@
 //! This module implements the frame decoder.
 
 use super::{
-    error, header, opcode, result,
+    dynamic, error, header, opcode, result,
     rows::{ColumnsCount, Flags, Metadata, PagingState},
 };
-use crate::cql::compression::{Compression, MyCompression};
+use crate::cql::compression::{Compression, UNCOMPRESSED};
 use anyhow::{anyhow, ensure};
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
     convert::{TryFrom, TryInto},
     hash::Hash,
     io::Cursor,
-    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     str,
 };
 /// RowsDecoder trait to decode the rows result from scylla
@@ -29,6 +29,32 @@ pub trait RowsDecoder<K, V> {
     }
 }
 
+/// Decode the first row of the rows result (if any), which is what most `RowsDecoder::try_decode` impls do,
+/// so they can be reduced to `decode_first_row(decoder)`.
+pub fn decode_first_row<R: super::Row>(decoder: Decoder) -> anyhow::Result<Option<R>> {
+    if decoder.is_rows()? {
+        Ok(R::rows_iter(decoder)?.next())
+    } else if decoder.is_error()? {
+        Err(anyhow!(decoder.get_error()?))
+    } else {
+        Err(anyhow!("Expected a rows result, got {}", frame_kind(&decoder)?))
+    }
+}
+
+/// Describe the kind of the response frame, which is its result kind if it's a RESULT.
+fn frame_kind(decoder: &Decoder) -> anyhow::Result<String> {
+    Ok(match decoder.opcode()? {
+        opcode::RESULT => match decoder.body_kind()? {
+            result::VOID => "a void result".to_string(),
+            result::SETKEYSPACE => "a set keyspace result".to_string(),
+            result::PREPARED => "a prepared result".to_string(),
+            result::SCHEMACHANGE => "a schema change result".to_string(),
+            kind => format!("the result kind {:#06x}", kind),
+        },
+        opcode => format!("the opcode {:#04x}", opcode),
+    })
+}
+
 /// VoidDecoder trait to decode the VOID result from scylla
 pub trait VoidDecoder {
     /// Try to decode the provided Decoder with an expected Void result
@@ -45,11 +71,12 @@ pub trait VoidDecoder {
     }
 }
 
+/// Create a Decoder from a frame which was already decompressed by the connection which received it.
 impl TryFrom<Vec<u8>> for Decoder {
     type Error = anyhow::Error;
 
     fn try_from(buffer: Vec<u8>) -> Result<Self, Self::Error> {
-        Decoder::new(buffer, MyCompression::get())
+        Decoder::new(buffer, UNCOMPRESSED)
     }
 }
 
@@ -81,6 +108,8 @@ pub trait Frame {
     fn is_supported(&self) -> anyhow::Result<bool>;
     /// Check whether the opcode is `READY`.
     fn is_ready(&self) -> anyhow::Result<bool>;
+    /// Check whether the opcode is `EVENT`.
+    fn is_event(&self) -> anyhow::Result<bool>;
     /// Check whether the body kind is `VOID`.
     fn is_void(&self) -> anyhow::Result<bool>;
     /// Check whether the body kind is `ROWS`.
@@ -135,6 +164,18 @@ pub trait Frame {
     fn paging_state(&self, has_more_pages: bool) -> anyhow::Result<PagingState>;
     /// The the metadata.
     fn metadata(&self) -> anyhow::Result<Metadata>;
+    /// Get the native protocol version of the frame, without the response direction bit.
+    fn protocol_version(&self) -> anyhow::Result<u8>;
+    /// Check whether the opcode is `RESULT`.
+    fn is_result(&self) -> anyhow::Result<bool>;
+    /// Check whether the body kind is `PREPARED`.
+    fn is_prepared(&self) -> anyhow::Result<bool>;
+    /// Check whether the body kind is `SET_KEYSPACE`.
+    fn is_set_keyspace(&self) -> anyhow::Result<bool>;
+    /// Check whether the body kind is `SCHEMA_CHANGE`.
+    fn is_schema_change(&self) -> anyhow::Result<bool>;
+    /// Get the result metadata id of a `PREPARED` result (protocol v5).
+    fn result_metadata_id(&self) -> anyhow::Result<Option<Vec<u8>>>;
 }
 /// The frame decoder structure.
 #[derive(Clone)]
@@ -161,6 +202,15 @@ impl Decoder {
     pub fn into_buffer(self) -> Vec<u8> {
         self.buffer
     }
+    /// Get the server warnings of the response, ie when a batch is too large or a tombstone threshold
+    /// is exceeded.
+    pub fn warnings(&self) -> Vec<String> {
+        self.header_flags.warnings().cloned().unwrap_or_default()
+    }
+    /// Get the custom payload of the response.
+    pub fn custom_payload(&self) -> HashMap<String, Vec<u8>> {
+        self.header_flags.custom_payload().cloned().unwrap_or_default()
+    }
 }
 
 #[allow(dead_code)]
@@ -169,7 +219,7 @@ impl Decoder {
 pub struct HeaderFlags {
     compression: bool,
     tracing: Option<[u8; 16]>,
-    custom_payload: bool,
+    custom_payload: Option<HashMap<String, Vec<u8>>>,
     warnings: Option<Vec<String>>,
     // this not a flag, but it indicates the body start in the buffer.
     body_start: usize,
@@ -178,7 +228,7 @@ pub struct HeaderFlags {
 #[allow(dead_code)]
 impl HeaderFlags {
     /// Create a new header flags.
-    pub fn new(buffer: &Vec<u8>) -> anyhow::Result<Self> {
+    pub fn new(buffer: &[u8]) -> anyhow::Result<Self> {
         let mut body_start = 9;
         let flags = buffer[1];
         let compression = flags & header::COMPRESSION == header::COMPRESSION;
@@ -205,7 +255,13 @@ impl HeaderFlags {
         } else {
             None
         };
-        let custom_payload = flags & header::CUSTOM_PAYLOAD == header::CUSTOM_PAYLOAD;
+        let custom_payload = if flags & header::CUSTOM_PAYLOAD == header::CUSTOM_PAYLOAD {
+            let (custom_payload, length) = bytes_map_with_returned_bytes_length(&buffer[body_start..])?;
+            body_start += length;
+            Some(custom_payload)
+        } else {
+            None
+        };
         Ok(Self {
             compression,
             tracing,
@@ -218,14 +274,30 @@ impl HeaderFlags {
     pub fn compression(&self) -> bool {
         self.compression
     }
+    /// Get the tracing id of the frame.
+    pub fn tracing_id(&self) -> Option<&[u8; 16]> {
+        self.tracing.as_ref()
+    }
     /// Take the tracing id of the frame.
     pub fn take_tracing_id(&mut self) -> Option<[u8; 16]> {
         self.tracing.take()
     }
+    /// Get the warnings of the frame.
+    pub fn warnings(&self) -> Option<&Vec<String>> {
+        self.warnings.as_ref()
+    }
     /// Take the warnings of the frame.
-    fn take_warnings(&mut self) -> Option<Vec<String>> {
+    pub fn take_warnings(&mut self) -> Option<Vec<String>> {
         self.warnings.take()
     }
+    /// Get the custom payload of the frame.
+    pub fn custom_payload(&self) -> Option<&HashMap<String, Vec<u8>>> {
+        self.custom_payload.as_ref()
+    }
+    /// Take the custom payload of the frame.
+    pub fn take_custom_payload(&mut self) -> Option<HashMap<String, Vec<u8>>> {
+        self.custom_payload.take()
+    }
 }
 
 impl Frame for Decoder {
@@ -238,7 +310,9 @@ impl Frame for Decoder {
         &self.header_flags
     }
     fn stream(&self) -> anyhow::Result<i16> {
-        todo!()
+        let buffer = self.buffer_as_ref();
+        ensure!(buffer.len() > 3, "Buffer is too small!");
+        Ok(i16::from_be_bytes(buffer[2..4].try_into()?))
     }
     fn opcode(&self) -> anyhow::Result<u8> {
         let buffer = self.buffer_as_ref();
@@ -277,6 +351,9 @@ impl Frame for Decoder {
     fn is_ready(&self) -> anyhow::Result<bool> {
         Ok(self.opcode()? == opcode::READY)
     }
+    fn is_event(&self) -> anyhow::Result<bool> {
+        Ok(self.opcode()? == opcode::EVENT)
+    }
     fn is_void(&self) -> anyhow::Result<bool> {
         Ok((self.opcode()? == opcode::RESULT) && (self.body_kind()? == result::VOID))
     }
@@ -395,8 +472,51 @@ impl Frame for Decoder {
     fn metadata(&self) -> anyhow::Result<Metadata> {
         let flags = self.rows_flags()?;
         let columns_count = self.columns_count()?;
-        let paging_state = self.paging_state(flags.has_more_pages())?;
-        Ok(Metadata::new(flags, columns_count, paging_state))
+        let mut paging_state = self.paging_state(flags.has_more_pages())?;
+        let buffer = self.buffer_as_ref();
+        let mut rows_start = paging_state.end();
+        if flags.metadata_changed() && self.protocol_version()? >= header::PROTOCOL_V5 {
+            // skip the new_metadata_id
+            rows_start = skip_short_bytes(buffer, rows_start)?;
+        }
+        let mut column_specs_start = None;
+        if !flags.no_metadata() {
+            // the column specs are only decoded by the rows which need them
+            column_specs_start = Some(rows_start);
+            rows_start = dynamic::skip_column_specs(buffer, rows_start, flags.global_table_spec(), columns_count)?;
+        }
+        paging_state.set_end(rows_start);
+        let mut metadata = Metadata::new(flags, columns_count, paging_state);
+        if let Some(column_specs_start) = column_specs_start {
+            metadata.set_column_specs_start(column_specs_start);
+        }
+        Ok(metadata)
+    }
+    fn protocol_version(&self) -> anyhow::Result<u8> {
+        Ok(self.version()? & 0x7F)
+    }
+    fn is_result(&self) -> anyhow::Result<bool> {
+        Ok(self.opcode()? == opcode::RESULT)
+    }
+    fn is_prepared(&self) -> anyhow::Result<bool> {
+        Ok((self.opcode()? == opcode::RESULT) && (self.body_kind()? == result::PREPARED))
+    }
+    fn is_set_keyspace(&self) -> anyhow::Result<bool> {
+        Ok((self.opcode()? == opcode::RESULT) && (self.body_kind()? == result::SETKEYSPACE))
+    }
+    fn is_schema_change(&self) -> anyhow::Result<bool> {
+        Ok((self.opcode()? == opcode::RESULT) && (self.body_kind()? == result::SCHEMACHANGE))
+    }
+    fn result_metadata_id(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(
+            if self.is_prepared()? && self.protocol_version()? >= header::PROTOCOL_V5 {
+                // <id><result_metadata_id>
+                let id_end = skip_short_bytes(self.body()?, 4)?;
+                Some(short_bytes(&self.body()?[id_end..])?)
+            } else {
+                None
+            },
+        )
     }
 }
 
@@ -480,6 +600,13 @@ impl ColumnDecoder for u8 {
     }
 }
 
+impl ColumnDecoder for bool {
+    fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+        ensure!(!slice.is_empty(), "Buffer is too small!");
+        Ok(slice[0] != 0)
+    }
+}
+
 impl ColumnDecoder for String {
     fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
         Ok(String::from_utf8(slice.to_vec())?)
@@ -553,6 +680,27 @@ where
     }
 }
 
+impl<E, S> ColumnDecoder for HashSet<E, S>
+where
+    E: Eq + Hash + ColumnDecoder,
+    S: ::std::hash::BuildHasher + Default,
+{
+    fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+        // a set is encoded as a list
+        Ok(Vec::<E>::try_decode(slice)?.into_iter().collect())
+    }
+}
+
+impl<E> ColumnDecoder for BTreeSet<E>
+where
+    E: Ord + ColumnDecoder,
+{
+    fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+        // a set is encoded as a list
+        Ok(Vec::<E>::try_decode(slice)?.into_iter().collect())
+    }
+}
+
 impl<K, V, S> ColumnDecoder for HashMap<K, V, S>
 where
     K: Eq + Hash + ColumnDecoder,
@@ -648,6 +796,17 @@ pub fn prepared_id(slice: &[u8]) -> anyhow::Result<[u8; 16]> {
     res.map_err(|e| anyhow!(e))
 }
 
+/// Check whether the uncompressed frame is a `PREPARED` result, without decoding it.
+pub fn is_prepared_result(frame: &[u8]) -> bool {
+    if frame.len() < 9 || frame[4] != opcode::RESULT {
+        return false;
+    }
+    HeaderFlags::new(frame)
+        .ok()
+        .and_then(|header_flags| frame.get(header_flags.body_start..(header_flags.body_start + 4)))
+        .is_some_and(|body_kind| body_kind == result::PREPARED.to_be_bytes())
+}
+
 /// Get hashmap of string to string vector from slice.
 pub fn string_multimap(slice: &[u8]) -> anyhow::Result<HashMap<String, Vec<String>>> {
     let length = u16::from_be_bytes(slice[0..2].try_into()?) as usize;
@@ -682,4 +841,81 @@ pub fn string_list_with_returned_bytes_length(slice: &[u8]) -> anyhow::Result<(V
     }
     Ok((list, s))
 }
-// todo inet fn (with port).
+
+/// Get the `bytes map` and its byte length from a u8 slice.
+pub fn bytes_map_with_returned_bytes_length(slice: &[u8]) -> anyhow::Result<(HashMap<String, Vec<u8>>, usize)> {
+    ensure!(slice.len() >= 2, "Buffer is too small!");
+    let length = u16::from_be_bytes(slice[0..2].try_into()?) as usize;
+    let mut map = HashMap::with_capacity(length);
+    let mut i = 2;
+    for _ in 0..length {
+        ensure!(slice.len() >= i + 2, "Buffer is too small!");
+        let key = string(&slice[i..])?;
+        // add [short] + string.len()
+        i += 2 + key.len();
+        ensure!(slice.len() >= i + 4, "Buffer is too small!");
+        let value = bytes(&slice[i..])?.unwrap_or_default();
+        // add [int] + bytes.len()
+        i += 4 + value.len();
+        map.insert(key, value);
+    }
+    Ok((map, i))
+}
+
+/// Get the `inet` (address with port) and its byte length from a u8 slice.
+pub fn inet(slice: &[u8]) -> anyhow::Result<(SocketAddr, usize)> {
+    ensure!(!slice.is_empty(), "Buffer is too small!");
+    let address_end = 1 + slice[0] as usize;
+    ensure!(slice.len() >= address_end + 4, "Buffer is too small!");
+    let ip = IpAddr::try_decode(&slice[1..address_end])?;
+    let port = i32::from_be_bytes(slice[address_end..(address_end + 4)].try_into()?);
+    Ok((SocketAddr::new(ip, port as u16), address_end + 4))
+}
+
+/// Skip the `short_bytes` at `i` in the buffer.
+fn skip_short_bytes(buffer: &[u8], i: usize) -> anyhow::Result<usize> {
+    ensure!(buffer.len() >= i + 2, "Buffer is too small!");
+    Ok(i + 2 + u16::from_be_bytes(buffer[i..(i + 2)].try_into()?) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::compression::UNCOMPRESSED;
+
+    #[test]
+    fn decode_warnings_and_custom_payload() {
+        let flags = header::TRACING | header::WARNING | header::CUSTOM_PAYLOAD;
+        let mut buffer = vec![0x84, flags, 0, 1, opcode::RESULT, 0, 0, 0, 0];
+        // tracing id
+        buffer.extend(&[7; 16]);
+        // warnings
+        buffer.extend(&[0, 1, 0, 18]);
+        buffer.extend(b"Batch is too large");
+        // custom payload
+        buffer.extend(&[0, 1, 0, 3]);
+        buffer.extend(b"key");
+        buffer.extend(&[0, 0, 0, 2, 4, 2]);
+        // void result
+        buffer.extend(&1i32.to_be_bytes());
+        let body_length = (buffer.len() - 9) as i32;
+        buffer[5..9].copy_from_slice(&body_length.to_be_bytes());
+        let decoder = Decoder::new(buffer, UNCOMPRESSED).unwrap();
+        assert_eq!(decoder.flags().tracing_id(), Some(&[7; 16]));
+        assert_eq!(decoder.warnings(), vec!["Batch is too large".to_string()]);
+        let mut custom_payload = HashMap::new();
+        custom_payload.insert("key".to_string(), vec![4, 2]);
+        assert_eq!(decoder.custom_payload(), custom_payload);
+        assert!(decoder.is_void().unwrap());
+    }
+
+    #[test]
+    fn decode_without_warnings() {
+        let mut buffer = vec![0x84, 0, 0, 1, opcode::RESULT, 0, 0, 0, 4];
+        buffer.extend(&1i32.to_be_bytes());
+        let decoder = Decoder::new(buffer, UNCOMPRESSED).unwrap();
+        assert!(decoder.warnings().is_empty());
+        assert!(decoder.custom_payload().is_empty());
+        assert!(decoder.is_void().unwrap());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the cql `duration` type, which is encoded as three signed vints.
+
+use super::{decoder::ColumnDecoder, encoder::ColumnEncoder};
+use anyhow::{anyhow, ensure};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+/// The cql duration, a number of months, days and nanoseconds.
+pub struct CqlDuration {
+    /// The number of months.
+    pub months: i32,
+    /// The number of days.
+    pub days: i32,
+    /// The number of nanoseconds.
+    pub nanoseconds: i64,
+}
+
+impl CqlDuration {
+    /// Create a new duration, all components must have the same sign.
+    pub fn new(months: i32, days: i32, nanoseconds: i64) -> Self {
+        Self {
+            months,
+            days,
+            nanoseconds,
+        }
+    }
+}
+
+impl ColumnEncoder for CqlDuration {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        let mut value = Vec::with_capacity(27);
+        encode_vint(self.months as i64, &mut value);
+        encode_vint(self.days as i64, &mut value);
+        encode_vint(self.nanoseconds, &mut value);
+        buffer.extend(&i32::to_be_bytes(value.len() as i32));
+        buffer.extend(value);
+    }
+}
+
+impl ColumnDecoder for CqlDuration {
+    fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+        let (months, i) = decode_vint(slice)?;
+        let (days, j) = decode_vint(&slice[i..])?;
+        let (nanoseconds, _) = decode_vint(&slice[i + j..])?;
+        Ok(Self {
+            months: months as i32,
+            days: days as i32,
+            nanoseconds,
+        })
+    }
+}
+
+/// Encode a signed vint, which is the zigzag encoded value prefixed by the number of extra bytes as leading ones.
+fn encode_vint(value: i64, buffer: &mut Vec<u8>) {
+    let zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    let bits = 64 - zigzag.leading_zeros() as usize;
+    // every extra byte takes one bit of the first byte
+    let extra_bytes = if bits <= 7 { 0 } else { ((bits - 1) / 7).min(8) };
+    let bytes = zigzag.to_be_bytes();
+    if extra_bytes < 8 {
+        let start = buffer.len();
+        buffer.extend(&bytes[(7 - extra_bytes)..]);
+        buffer[start] |= !(0xff_u8 >> extra_bytes);
+    } else {
+        buffer.push(0xff);
+        buffer.extend(&bytes);
+    }
+}
+
+/// Decode a signed vint, returning the value and the number of consumed bytes.
+fn decode_vint(slice: &[u8]) -> anyhow::Result<(i64, usize)> {
+    let first = *slice.first().ok_or_else(|| anyhow!("Buffer is too small!"))?;
+    let extra_bytes = first.leading_ones() as usize;
+    ensure!(slice.len() > extra_bytes, "Buffer is too small!");
+    let mut zigzag = if extra_bytes < 8 {
+        (first & (0xff >> extra_bytes)) as u64
+    } else {
+        0
+    };
+    for byte in &slice[1..=extra_bytes] {
+        zigzag = (zigzag << 8) | *byte as u64;
+    }
+    Ok((((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64), extra_bytes + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_vint_encoding() {
+        let mut buffer = Vec::new();
+        CqlDuration::new(1, -2, 1_000_000_000).encode(&mut buffer);
+        // 1 -> 2, -2 -> 3, 1e9 -> 2e9 (4 extra bytes)
+        assert_eq!(buffer, vec![0, 0, 0, 7, 2, 3, 0xf0, 0x77, 0x35, 0x94, 0x00]);
+    }
+
+    #[test]
+    fn duration_round_trip() {
+        for duration in [
+            CqlDuration::default(),
+            CqlDuration::new(12, 30, 86_399_999_999_999),
+            CqlDuration::new(-1, -1, -1),
+            CqlDuration::new(i32::MAX, i32::MIN, i64::MIN),
+            CqlDuration::new(0, 0, i64::MAX),
+        ]
+        .iter()
+        {
+            let buffer = duration.encode_new();
+            assert_eq!(&CqlDuration::try_decode(&buffer[4..]).unwrap(), duration);
+        }
+    }
+}
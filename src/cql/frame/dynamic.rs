@@ -0,0 +1,614 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the result column specs and the dynamic row decoder, which decodes the columns
+//! of a result-set to [`CqlValue`]s according to their types in the result metadata.
+
+use super::{decoder::ColumnDecoder, duration::CqlDuration, ColumnValue, Row, Rows};
+use anyhow::{anyhow, bail, ensure};
+use std::{convert::TryInto, net::IpAddr, str, sync::Arc};
+
+/// The type of a column, as described by its [option] in the result metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnType {
+    /// A custom type, described by its java class name.
+    Custom(String),
+    /// The `ascii` type.
+    Ascii,
+    /// The `bigint` type.
+    Bigint,
+    /// The `blob` type.
+    Blob,
+    /// The `boolean` type.
+    Boolean,
+    /// The `counter` type.
+    Counter,
+    /// The `decimal` type.
+    Decimal,
+    /// The `double` type.
+    Double,
+    /// The `float` type.
+    Float,
+    /// The `int` type.
+    Int,
+    /// The `timestamp` type.
+    Timestamp,
+    /// The `uuid` type.
+    Uuid,
+    /// The `varchar` (or `text`) type.
+    Varchar,
+    /// The `varint` type.
+    Varint,
+    /// The `timeuuid` type.
+    Timeuuid,
+    /// The `inet` type.
+    Inet,
+    /// The `date` type.
+    Date,
+    /// The `time` type.
+    Time,
+    /// The `smallint` type.
+    Smallint,
+    /// The `tinyint` type.
+    Tinyint,
+    /// The `duration` type.
+    Duration,
+    /// A `list` of the element type.
+    List(Box<ColumnType>),
+    /// A `map` of the key and value types.
+    Map(Box<ColumnType>, Box<ColumnType>),
+    /// A `set` of the element type.
+    Set(Box<ColumnType>),
+    /// A user defined type.
+    Udt {
+        /// The keyspace of the type.
+        keyspace: String,
+        /// The name of the type.
+        name: String,
+        /// The names and types of the fields, in the order of the type definition.
+        fields: Vec<(String, ColumnType)>,
+    },
+    /// A `tuple` of the element types.
+    Tuple(Vec<ColumnType>),
+}
+
+/// The spec of a result column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSpec {
+    /// The keyspace of the column.
+    pub keyspace: String,
+    /// The table of the column.
+    pub table: String,
+    /// The name of the column.
+    pub name: String,
+    /// The type of the column.
+    pub column_type: ColumnType,
+}
+
+/// A column value decoded according to its [`ColumnType`].
+///
+/// The types which have no std equivalent are kept in their encoded form, ie the `varint` is the
+/// big-endian two's complement of the integer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CqlValue {
+    /// A custom value.
+    Custom(Vec<u8>),
+    /// An `ascii` value.
+    Ascii(String),
+    /// A `bigint` value.
+    Bigint(i64),
+    /// A `blob` value.
+    Blob(Vec<u8>),
+    /// A `boolean` value.
+    Boolean(bool),
+    /// A `counter` value.
+    Counter(i64),
+    /// A `decimal` value, ie `unscaled * 10^-scale`.
+    Decimal {
+        /// The varint unscaled value.
+        unscaled: Vec<u8>,
+        /// The scale.
+        scale: i32,
+    },
+    /// A `double` value.
+    Double(f64),
+    /// A `float` value.
+    Float(f32),
+    /// An `int` value.
+    Int(i32),
+    /// A `timestamp` value, in milliseconds since the unix epoch.
+    Timestamp(i64),
+    /// A `uuid` value.
+    Uuid([u8; 16]),
+    /// A `varchar` value.
+    Varchar(String),
+    /// A `varint` value.
+    Varint(Vec<u8>),
+    /// A `timeuuid` value.
+    Timeuuid([u8; 16]),
+    /// An `inet` value.
+    Inet(IpAddr),
+    /// A `date` value, in days with the unix epoch at 2^31.
+    Date(u32),
+    /// A `time` value, in nanoseconds since midnight.
+    Time(i64),
+    /// A `smallint` value.
+    Smallint(i16),
+    /// A `tinyint` value.
+    Tinyint(i8),
+    /// A `duration` value.
+    Duration(CqlDuration),
+    /// A `list` value.
+    List(Vec<CqlValue>),
+    /// A `map` value, in the encoded order.
+    Map(Vec<(CqlValue, CqlValue)>),
+    /// A `set` value.
+    Set(Vec<CqlValue>),
+    /// A user defined type value, the missing trailing fields and the null fields are `None`.
+    Udt(Vec<(String, Option<CqlValue>)>),
+    /// A `tuple` value, the null elements are `None`.
+    Tuple(Vec<Option<CqlValue>>),
+}
+
+impl CqlValue {
+    /// Decode the (non null) value of the column type.
+    pub fn try_decode(column_type: &ColumnType, slice: &[u8]) -> anyhow::Result<Self> {
+        Ok(match column_type {
+            ColumnType::Custom(_) => CqlValue::Custom(slice.to_vec()),
+            ColumnType::Ascii => CqlValue::Ascii(String::try_decode(slice)?),
+            ColumnType::Bigint => CqlValue::Bigint(i64::try_decode(slice)?),
+            ColumnType::Blob => CqlValue::Blob(slice.to_vec()),
+            ColumnType::Boolean => CqlValue::Boolean(bool::try_decode(slice)?),
+            ColumnType::Counter => CqlValue::Counter(i64::try_decode(slice)?),
+            ColumnType::Decimal => {
+                ensure!(slice.len() >= 4, "Buffer is too small!");
+                CqlValue::Decimal {
+                    scale: i32::try_decode(&slice[..4])?,
+                    unscaled: slice[4..].to_vec(),
+                }
+            }
+            ColumnType::Double => CqlValue::Double(f64::try_decode(slice)?),
+            ColumnType::Float => CqlValue::Float(f32::try_decode(slice)?),
+            ColumnType::Int => CqlValue::Int(i32::try_decode(slice)?),
+            ColumnType::Timestamp => CqlValue::Timestamp(i64::try_decode(slice)?),
+            ColumnType::Uuid => CqlValue::Uuid(slice.try_into()?),
+            ColumnType::Varchar => CqlValue::Varchar(String::try_decode(slice)?),
+            ColumnType::Varint => CqlValue::Varint(slice.to_vec()),
+            ColumnType::Timeuuid => CqlValue::Timeuuid(slice.try_into()?),
+            ColumnType::Inet => {
+                ensure!(
+                    slice.len() == 4 || slice.len() == 16,
+                    "Invalid inet length {}",
+                    slice.len()
+                );
+                CqlValue::Inet(IpAddr::try_decode(slice)?)
+            }
+            ColumnType::Date => CqlValue::Date(u32::try_decode(slice)?),
+            ColumnType::Time => CqlValue::Time(i64::try_decode(slice)?),
+            ColumnType::Smallint => CqlValue::Smallint(i16::try_decode(slice)?),
+            ColumnType::Tinyint => CqlValue::Tinyint(i8::try_decode(slice)?),
+            ColumnType::Duration => CqlValue::Duration(CqlDuration::try_decode(slice)?),
+            ColumnType::List(element_type) => CqlValue::List(decode_elements(element_type, slice)?),
+            ColumnType::Set(element_type) => CqlValue::Set(decode_elements(element_type, slice)?),
+            ColumnType::Map(key_type, value_type) => {
+                let (count, mut start) = collection_count(slice)?;
+                // the count is untrusted, so it's capped by the least size of the [bytes] entries
+                let mut map = Vec::with_capacity(count.min(slice.len() / 8));
+                for _ in 0..count {
+                    let key = element(slice, &mut start)?.ok_or_else(|| anyhow!("Null map key!"))?;
+                    let value = element(slice, &mut start)?.ok_or_else(|| anyhow!("Null map value!"))?;
+                    map.push((
+                        CqlValue::try_decode(key_type, key)?,
+                        CqlValue::try_decode(value_type, value)?,
+                    ));
+                }
+                CqlValue::Map(map)
+            }
+            ColumnType::Udt { fields, .. } => {
+                let mut start = 0;
+                let mut values = Vec::with_capacity(fields.len());
+                for (name, field_type) in fields.iter() {
+                    // the trailing fields may be missing from the value
+                    let value = if start < slice.len() {
+                        element(slice, &mut start)?
+                    } else {
+                        None
+                    };
+                    values.push((name.clone(), nullable(field_type, value)?));
+                }
+                CqlValue::Udt(values)
+            }
+            ColumnType::Tuple(element_types) => {
+                let mut start = 0;
+                let mut values = Vec::with_capacity(element_types.len());
+                for element_type in element_types.iter() {
+                    values.push(nullable(element_type, element(slice, &mut start)?)?);
+                }
+                CqlValue::Tuple(values)
+            }
+        })
+    }
+}
+
+/// Decode a nullable value, the empty values of the types which can't be empty are nulls as well.
+fn nullable(column_type: &ColumnType, value: Option<&[u8]>) -> anyhow::Result<Option<CqlValue>> {
+    match value {
+        Some(value) if !value.is_empty() || can_be_empty(column_type) => {
+            CqlValue::try_decode(column_type, value).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Check whether an empty value of the column type is a valid (non null) value.
+fn can_be_empty(column_type: &ColumnType) -> bool {
+    matches!(
+        column_type,
+        ColumnType::Custom(_) | ColumnType::Ascii | ColumnType::Blob | ColumnType::Varchar
+    )
+}
+
+/// Decode the [int] count of a collection, along with the start of its elements.
+fn collection_count(slice: &[u8]) -> anyhow::Result<(usize, usize)> {
+    ensure!(slice.len() >= 4, "Buffer is too small!");
+    let count = i32::from_be_bytes(slice[..4].try_into()?);
+    ensure!(count >= 0, "Invalid collection count {}", count);
+    Ok((count as usize, 4))
+}
+
+/// Decode the list or set elements.
+fn decode_elements(element_type: &ColumnType, slice: &[u8]) -> anyhow::Result<Vec<CqlValue>> {
+    let (count, mut start) = collection_count(slice)?;
+    // the count is untrusted, so it's capped by the least size of the [bytes] elements
+    let mut elements = Vec::with_capacity(count.min(slice.len() / 4));
+    for _ in 0..count {
+        let value = element(slice, &mut start)?.ok_or_else(|| anyhow!("Null collection element!"))?;
+        elements.push(CqlValue::try_decode(element_type, value)?);
+    }
+    Ok(elements)
+}
+
+/// Get the [bytes] element at `start` in the slice, which is `None` if it's null.
+fn element<'a>(slice: &'a [u8], start: &mut usize) -> anyhow::Result<Option<&'a [u8]>> {
+    ensure!(slice.len() >= *start + 4, "Buffer is too small!");
+    let length = i32::from_be_bytes(slice[*start..(*start + 4)].try_into()?);
+    *start += 4;
+    if length < 0 {
+        return Ok(None);
+    }
+    let end = *start + length as usize;
+    ensure!(slice.len() >= end, "Buffer is too small!");
+    let element = &slice[*start..end];
+    *start = end;
+    Ok(Some(element))
+}
+
+/// A result-set row decoded according to the column specs of the result metadata.
+///
+/// ## Example
+/// ```no_run
+/// use scylla_rs::cql::{DynamicRow, Row};
+/// # fn print(decoder: scylla_rs::cql::Decoder) -> anyhow::Result<()> {
+/// // the response of any SELECT
+/// for row in DynamicRow::rows_iter(decoder)? {
+///     for (spec, value) in row.column_specs().iter().zip(row.values()) {
+///         println!("{}: {:?}", spec.name, value);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicRow {
+    column_specs: Arc<[ColumnSpec]>,
+    values: Vec<Option<CqlValue>>,
+}
+
+impl DynamicRow {
+    /// Get the column specs of the row.
+    pub fn column_specs(&self) -> &[ColumnSpec] {
+        &self.column_specs
+    }
+    /// Get the column values of the row, the null columns are `None`.
+    pub fn values(&self) -> &[Option<CqlValue>] {
+        &self.values
+    }
+    /// Get the value of the column by its name.
+    pub fn get(&self, name: &str) -> Option<&CqlValue> {
+        self.column_specs
+            .iter()
+            .position(|spec| spec.name == name)
+            .and_then(|index| self.values[index].as_ref())
+    }
+    /// Take the column values of the row.
+    pub fn into_values(self) -> Vec<Option<CqlValue>> {
+        self.values
+    }
+}
+
+impl Row for DynamicRow {
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self> {
+        let column_specs = match rows.column_specs()? {
+            Some(column_specs) => column_specs.clone(),
+            None => bail!("The result metadata is required to decode a dynamic row"),
+        };
+        let mut values = Vec::with_capacity(column_specs.len());
+        for spec in column_specs.iter() {
+            let value = rows.column_bytes()?;
+            values.push(
+                nullable(&spec.column_type, value)
+                    .map_err(|e| anyhow!("Failed to decode the column {}: {}", spec.name, e))?,
+            );
+        }
+        Ok(Self { column_specs, values })
+    }
+}
+
+/// Get the column specs of the rows metadata at `i` in the buffer, along with the end of the column specs.
+pub(crate) fn column_specs(
+    buffer: &[u8],
+    mut i: usize,
+    global_table_spec: bool,
+    columns_count: i32,
+) -> anyhow::Result<(Vec<ColumnSpec>, usize)> {
+    let mut global = None;
+    if global_table_spec {
+        // <keyspace><table>
+        let keyspace = string(buffer, &mut i)?;
+        global = Some((keyspace, string(buffer, &mut i)?));
+    }
+    let mut specs = Vec::with_capacity(columns_count.max(0) as usize);
+    for _ in 0..columns_count {
+        let (keyspace, table) = match &global {
+            Some((keyspace, table)) => (keyspace.clone(), table.clone()),
+            None => {
+                let keyspace = string(buffer, &mut i)?;
+                (keyspace, string(buffer, &mut i)?)
+            }
+        };
+        // <name><type>
+        let name = string(buffer, &mut i)?;
+        let column_type = column_type(buffer, &mut i)?;
+        specs.push(ColumnSpec {
+            keyspace,
+            table,
+            name,
+            column_type,
+        });
+    }
+    Ok((specs, i))
+}
+
+/// Skip the column specs of the rows metadata at `i` in the buffer without keeping them, and get the end of the
+/// column specs.
+pub(crate) fn skip_column_specs(
+    buffer: &[u8],
+    mut i: usize,
+    global_table_spec: bool,
+    columns_count: i32,
+) -> anyhow::Result<usize> {
+    if global_table_spec {
+        // <keyspace><table>
+        skip_string(buffer, &mut i)?;
+        skip_string(buffer, &mut i)?;
+    }
+    for _ in 0..columns_count {
+        if !global_table_spec {
+            skip_string(buffer, &mut i)?;
+            skip_string(buffer, &mut i)?;
+        }
+        // <name><type>
+        skip_string(buffer, &mut i)?;
+        column_type(buffer, &mut i)?;
+    }
+    Ok(i)
+}
+
+/// Decode the type [option] at `i` in the buffer.
+fn column_type(buffer: &[u8], i: &mut usize) -> anyhow::Result<ColumnType> {
+    let id = short(buffer, i)?;
+    Ok(match id {
+        0x0000 => ColumnType::Custom(string(buffer, i)?),
+        0x0001 => ColumnType::Ascii,
+        0x0002 => ColumnType::Bigint,
+        0x0003 => ColumnType::Blob,
+        0x0004 => ColumnType::Boolean,
+        0x0005 => ColumnType::Counter,
+        0x0006 => ColumnType::Decimal,
+        0x0007 => ColumnType::Double,
+        0x0008 => ColumnType::Float,
+        0x0009 => ColumnType::Int,
+        0x000B => ColumnType::Timestamp,
+        0x000C => ColumnType::Uuid,
+        0x000D => ColumnType::Varchar,
+        0x000E => ColumnType::Varint,
+        0x000F => ColumnType::Timeuuid,
+        0x0010 => ColumnType::Inet,
+        0x0011 => ColumnType::Date,
+        0x0012 => ColumnType::Time,
+        0x0013 => ColumnType::Smallint,
+        0x0014 => ColumnType::Tinyint,
+        0x0015 => ColumnType::Duration,
+        0x0020 => ColumnType::List(Box::new(column_type(buffer, i)?)),
+        0x0021 => {
+            let key_type = column_type(buffer, i)?;
+            ColumnType::Map(Box::new(key_type), Box::new(column_type(buffer, i)?))
+        }
+        0x0022 => ColumnType::Set(Box::new(column_type(buffer, i)?)),
+        0x0030 => {
+            let keyspace = string(buffer, i)?;
+            let name = string(buffer, i)?;
+            let count = short(buffer, i)?;
+            let mut fields = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let field_name = string(buffer, i)?;
+                fields.push((field_name, column_type(buffer, i)?));
+            }
+            ColumnType::Udt { keyspace, name, fields }
+        }
+        0x0031 => {
+            let count = short(buffer, i)?;
+            let mut element_types = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                element_types.push(column_type(buffer, i)?);
+            }
+            ColumnType::Tuple(element_types)
+        }
+        id => bail!("Unknown column type id {:#06x}", id),
+    })
+}
+
+/// Decode the [short] at `i` in the buffer.
+fn short(buffer: &[u8], i: &mut usize) -> anyhow::Result<u16> {
+    ensure!(buffer.len() >= *i + 2, "Buffer is too small!");
+    let short = u16::from_be_bytes(buffer[*i..(*i + 2)].try_into()?);
+    *i += 2;
+    Ok(short)
+}
+
+/// Skip the [string] at `i` in the buffer.
+fn skip_string(buffer: &[u8], i: &mut usize) -> anyhow::Result<()> {
+    let length = short(buffer, i)? as usize;
+    ensure!(buffer.len() >= *i + length, "Buffer is too small!");
+    *i += length;
+    Ok(())
+}
+
+/// Decode the [string] at `i` in the buffer.
+fn string(buffer: &[u8], i: &mut usize) -> anyhow::Result<String> {
+    let length = short(buffer, i)? as usize;
+    ensure!(buffer.len() >= *i + length, "Buffer is too small!");
+    let string = str::from_utf8(&buffer[*i..(*i + length)])?.to_string();
+    *i += length;
+    Ok(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::{compression::UNCOMPRESSED, Decoder};
+
+    fn bytes(value: &[u8]) -> Vec<u8> {
+        let mut bytes = (value.len() as i32).to_be_bytes().to_vec();
+        bytes.extend(value);
+        bytes
+    }
+
+    #[test]
+    fn decode_column_specs() {
+        let mut buffer = Vec::new();
+        for string in ["ks", "table", "a"].iter() {
+            buffer.extend(&(string.len() as u16).to_be_bytes());
+            buffer.extend(string.as_bytes());
+        }
+        // map<varchar, frozen<tuple<int, list<uuid>>>>
+        buffer.extend(&[
+            0x00, 0x21, 0x00, 0x0D, 0x00, 0x31, 0x00, 0x02, 0x00, 0x09, 0x00, 0x20, 0x00, 0x0C,
+        ]);
+        buffer.extend(&[0, 1, b'b']);
+        // udt ks.point { x int, y int }
+        buffer.extend(&[0x00, 0x30, 0, 2, b'k', b's', 0, 5]);
+        buffer.extend(b"point");
+        buffer.extend(&[0, 2, 0, 1, b'x', 0x00, 0x09, 0, 1, b'y', 0x00, 0x09]);
+        buffer.push(0xff);
+        let (specs, end) = column_specs(&buffer, 0, true, 2).unwrap();
+        assert_eq!(end, buffer.len() - 1);
+        assert_eq!(specs[0].table, "table");
+        assert_eq!(
+            specs[0].column_type,
+            ColumnType::Map(
+                Box::new(ColumnType::Varchar),
+                Box::new(ColumnType::Tuple(vec![
+                    ColumnType::Int,
+                    ColumnType::List(Box::new(ColumnType::Uuid))
+                ]))
+            )
+        );
+        assert_eq!(specs[1].name, "b");
+        assert_eq!(
+            specs[1].column_type,
+            ColumnType::Udt {
+                keyspace: "ks".to_string(),
+                name: "point".to_string(),
+                fields: vec![("x".to_string(), ColumnType::Int), ("y".to_string(), ColumnType::Int)],
+            }
+        );
+        assert!(column_specs(&buffer[..20], 0, true, 2).is_err());
+        assert_eq!(skip_column_specs(&buffer, 0, true, 2).unwrap(), end);
+        assert!(skip_column_specs(&buffer[..20], 0, true, 2).is_err());
+    }
+
+    #[test]
+    fn decode_cql_values() {
+        let point = ColumnType::Udt {
+            keyspace: "ks".to_string(),
+            name: "point".to_string(),
+            fields: vec![("x".to_string(), ColumnType::Int), ("y".to_string(), ColumnType::Int)],
+        };
+        // the y field is missing
+        let value = bytes(&7i32.to_be_bytes());
+        assert_eq!(
+            CqlValue::try_decode(&point, &value).unwrap(),
+            CqlValue::Udt(vec![("x".to_string(), Some(CqlValue::Int(7))), ("y".to_string(), None)])
+        );
+        let map = ColumnType::Map(
+            Box::new(ColumnType::Varchar),
+            Box::new(ColumnType::Set(Box::new(ColumnType::Inet))),
+        );
+        let mut value = 1i32.to_be_bytes().to_vec();
+        value.extend(bytes(b"a"));
+        let mut set = 1i32.to_be_bytes().to_vec();
+        set.extend(bytes(&[127, 0, 0, 1]));
+        value.extend(bytes(&set));
+        assert_eq!(
+            CqlValue::try_decode(&map, &value).unwrap(),
+            CqlValue::Map(vec![(
+                CqlValue::Varchar("a".to_string()),
+                CqlValue::Set(vec![CqlValue::Inet([127, 0, 0, 1].into())])
+            )])
+        );
+        let tuple = ColumnType::Tuple(vec![ColumnType::Varchar, ColumnType::Bigint]);
+        let mut value = bytes(b"");
+        value.extend(&(-1i32).to_be_bytes());
+        assert_eq!(
+            CqlValue::try_decode(&tuple, &value).unwrap(),
+            CqlValue::Tuple(vec![Some(CqlValue::Varchar(String::new())), None])
+        );
+        assert!(CqlValue::try_decode(&ColumnType::Int, &[0, 1]).is_err());
+        // the huge counts fail on the missing elements rather than allocating them upfront
+        let list = ColumnType::List(Box::new(ColumnType::Int));
+        assert!(CqlValue::try_decode(&list, &i32::MAX.to_be_bytes()).is_err());
+        assert!(CqlValue::try_decode(&map, &i32::MAX.to_be_bytes()).is_err());
+    }
+
+    #[test]
+    fn decode_dynamic_rows() {
+        let mut body: Vec<u8> = Vec::new();
+        // kind, flags (global_table_spec), columns_count
+        for int in [2i32, 1, 2].iter() {
+            body.extend(&int.to_be_bytes());
+        }
+        for string in ["ks", "people", "name"].iter() {
+            body.extend(&(string.len() as u16).to_be_bytes());
+            body.extend(string.as_bytes());
+        }
+        body.extend(&[0x00, 0x0D, 0, 3]);
+        body.extend(b"age");
+        body.extend(&[0x00, 0x09]);
+        // rows_count
+        body.extend(&1i32.to_be_bytes());
+        body.extend(bytes(b"alice"));
+        body.extend(&(-1i32).to_be_bytes());
+        let mut frame = vec![0x84, 0, 0, 0, 0x08];
+        frame.extend(&(body.len() as i32).to_be_bytes());
+        frame.extend(body);
+        let rows: Vec<DynamicRow> = DynamicRow::rows_iter(Decoder::new(frame, UNCOMPRESSED).unwrap())
+            .unwrap()
+            .collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].column_specs()[1].column_type, ColumnType::Int);
+        assert_eq!(rows[0].get("name"), Some(&CqlValue::Varchar("alice".to_string())));
+        assert_eq!(rows[0].get("age"), None);
+        assert_eq!(rows[0].values().len(), 2);
+    }
+}
@@ -4,7 +4,7 @@
 //! This module implements the frame encoder.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
     io::Cursor,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
 };
@@ -229,21 +229,48 @@ where
     E: ColumnEncoder,
 {
     fn encode(&self, buffer: &mut Vec<u8>) {
-        // total byte_size of the list is unknown,
-        // therefore we pad zero length for now.
-        buffer.extend(&BE_0_BYTES_LEN);
-        // in order to compute the byte_size we snapshot
-        // the current buffer length in advance
-        let current_length = buffer.len();
-        buffer.extend(&i32::to_be_bytes(self.len() as i32));
-        for e in self {
-            e.encode(buffer);
-        }
-        let list_byte_size = buffer.len() - current_length;
-        buffer[(current_length - 4)..current_length].copy_from_slice(&i32::to_be_bytes(list_byte_size as i32));
+        encode_collection(self.len(), self.iter(), buffer);
     }
 }
 
+impl<E, S: ::std::hash::BuildHasher> ColumnEncoder for HashSet<E, S>
+where
+    E: ColumnEncoder,
+{
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        encode_collection(self.len(), self.iter(), buffer);
+    }
+}
+
+impl<E> ColumnEncoder for BTreeSet<E>
+where
+    E: ColumnEncoder,
+{
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        encode_collection(self.len(), self.iter(), buffer);
+    }
+}
+
+/// Encode the elements of a list or set.
+fn encode_collection<'a, E: 'a + ColumnEncoder>(
+    len: usize,
+    elements: impl Iterator<Item = &'a E>,
+    buffer: &mut Vec<u8>,
+) {
+    // total byte_size of the collection is unknown,
+    // therefore we pad zero length for now.
+    buffer.extend(&BE_0_BYTES_LEN);
+    // in order to compute the byte_size we snapshot
+    // the current buffer length in advance
+    let current_length = buffer.len();
+    buffer.extend(&i32::to_be_bytes(len as i32));
+    for e in elements {
+        e.encode(buffer);
+    }
+    let collection_byte_size = buffer.len() - current_length;
+    buffer[(current_length - 4)..current_length].copy_from_slice(&i32::to_be_bytes(collection_byte_size as i32));
+}
+
 impl<K, V, S: ::std::hash::BuildHasher> ColumnEncoder for HashMap<K, V, S>
 where
     K: ColumnEncoder,
@@ -275,6 +302,7 @@ impl ColumnEncoder for Null {
 }
 
 /// An encode chain. Allows sequential encodes stored back-to-back in a buffer.
+#[derive(Default)]
 pub struct TokenEncodeChain {
     buffer: Vec<u8>,
 }
@@ -287,6 +315,14 @@ impl TokenEncodeChain {
         self
     }
 
+    /// Chain the encoded bytes of a value, ie a bound value of a query frame
+    pub fn chain_bytes(mut self, bytes: &[u8]) -> Self {
+        self.buffer.extend(&u16::to_be_bytes(bytes.len() as u16));
+        self.buffer.extend(bytes);
+        self.buffer.push(0);
+        self
+    }
+
     /// Complete the chain and return the token
     pub fn finish(self) -> i64 {
         crate::cql::murmur3_cassandra_x64_128(&self.buffer, 0).0
@@ -309,3 +345,20 @@ pub trait TokenEncoder: ColumnEncoder {
 }
 
 impl<T: ColumnEncoder> TokenEncoder for T {}
+
+/// Encode the `[bytes map]` (ie the custom payload) into the buffer.
+pub(crate) fn encode_bytes_map(map: &HashMap<String, Vec<u8>>, buffer: &mut Vec<u8>) {
+    buffer.extend(&u16::to_be_bytes(map.len() as u16));
+    for (key, value) in map {
+        buffer.extend(&u16::to_be_bytes(key.len() as u16));
+        buffer.extend(key.as_bytes());
+        buffer.extend(&i32::to_be_bytes(value.len() as i32));
+        buffer.extend(value);
+    }
+}
+
+/// Encode the `[string]` (ie the name of a named value) into the buffer.
+pub(crate) fn encode_string(string: &str, buffer: &mut Vec<u8>) {
+    buffer.extend(&u16::to_be_bytes(string.len() as u16));
+    buffer.extend(string.as_bytes());
+}
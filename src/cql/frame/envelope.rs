@@ -0,0 +1,289 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module upgrades the request frames, which the frame builders encode as protocol v4, to protocol v5
+//! frames (called envelopes in the v5 spec).
+//!
+//! The request payloads are built before it is known which connection (and therefore which protocol version)
+//! will carry them, so the upgrade happens right before the frame is written to a v5 connection.
+
+use super::{
+    batchflags::WITH_NAMES_FOR_VALUES,
+    header::{COMPRESSION, CUSTOM_PAYLOAD, PROTOCOL_V5},
+    opcode::{BATCH, EXECUTE, PREPARE, QUERY},
+};
+use anyhow::{anyhow, ensure};
+use std::{collections::HashMap, convert::TryInto, sync::RwLock};
+
+/// The length of the frame header.
+const HEADER_LENGTH: usize = 9;
+
+/// The result metadata ids of the prepared statements by their ids, which are cached from the PREPARED results.
+static RESULT_METADATA_IDS: RwLock<Option<HashMap<[u8; 16], Vec<u8>>>> = RwLock::new(None);
+
+/// Cache the result metadata id of the prepared statement, which is sent along with its EXECUTE frames.
+pub fn set_result_metadata_id(id: [u8; 16], result_metadata_id: Vec<u8>) {
+    RESULT_METADATA_IDS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(id, result_metadata_id);
+}
+
+/// Get the cached result metadata id of the prepared statement.
+pub fn result_metadata_id(id: &[u8; 16]) -> Option<Vec<u8>> {
+    RESULT_METADATA_IDS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()?
+        .get(id)
+        .cloned()
+}
+
+/// Upgrade the v4 request frame to a v5 frame, the EXECUTE frame gets the cached result metadata id of its
+/// prepared statement (if any).
+pub fn upgrade_request(frame: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let result_metadata_id = prepared_id(frame)?
+        .and_then(|id| result_metadata_id(&id))
+        .unwrap_or_default();
+    upgrade_frame(frame, &result_metadata_id)
+}
+
+/// Get the prepared id of the EXECUTE frame.
+fn prepared_id(frame: &[u8]) -> anyhow::Result<Option<[u8; 16]>> {
+    ensure!(frame.len() >= HEADER_LENGTH, "Buffer is too small!");
+    if frame[4] != EXECUTE {
+        return Ok(None);
+    }
+    let mut i = HEADER_LENGTH;
+    if frame[1] & CUSTOM_PAYLOAD == CUSTOM_PAYLOAD {
+        i = skip_bytes_map(frame, i)?;
+    }
+    let end = skip_short_bytes(frame, i)?;
+    ensure!(frame.len() >= end, "Buffer is too small!");
+    Ok(frame[(i + 2)..end].try_into().ok())
+}
+
+/// Upgrade the v4 request frame to a v5 frame.
+///
+/// The frame must not be compressed (compression is applied per segment in v5), the one byte query/batch flags
+/// are widened to an int, the prepare flags are added and the `result_metadata_id` is added to the execute
+/// frame. An empty `result_metadata_id` makes the server always return the up-to-date result metadata.
+pub fn upgrade_frame(frame: &[u8], result_metadata_id: &[u8]) -> anyhow::Result<Vec<u8>> {
+    ensure!(frame.len() >= HEADER_LENGTH, "Buffer is too small!");
+    ensure!(frame[1] & COMPRESSION == 0, "Compressed frames can't be upgraded!");
+    let mut upgraded = Vec::with_capacity(frame.len() + 8 + result_metadata_id.len());
+    upgraded.extend(&frame[..HEADER_LENGTH]);
+    upgraded[0] = PROTOCOL_V5;
+    let mut i = HEADER_LENGTH;
+    if frame[1] & CUSTOM_PAYLOAD == CUSTOM_PAYLOAD {
+        i = skip_bytes_map(frame, i)?;
+        // the custom payload is kept as is
+        upgraded.extend(&frame[HEADER_LENGTH..i]);
+    }
+    match frame[4] {
+        QUERY => {
+            // <query><consistency><flags>
+            let flags = skip_long_string(frame, i)? + 2;
+            widen_flags(frame, i, flags, &mut upgraded)?;
+        }
+        EXECUTE => {
+            // <id><result_metadata_id><consistency><flags>
+            let consistency = skip_short_bytes(frame, i)?;
+            upgraded.extend(&frame[i..consistency]);
+            upgraded.extend(&(result_metadata_id.len() as u16).to_be_bytes());
+            upgraded.extend(result_metadata_id);
+            widen_flags(frame, consistency, consistency + 2, &mut upgraded)?;
+        }
+        BATCH => {
+            // <type><n><query_1>...<query_n><consistency><flags>
+            let flags = batch_flags_index(frame, i)?;
+            widen_flags(frame, i, flags, &mut upgraded)?;
+        }
+        PREPARE => {
+            // <query><flags>
+            upgraded.extend(&frame[i..]);
+            upgraded.extend(&0i32.to_be_bytes());
+        }
+        _ => upgraded.extend(&frame[i..]),
+    }
+    let body_length = (upgraded.len() - HEADER_LENGTH) as i32;
+    upgraded[5..9].copy_from_slice(&body_length.to_be_bytes());
+    Ok(upgraded)
+}
+
+/// Copy the frame from `start` and widen the one byte flags at `flags` to an int.
+fn widen_flags(frame: &[u8], start: usize, flags: usize, upgraded: &mut Vec<u8>) -> anyhow::Result<()> {
+    ensure!(frame.len() > flags, "Buffer is too small!");
+    upgraded.extend(&frame[start..flags]);
+    upgraded.extend(&(frame[flags] as i32).to_be_bytes());
+    upgraded.extend(&frame[(flags + 1)..]);
+    Ok(())
+}
+
+/// Find the index of the batch flags, which follow the queries and their consistency.
+///
+/// The batches can't bind their values by name (CASSANDRA-10246), so a batch with the `with_names_for_values`
+/// flag is rejected.
+fn batch_flags_index(frame: &[u8], i: usize) -> anyhow::Result<usize> {
+    ensure!(frame.len() >= i + 3, "Buffer is too small!");
+    let count = u16::from_be_bytes(frame[(i + 1)..(i + 3)].try_into()?);
+    let mut j = i + 3;
+    for _ in 0..count {
+        let kind = *frame.get(j).ok_or_else(|| anyhow!("Buffer is too small!"))?;
+        j = match kind {
+            0 => skip_long_string(frame, j + 1)?,
+            _ => skip_short_bytes(frame, j + 1)?,
+        };
+        j = skip_values(frame, j)?;
+    }
+    let flags = *frame.get(j + 2).ok_or_else(|| anyhow!("Buffer is too small!"))?;
+    ensure!(
+        flags & WITH_NAMES_FOR_VALUES == 0,
+        "The batch values can't be bound by name!"
+    );
+    Ok(j + 2)
+}
+
+fn skip_long_string(frame: &[u8], i: usize) -> anyhow::Result<usize> {
+    ensure!(frame.len() >= i + 4, "Buffer is too small!");
+    let length = i32::from_be_bytes(frame[i..(i + 4)].try_into()?).max(0) as usize;
+    Ok(i + 4 + length)
+}
+
+fn skip_short_bytes(frame: &[u8], i: usize) -> anyhow::Result<usize> {
+    ensure!(frame.len() >= i + 2, "Buffer is too small!");
+    Ok(i + 2 + u16::from_be_bytes(frame[i..(i + 2)].try_into()?) as usize)
+}
+
+fn skip_values(frame: &[u8], i: usize) -> anyhow::Result<usize> {
+    ensure!(frame.len() >= i + 2, "Buffer is too small!");
+    let count = u16::from_be_bytes(frame[i..(i + 2)].try_into()?);
+    let mut j = i + 2;
+    for _ in 0..count {
+        // null and unset values have a negative length
+        j = skip_long_string(frame, j)?;
+    }
+    Ok(j)
+}
+
+fn skip_bytes_map(frame: &[u8], i: usize) -> anyhow::Result<usize> {
+    ensure!(frame.len() >= i + 2, "Buffer is too small!");
+    let count = u16::from_be_bytes(frame[i..(i + 2)].try_into()?);
+    let mut j = i + 2;
+    for _ in 0..count {
+        j = skip_short_bytes(frame, j)?;
+        j = skip_long_string(frame, j)?;
+    }
+    Ok(j)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::{Batch, Consistency, Prepare, Query, Statements, Values};
+    use std::collections::HashMap;
+
+    #[test]
+    fn upgrade_query_frame() {
+        let Query(payload) = Query::new()
+            .statement("SELECT * FROM ks.table WHERE key = ?")
+            .consistency(Consistency::One)
+            .value(&"key")
+            .build()
+            .unwrap();
+        let upgraded = upgrade_frame(&payload, &[]).unwrap();
+        assert_eq!(upgraded[0], PROTOCOL_V5);
+        assert_eq!(upgraded.len(), payload.len() + 3);
+        assert_eq!(
+            i32::from_be_bytes(upgraded[5..9].try_into().unwrap()) as usize,
+            upgraded.len() - 9
+        );
+        let flags = 9 + 4 + 36 + 2;
+        assert_eq!(&upgraded[flags..(flags + 4)], &[0, 0, 0, payload[flags]]);
+        assert_eq!(&upgraded[(flags + 4)..], &payload[(flags + 1)..]);
+    }
+
+    #[test]
+    fn upgrade_execute_frame() {
+        let Query(payload) = Query::new()
+            .id(&[7; 16])
+            .consistency(Consistency::Quorum)
+            .build()
+            .unwrap();
+        let upgraded = upgrade_frame(&payload, &[1, 2]).unwrap();
+        assert_eq!(upgraded[4], EXECUTE);
+        assert_eq!(&upgraded[9..27], &payload[9..27]);
+        assert_eq!(&upgraded[27..31], &[0, 2, 1, 2]);
+        assert_eq!(&upgraded[31..33], &payload[27..29]);
+        assert_eq!(&upgraded[33..], &[0, 0, 0, payload[29]]);
+    }
+
+    #[test]
+    fn upgrade_execute_request() {
+        let Query(payload) = Query::new()
+            .id(&[9; 16])
+            .consistency(Consistency::Quorum)
+            .build()
+            .unwrap();
+        // the result metadata id is empty until it's cached
+        assert_eq!(&upgrade_request(&payload).unwrap()[27..29], &[0, 0]);
+        set_result_metadata_id([9; 16], vec![3, 4, 5]);
+        assert_eq!(
+            upgrade_request(&payload).unwrap(),
+            upgrade_frame(&payload, &[3, 4, 5]).unwrap()
+        );
+        let Query(payload) = Query::new()
+            .statement("SELECT * FROM ks.table")
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert_eq!(
+            upgrade_request(&payload).unwrap(),
+            upgrade_frame(&payload, &[]).unwrap()
+        );
+    }
+
+    #[test]
+    fn upgrade_batch_and_prepare_frames() {
+        let Batch(payload) = Batch::new()
+            .logged()
+            .statement("INSERT INTO ks.table (key) VALUES (?)")
+            .value(&"key")
+            .id(&[0; 16])
+            .null_value()
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        let upgraded = upgrade_frame(&payload, &[]).unwrap();
+        assert_eq!(upgraded.len(), payload.len() + 3);
+        assert_eq!(&upgraded[(upgraded.len() - 4)..], &[0, 0, 0, 0]);
+        // the batch values can't be named
+        let mut named = payload.clone();
+        let flags = named.len() - 1;
+        named[flags] |= WITH_NAMES_FOR_VALUES;
+        let error = upgrade_frame(&named, &[]).unwrap_err();
+        assert_eq!(error.to_string(), "The batch values can't be bound by name!");
+        let Prepare(payload) = Prepare::new().statement("SELECT * FROM ks.table").build().unwrap();
+        let upgraded = upgrade_frame(&payload, &[]).unwrap();
+        assert_eq!(&upgraded[9..(payload.len())], &payload[9..]);
+        assert_eq!(&upgraded[payload.len()..], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn upgrade_frame_keeps_custom_payload() {
+        let mut custom_payload = HashMap::new();
+        custom_payload.insert("key".to_string(), vec![1, 2, 3]);
+        let Query(payload) = Query::new()
+            .custom_payload(&custom_payload)
+            .id(&[7; 16])
+            .consistency(Consistency::Quorum)
+            .build()
+            .unwrap();
+        let upgraded = upgrade_frame(&payload, &[]).unwrap();
+        assert_eq!(upgraded[1] & CUSTOM_PAYLOAD, CUSTOM_PAYLOAD);
+        assert_eq!(&upgraded[9..41], &payload[9..41]);
+        assert_eq!(&upgraded[41..43], &[0, 0]);
+        assert_eq!(&upgraded[43..], &[payload[41], payload[42], 0, 0, 0, payload[43]]);
+    }
+}
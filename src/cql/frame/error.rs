@@ -5,12 +5,16 @@
 
 use super::{
     consistency::Consistency,
-    decoder::{self, Decoder, Frame},
+    decoder::{self, ColumnDecoder, Decoder, Frame},
+    header::{PROTOCOL_V4, PROTOCOL_V5},
 };
 use anyhow::{bail, ensure};
 // use num_derive::FromPrimitive;
 // use num_traits::FromPrimitive;
-use std::convert::{TryFrom, TryInto};
+use std::{
+    convert::{TryFrom, TryInto},
+    net::IpAddr,
+};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -28,14 +32,10 @@ pub struct CqlError {
 impl CqlError {
     /// Get the CQL error from the frame decoder.
     pub fn new(decoder: &Decoder) -> anyhow::Result<CqlError> {
-        Self::try_from(decoder.body()?)
+        Self::decode(decoder.body()?, decoder.protocol_version()?)
     }
-}
-
-impl TryFrom<&[u8]> for CqlError {
-    type Error = anyhow::Error;
-
-    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+    /// Decode the CQL error body of the given native protocol version.
+    fn decode(slice: &[u8], version: u8) -> anyhow::Result<CqlError> {
         let code = ErrorCodes::try_from(slice)?;
         let message = decoder::string(&slice[4..])?;
         let additional: Option<Additional>;
@@ -56,8 +56,9 @@ impl TryFrom<&[u8]> for CqlError {
                 )?))
             }
             ErrorCodes::ReadFailure => {
-                additional = Some(Additional::ReadFailure(ReadFailure::try_from(
+                additional = Some(Additional::ReadFailure(ReadFailure::decode(
                     &slice[(6 + message.len()..)],
+                    version,
                 )?))
             }
             ErrorCodes::FunctionFailure => {
@@ -66,8 +67,9 @@ impl TryFrom<&[u8]> for CqlError {
                 )?))
             }
             ErrorCodes::WriteFailure => {
-                additional = Some(Additional::WriteFailure(WriteFailure::try_from(
+                additional = Some(Additional::WriteFailure(WriteFailure::decode(
                     &slice[(6 + message.len()..)],
+                    version,
                 )?))
             }
             ErrorCodes::AlreadyExists => {
@@ -92,6 +94,14 @@ impl TryFrom<&[u8]> for CqlError {
     }
 }
 
+impl TryFrom<&[u8]> for CqlError {
+    type Error = anyhow::Error;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        Self::decode(slice, PROTOCOL_V4)
+    }
+}
+
 impl CqlError {
     /// Take the unprepared_id if the error is Unprepared error
     pub fn take_unprepared_id(&mut self) -> Option<[u8; 16]> {
@@ -286,6 +296,9 @@ impl TryFrom<&[u8]> for ReadTimeout {
         })
     }
 }
+/// The failure code of every failed node, keyed by the node address.
+pub type ReasonMap = Vec<(IpAddr, u16)>;
+
 #[derive(Debug)]
 /// The addtional error information, `ReadFailure`, stucture.
 pub struct ReadFailure {
@@ -298,6 +311,8 @@ pub struct ReadFailure {
     pub blockfor: i32,
     /// The number of nodes that experience a failure while executing the request.
     pub num_failures: i32,
+    /// The failure code of every failed node (protocol v5).
+    pub reason_map: ReasonMap,
     /// If its value is 0, it means the replica that was asked for data had not
     /// responded. Otherwise, the value is != 0.
     pub data_present: u8,
@@ -308,24 +323,31 @@ impl ReadFailure {
         self.data_present == 0
     }
 }
-impl TryFrom<&[u8]> for ReadFailure {
-    type Error = anyhow::Error;
-
-    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+impl ReadFailure {
+    fn decode(slice: &[u8], version: u8) -> anyhow::Result<Self> {
         let cl = Consistency::try_from(slice)?;
         let received = i32::from_be_bytes(slice[2..6].try_into()?);
         let blockfor = i32::from_be_bytes(slice[6..10].try_into()?);
-        let num_failures = i32::from_be_bytes(slice[10..14].try_into()?);
-        let data_present = slice[14];
+        let (num_failures, reason_map, end) = failures(slice, version)?;
+        ensure!(slice.len() > end, "Buffer is too small!");
+        let data_present = slice[end];
         Ok(Self {
             cl,
             received,
             blockfor,
             num_failures,
+            reason_map,
             data_present,
         })
     }
 }
+impl TryFrom<&[u8]> for ReadFailure {
+    type Error = anyhow::Error;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        Self::decode(slice, PROTOCOL_V4)
+    }
+}
 #[derive(Debug)]
 /// The addtional error information, `FunctionFailure`, stucture.
 pub struct FunctionFailure {
@@ -362,28 +384,58 @@ pub struct WriteFailure {
     pub blockfor: i32,
     /// Representing the number of nodes that experience a failure while executing the request.
     pub num_failures: i32,
+    /// The failure code of every failed node (protocol v5).
+    pub reason_map: ReasonMap,
     /// Describes the type of the write that timed out.
     pub writetype: WriteType,
 }
 
-impl TryFrom<&[u8]> for WriteFailure {
-    type Error = anyhow::Error;
-
-    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+impl WriteFailure {
+    fn decode(slice: &[u8], version: u8) -> anyhow::Result<Self> {
         let cl = Consistency::try_from(slice)?;
         let received = i32::from_be_bytes(slice[2..6].try_into()?);
         let blockfor = i32::from_be_bytes(slice[6..10].try_into()?);
-        let num_failures = i32::from_be_bytes(slice[10..14].try_into()?);
-        let writetype = WriteType::try_from(&slice[14..])?;
+        let (num_failures, reason_map, end) = failures(slice, version)?;
+        let writetype = WriteType::try_from(&slice[end..])?;
         Ok(Self {
             cl,
             received,
             blockfor,
             num_failures,
+            reason_map,
             writetype,
         })
     }
 }
+impl TryFrom<&[u8]> for WriteFailure {
+    type Error = anyhow::Error;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        Self::decode(slice, PROTOCOL_V4)
+    }
+}
+
+/// Decode the failures which follow `<cl><received><blockfor>`, that is `<numfailures>` in protocol v4
+/// and `<reasonmap>` in protocol v5. Returns the number of failures, the reason map and its end.
+fn failures(slice: &[u8], version: u8) -> anyhow::Result<(i32, ReasonMap, usize)> {
+    ensure!(slice.len() >= 14, "Buffer is too small!");
+    let num_failures = i32::from_be_bytes(slice[10..14].try_into()?);
+    let mut reason_map = Vec::new();
+    let mut i = 14;
+    if version >= PROTOCOL_V5 {
+        for _ in 0..num_failures {
+            // <endpoint><failurecode>, where endpoint is [inetaddr]
+            ensure!(slice.len() > i, "Buffer is too small!");
+            let address_end = i + 1 + slice[i] as usize;
+            ensure!(slice.len() >= address_end + 2, "Buffer is too small!");
+            let endpoint = IpAddr::try_decode(&slice[(i + 1)..address_end])?;
+            let failure_code = u16::from_be_bytes(slice[address_end..(address_end + 2)].try_into()?);
+            reason_map.push((endpoint, failure_code));
+            i = address_end + 2;
+        }
+    }
+    Ok((num_failures, reason_map, i))
+}
 #[derive(Debug)]
 /// The addtional error information, `AlreadyExists`, stucture.
 pub struct AlreadyExists {
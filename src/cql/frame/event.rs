@@ -0,0 +1,371 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the Event frame, which is pushed by the server (on stream id -1)
+//! once the connection is registered for the event types.
+
+use super::decoder::{inet, string, string_list_with_returned_bytes_length, Decoder, Frame};
+use anyhow::{anyhow, bail, ensure};
+use std::{
+    convert::{TryFrom, TryInto},
+    net::SocketAddr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The event types a connection can register for.
+pub enum EventType {
+    /// Events related to change in the cluster topology.
+    TopologyChange,
+    /// Events related to change of node status (up/down).
+    StatusChange,
+    /// Events related to schema change.
+    SchemaChange,
+}
+
+impl EventType {
+    /// Get the event type as it is encoded in the REGISTER and EVENT frames.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::TopologyChange => "TOPOLOGY_CHANGE",
+            EventType::StatusChange => "STATUS_CHANGE",
+            EventType::SchemaChange => "SCHEMA_CHANGE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The server event.
+pub enum Event {
+    /// A node was added to or removed from the cluster.
+    TopologyChange(TopologyChange),
+    /// A node went up or down.
+    StatusChange(StatusChange),
+    /// A keyspace, table, type, function or aggregate was created, updated or dropped.
+    SchemaChange(SchemaChange),
+}
+
+impl Event {
+    /// Create an Event from the frame decoder.
+    pub fn new(decoder: &Decoder) -> anyhow::Result<Self> {
+        ensure!(decoder.is_event()?, "Not an event frame!");
+        Self::try_from(decoder.body()?)
+    }
+    /// Get the type of the event.
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::TopologyChange(_) => EventType::TopologyChange,
+            Event::StatusChange(_) => EventType::StatusChange,
+            Event::SchemaChange(_) => EventType::SchemaChange,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Event {
+    type Error = anyhow::Error;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        let event_type = string(slice)?;
+        let slice = &slice[2 + event_type.len()..];
+        Ok(match event_type.as_str() {
+            "TOPOLOGY_CHANGE" => Event::TopologyChange(TopologyChange::try_from(slice)?),
+            "STATUS_CHANGE" => Event::StatusChange(StatusChange::try_from(slice)?),
+            "SCHEMA_CHANGE" => Event::SchemaChange(SchemaChange::try_from(slice)?),
+            _ => bail!("Unknown event type: {}", event_type),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kind of topology change.
+pub enum TopologyChangeType {
+    /// A new node was added to the cluster.
+    NewNode,
+    /// A node was removed from the cluster.
+    RemovedNode,
+    /// A node moved its tokens (deprecated since protocol v4).
+    MovedNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The `TOPOLOGY_CHANGE` event.
+pub struct TopologyChange {
+    /// The kind of topology change.
+    pub change: TopologyChangeType,
+    /// The rpc address of the concerned node.
+    pub address: SocketAddr,
+}
+
+impl TryFrom<&[u8]> for TopologyChange {
+    type Error = anyhow::Error;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        let change = string(slice)?;
+        let (address, _) = inet(&slice[2 + change.len()..])?;
+        let change = match change.as_str() {
+            "NEW_NODE" => TopologyChangeType::NewNode,
+            "REMOVED_NODE" => TopologyChangeType::RemovedNode,
+            "MOVED_NODE" => TopologyChangeType::MovedNode,
+            _ => bail!("Unknown topology change: {}", change),
+        };
+        Ok(Self { change, address })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kind of status change.
+pub enum StatusChangeType {
+    /// The node is up.
+    Up,
+    /// The node is down.
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The `STATUS_CHANGE` event.
+pub struct StatusChange {
+    /// The kind of status change.
+    pub change: StatusChangeType,
+    /// The rpc address of the concerned node.
+    pub address: SocketAddr,
+}
+
+impl TryFrom<&[u8]> for StatusChange {
+    type Error = anyhow::Error;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        let change = string(slice)?;
+        let (address, _) = inet(&slice[2 + change.len()..])?;
+        let change = match change.as_str() {
+            "UP" => StatusChangeType::Up,
+            "DOWN" => StatusChangeType::Down,
+            _ => bail!("Unknown status change: {}", change),
+        };
+        Ok(Self { change, address })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kind of schema change.
+pub enum SchemaChangeType {
+    /// The schema element was created.
+    Created,
+    /// The schema element was updated.
+    Updated,
+    /// The schema element was dropped.
+    Dropped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The schema element affected by a schema change.
+pub enum SchemaChangeTarget {
+    /// The keyspace itself.
+    Keyspace(String),
+    /// A table of the keyspace.
+    Table {
+        /// The keyspace of the table.
+        keyspace: String,
+        /// The table name.
+        table: String,
+    },
+    /// A user defined type of the keyspace.
+    Type {
+        /// The keyspace of the type.
+        keyspace: String,
+        /// The type name.
+        name: String,
+    },
+    /// A user defined function of the keyspace.
+    Function {
+        /// The keyspace of the function.
+        keyspace: String,
+        /// The function name.
+        name: String,
+        /// The cql types of the function arguments.
+        arguments: Vec<String>,
+    },
+    /// A user defined aggregate of the keyspace.
+    Aggregate {
+        /// The keyspace of the aggregate.
+        keyspace: String,
+        /// The aggregate name.
+        name: String,
+        /// The cql types of the aggregate arguments.
+        arguments: Vec<String>,
+    },
+}
+
+impl SchemaChangeTarget {
+    /// Get the keyspace of the schema element.
+    pub fn keyspace(&self) -> &str {
+        match self {
+            SchemaChangeTarget::Keyspace(keyspace)
+            | SchemaChangeTarget::Table { keyspace, .. }
+            | SchemaChangeTarget::Type { keyspace, .. }
+            | SchemaChangeTarget::Function { keyspace, .. }
+            | SchemaChangeTarget::Aggregate { keyspace, .. } => keyspace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The `SCHEMA_CHANGE` event, which shares its layout with the `SCHEMA_CHANGE` result.
+pub struct SchemaChange {
+    /// The kind of schema change.
+    pub change: SchemaChangeType,
+    /// The affected schema element.
+    pub target: SchemaChangeTarget,
+}
+
+impl TryFrom<&[u8]> for SchemaChange {
+    type Error = anyhow::Error;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        let mut strings = Strings { slice, i: 0 };
+        let change = match strings.next()?.as_str() {
+            "CREATED" => SchemaChangeType::Created,
+            "UPDATED" => SchemaChangeType::Updated,
+            "DROPPED" => SchemaChangeType::Dropped,
+            change => bail!("Unknown schema change: {}", change),
+        };
+        let target = strings.next()?;
+        let keyspace = strings.next()?;
+        let target = match target.as_str() {
+            "KEYSPACE" => SchemaChangeTarget::Keyspace(keyspace),
+            "TABLE" => SchemaChangeTarget::Table {
+                keyspace,
+                table: strings.next()?,
+            },
+            "TYPE" => SchemaChangeTarget::Type {
+                keyspace,
+                name: strings.next()?,
+            },
+            "FUNCTION" => SchemaChangeTarget::Function {
+                keyspace,
+                name: strings.next()?,
+                arguments: strings.next_list()?,
+            },
+            "AGGREGATE" => SchemaChangeTarget::Aggregate {
+                keyspace,
+                name: strings.next()?,
+                arguments: strings.next_list()?,
+            },
+            _ => bail!("Unknown schema change target: {}", target),
+        };
+        Ok(Self { change, target })
+    }
+}
+
+/// Helper to read the consecutive [string]s of the schema change.
+struct Strings<'a> {
+    slice: &'a [u8],
+    i: usize,
+}
+
+impl<'a> Strings<'a> {
+    fn next(&mut self) -> anyhow::Result<String> {
+        ensure!(self.slice.len() >= self.i + 2, "Buffer is too small!");
+        let length = u16::from_be_bytes(self.slice[self.i..(self.i + 2)].try_into()?) as usize;
+        ensure!(self.slice.len() >= self.i + 2 + length, "Buffer is too small!");
+        let string = string(&self.slice[self.i..])?;
+        self.i += 2 + length;
+        Ok(string)
+    }
+    fn next_list(&mut self) -> anyhow::Result<Vec<String>> {
+        let (list, length) = string_list_with_returned_bytes_length(
+            self.slice
+                .get(self.i..)
+                .ok_or_else(|| anyhow!("Buffer is too small!"))?,
+        )?;
+        self.i += length;
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::compression::UNCOMPRESSED;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn event_frame(body: &[u8]) -> Decoder {
+        // events are pushed on stream id -1
+        let mut buffer = vec![0x84, 0, 0xff, 0xff, super::super::opcode::EVENT];
+        buffer.extend(&(body.len() as i32).to_be_bytes());
+        buffer.extend(body);
+        Decoder::new(buffer, UNCOMPRESSED).unwrap()
+    }
+
+    fn strings(strings: &[&str]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for string in strings {
+            body.extend(&(string.len() as u16).to_be_bytes());
+            body.extend(string.as_bytes());
+        }
+        body
+    }
+
+    #[test]
+    fn decode_topology_and_status_change() {
+        let mut body = strings(&["TOPOLOGY_CHANGE", "NEW_NODE"]);
+        body.extend(&[4, 10, 0, 0, 7]);
+        body.extend(&9042i32.to_be_bytes());
+        let decoder = event_frame(&body);
+        assert_eq!(decoder.stream().unwrap(), -1);
+        let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7)), 9042);
+        assert_eq!(
+            Event::new(&decoder).unwrap(),
+            Event::TopologyChange(TopologyChange {
+                change: TopologyChangeType::NewNode,
+                address,
+            })
+        );
+        let mut body = strings(&["STATUS_CHANGE", "DOWN"]);
+        body.extend(&[4, 10, 0, 0, 7]);
+        body.extend(&9042i32.to_be_bytes());
+        let event = Event::new(&event_frame(&body)).unwrap();
+        assert_eq!(event.event_type(), EventType::StatusChange);
+        assert_eq!(
+            event,
+            Event::StatusChange(StatusChange {
+                change: StatusChangeType::Down,
+                address,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_schema_change() {
+        let body = strings(&["SCHEMA_CHANGE", "CREATED", "TABLE", "ks", "tbl"]);
+        assert_eq!(
+            Event::new(&event_frame(&body)).unwrap(),
+            Event::SchemaChange(SchemaChange {
+                change: SchemaChangeType::Created,
+                target: SchemaChangeTarget::Table {
+                    keyspace: "ks".to_string(),
+                    table: "tbl".to_string(),
+                },
+            })
+        );
+        let mut body = strings(&["SCHEMA_CHANGE", "DROPPED", "FUNCTION", "ks", "f"]);
+        body.extend(&[0, 2]);
+        body.extend(strings(&["int", "text"]));
+        match Event::new(&event_frame(&body)).unwrap() {
+            Event::SchemaChange(SchemaChange { change, target }) => {
+                assert_eq!(change, SchemaChangeType::Dropped);
+                assert_eq!(target.keyspace(), "ks");
+                assert_eq!(
+                    target,
+                    SchemaChangeTarget::Function {
+                        keyspace: "ks".to_string(),
+                        name: "f".to_string(),
+                        arguments: vec!["int".to_string(), "text".to_string()],
+                    }
+                );
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        // truncated body
+        let body = strings(&["SCHEMA_CHANGE", "UPDATED", "TYPE", "ks"]);
+        assert!(Event::new(&event_frame(&body)).is_err());
+    }
+}
@@ -14,3 +14,7 @@ pub const TRACING: u8 = 0x02;
 pub const CUSTOM_PAYLOAD: u8 = 0x04;
 /// The warning flag.
 pub const WARNING: u8 = 0x08;
+/// The native protocol v4.
+pub const PROTOCOL_V4: u8 = 0x04;
+/// The native protocol v5.
+pub const PROTOCOL_V5: u8 = 0x05;
@@ -3,42 +3,67 @@
 
 //! This crate implements decoder/encoder for a Cassandra frame and the associated protocol.
 //! See `https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec` for more details.
+//! The frames are built as protocol v4 and upgraded to protocol v5 (if negotiated) by [`upgrade_frame`].
 
 pub(crate) mod auth_challenge;
 pub(crate) mod auth_response;
+pub(crate) mod auth_scram;
 pub(crate) mod auth_success;
 pub(crate) mod authenticate;
 pub(crate) mod batch;
 pub(crate) mod batchflags;
 pub(crate) mod consistency;
 pub(crate) mod decoder;
+pub(crate) mod duration;
+pub(crate) mod dynamic;
 pub(crate) mod encoder;
+pub(crate) mod envelope;
 pub(crate) mod error;
+pub(crate) mod event;
 pub(crate) mod header;
 pub(crate) mod opcode;
 pub(crate) mod options;
 pub(crate) mod prepare;
 pub(crate) mod query;
 pub(crate) mod queryflags;
+pub(crate) mod register;
 pub(crate) mod result;
 pub(crate) mod rows;
 pub(crate) mod startup;
 pub(crate) mod supported;
+pub(crate) mod types;
 
-pub use auth_response::{AllowAllAuth, PasswordAuth};
+pub use auth_response::{AllowAllAuth, Authenticator, BoxedAuthenticator, DynAuthenticator, PasswordAuth};
+pub use auth_scram::ScramSha256Auth;
 pub use auth_success::AuthSuccess;
 pub use batch::*;
 pub use consistency::Consistency;
-pub use decoder::{ColumnDecoder, Decoder, Frame, RowsDecoder, VoidDecoder};
+pub use decoder::{decode_first_row, is_prepared_result, ColumnDecoder, Decoder, Frame, RowsDecoder, VoidDecoder};
+pub use duration::CqlDuration;
+pub use dynamic::{ColumnSpec, ColumnType, CqlValue, DynamicRow};
 pub use encoder::{ColumnEncodeChain, ColumnEncoder, TokenEncodeChain, TokenEncoder};
-pub use error::{CqlError, ErrorCodes};
+pub use envelope::{result_metadata_id, set_result_metadata_id, upgrade_frame, upgrade_request};
+pub use error::{Additional, CqlError, ErrorCodes, ReadTimeout, UnavailableException, WriteTimeout, WriteType};
+pub use event::{
+    Event, EventType, SchemaChange, SchemaChangeTarget, SchemaChangeType, StatusChange, StatusChangeType,
+    TopologyChange, TopologyChangeType,
+};
+pub use header::{PROTOCOL_V4, PROTOCOL_V5};
 pub use prepare::Prepare;
 pub use query::{
-    PreparedStatement, Query, QueryBuild, QueryBuilder, QueryConsistency, QueryFlags, QueryPagingState,
-    QuerySerialConsistency, QueryStatement, QueryValues,
+    PreparedStatement, Query, QueryBuild, QueryBuilder, QueryConsistency, QueryFlags, QueryNamedValues,
+    QueryPagingState, QuerySerialConsistency, QueryStatement, QueryValues,
 };
+pub use result::{Prepared, ResultKind, SetKeyspace};
 pub use rows::*;
+#[cfg(feature = "derive")]
+pub use scylla_rs_macros::{ColumnDecoder, ColumnEncoder, Row};
 pub use std::convert::TryInto;
+#[doc(hidden)]
+pub use types::decode_udt_field;
+#[cfg(feature = "num-bigint")]
+pub use types::CqlDecimal;
+pub use types::{Blob, Counter};
 
 /// Big Endian 16-length, used for MD5 ID
 const MD5_BE_LENGTH: [u8; 2] = [0, 16];
@@ -72,3 +97,16 @@ pub trait Values: Sized {
     /// Set Null value, note: for write queries this will create tombstone for V;
     fn null_value(self) -> Self::Return;
 }
+
+/// Defines shared functionality for frames that can receive named statement values, which are bound to the
+/// markers by name rather than position.
+pub trait NamedValues: Sized {
+    /// The return type after applying a named value
+    type Return: NamedValues<Return = Self::Return>;
+    /// Value of type V, bound to the marker `name`.
+    fn named_value<V: ColumnEncoder>(self, name: &str, value: &V) -> Self::Return;
+    /// Unset value, bound to the marker `name`.
+    fn named_unset_value(self, name: &str) -> Self::Return;
+    /// Set Null value bound to the marker `name`, note: for write queries this will create tombstone for V;
+    fn named_null_value(self, name: &str) -> Self::Return;
+}
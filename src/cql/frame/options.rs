@@ -3,10 +3,10 @@
 
 //! This module implements the Options frame.
 
-use super::opcode::OPTIONS;
+use super::{header::PROTOCOL_V4, opcode::OPTIONS};
 
 /// Blanket cql frame header for OPTIONS frame.
-const OPTIONS_HEADER: &'static [u8] = &[4, 0, 0, 0, OPTIONS, 0, 0, 0, 0];
+const OPTIONS_HEADER: &'static [u8] = &[PROTOCOL_V4, 0, 0, 0, OPTIONS, 0, 0, 0, 0];
 
 /// The Options frame structure.
 pub(crate) struct Options(pub Vec<u8>);
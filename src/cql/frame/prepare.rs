@@ -3,12 +3,12 @@
 
 //! This module implements the Prepare frame.
 
-use super::opcode::PREPARE;
+use super::{header::PROTOCOL_V4, opcode::PREPARE};
 
-use crate::cql::compression::{Compression, MyCompression};
+use crate::cql::compression::{Compression, UNCOMPRESSED};
 
 /// Blanket cql frame header for prepare frame.
-const PREPARE_HEADER: &'static [u8] = &[4, 0, 0, 0, PREPARE, 0, 0, 0, 0];
+const PREPARE_HEADER: &'static [u8] = &[PROTOCOL_V4, 0, 0, 0, PREPARE, 0, 0, 0, 0];
 
 /// The prepare frame structure.
 pub struct Prepare(pub Vec<u8>);
@@ -54,11 +54,10 @@ impl PrepareBuilder<PrepareStatement> {
 }
 
 impl PrepareBuilder<PrepareBuild> {
-    /// Build the prepare frame with an assigned compression type.
+    /// Build the prepare frame.
     pub fn build(mut self) -> anyhow::Result<Prepare> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         Ok(Prepare(self.buffer))
     }
 }
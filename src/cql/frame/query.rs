@@ -5,18 +5,27 @@
 
 use super::{
     consistency::Consistency,
-    encoder::{ColumnEncoder, BE_8_BYTES_LEN, BE_NULL_BYTES_LEN, BE_UNSET_BYTES_LEN},
+    encoder::{
+        encode_bytes_map, encode_string, ColumnEncoder, TokenEncodeChain, BE_8_BYTES_LEN, BE_NULL_BYTES_LEN,
+        BE_UNSET_BYTES_LEN,
+    },
+    header::{CUSTOM_PAYLOAD, PROTOCOL_V4, TRACING},
     opcode::{EXECUTE, QUERY},
     queryflags::*,
-    QueryOrPrepared, Statements, Values,
+    NamedValues, QueryOrPrepared, Statements, Values,
 };
-use crate::cql::compression::{Compression, MyCompression};
+use crate::cql::{
+    compression::{Compression, UNCOMPRESSED},
+    murmur3_cassandra_x64_128,
+};
+use std::{collections::HashMap, convert::TryInto};
 
 /// Blanket cql frame header for query frame.
-const QUERY_HEADER: &'static [u8] = &[4, 0, 0, 0, QUERY, 0, 0, 0, 0];
+const QUERY_HEADER: &'static [u8] = &[PROTOCOL_V4, 0, 0, 0, QUERY, 0, 0, 0, 0];
 
 /// Query request builder. Maintains a type-gated stage so that operations
-/// are applied in a valid order.
+/// are applied in a valid order. The values are bound either by position ([`Values`])
+/// or by name ([`NamedValues`]), but can't be mixed in a query.
 ///
 /// ## Example
 /// ```
@@ -59,6 +68,9 @@ pub struct QueryValues {
     value_count: u16,
 }
 
+/// Gating type for named query values, which can't be mixed with positional values
+pub struct QueryNamedValues(QueryValues);
+
 /// Gating type for query paging state
 pub struct QueryPagingState {
     query_flags: QueryFlags,
@@ -98,6 +110,24 @@ impl QueryBuilder<QueryHeader> {
     }
 }
 
+impl<Stage> QueryBuilder<Stage> {
+    /// Request tracing of the query, the tracing id is returned in the response header.
+    pub fn tracing(mut self) -> Self {
+        self.buffer[1] |= TRACING;
+        self
+    }
+}
+
+impl QueryBuilder<QueryStatement> {
+    /// Set the custom payload of the query (or execute) frame, which is passed to the custom query handler of
+    /// the server.
+    pub fn custom_payload(mut self, custom_payload: &HashMap<String, Vec<u8>>) -> Self {
+        self.buffer[1] |= CUSTOM_PAYLOAD;
+        encode_bytes_map(custom_payload, &mut self.buffer);
+        self
+    }
+}
+
 impl QueryOrPrepared for QueryStatement {
     fn encode_statement<T: Statements>(query_or_batch: T, statement: &str) -> T::Return {
         query_or_batch.statement(statement)
@@ -213,7 +243,47 @@ impl Values for QueryBuilder<QueryFlags> {
         }
     }
 }
+impl NamedValues for QueryBuilder<QueryFlags> {
+    type Return = QueryBuilder<QueryNamedValues>;
+    /// Set the first named value in the query frame.
+    fn named_value<V: ColumnEncoder>(self, name: &str, value: &V) -> QueryBuilder<QueryNamedValues> {
+        let mut builder = self.named_values(name);
+        value.encode(&mut builder.buffer);
+        builder
+    }
+    /// Set the first named value to be unset in the query frame.
+    fn named_unset_value(self, name: &str) -> QueryBuilder<QueryNamedValues> {
+        let mut builder = self.named_values(name);
+        builder.buffer.extend(&BE_UNSET_BYTES_LEN);
+        builder
+    }
+    /// Set the first named value to be null in the query frame.
+    fn named_null_value(self, name: &str) -> QueryBuilder<QueryNamedValues> {
+        let mut builder = self.named_values(name);
+        builder.buffer.extend(&BE_NULL_BYTES_LEN);
+        builder
+    }
+}
 impl QueryBuilder<QueryFlags> {
+    /// Push the query flags, the value_count and the name of the first named value.
+    fn named_values(mut self, name: &str) -> QueryBuilder<QueryNamedValues> {
+        // push SKIP_METADATA, VALUES and WITH_NAMES_FOR_VALUES query_flag to the buffer
+        self.buffer.push(SKIP_METADATA | VALUES | WITH_NAMES_FOR_VALUES);
+        let value_count = 1;
+        // push value_count
+        self.buffer.extend(&u16::to_be_bytes(value_count));
+        // push the name of the value
+        encode_string(name, &mut self.buffer);
+        // create query_named_values
+        let query_named_values = QueryNamedValues(QueryValues {
+            query_flags: self.stage,
+            value_count,
+        });
+        QueryBuilder::<QueryNamedValues> {
+            buffer: self.buffer,
+            stage: query_named_values,
+        }
+    }
     /// Set the page size in the query frame, without any value.
     pub fn page_size(mut self, page_size: i32) -> QueryBuilder<QueryPagingState> {
         // push SKIP_METADATA and page_size query_flag to the buffer
@@ -279,14 +349,12 @@ impl QueryBuilder<QueryFlags> {
             stage: query_build,
         }
     }
-    /// Build a query frame with an assigned compression type, without any value.
+    /// Build a query frame, without any value.
     pub fn build(mut self) -> anyhow::Result<Query> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
         // push SKIP_METADATA query_flag to the buffer
         self.buffer.push(SKIP_METADATA);
-        // apply compression to query frame
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         // create query
         Ok(Query(self.buffer))
     }
@@ -319,6 +387,49 @@ impl Values for QueryBuilder<QueryValues> {
     }
 }
 impl QueryBuilder<QueryValues> {
+    /// Get the prepared statement id of the execute frame.
+    pub fn prepared_id(&self) -> Option<[u8; 16]> {
+        if self.buffer[4] == EXECUTE {
+            // the id is followed by the consistency
+            let id_end = self.stage.query_flags.index - 2;
+            self.buffer[(id_end - 16)..id_end].try_into().ok()
+        } else {
+            None
+        }
+    }
+    /// Compute the token of the bound partition key values, given the partition key indexes of the
+    /// prepared statement (see `Prepared::pk_indexes`).
+    ///
+    /// Returns `None` if a partition key value is missing, null or unset.
+    pub fn partition_key_token(&self, pk_indexes: &[u16]) -> Option<i64> {
+        if pk_indexes.is_empty() {
+            return None;
+        }
+        // the values follow the flags and the value_count
+        let mut values = Vec::with_capacity(self.stage.value_count as usize);
+        let mut start = self.stage.query_flags.index + 3;
+        for _ in 0..self.stage.value_count {
+            let length = i32::from_be_bytes(self.buffer.get(start..(start + 4))?.try_into().ok()?);
+            start += 4;
+            if length < 0 {
+                values.push(None);
+            } else {
+                let end = start + length as usize;
+                values.push(Some(self.buffer.get(start..end)?));
+                start = end;
+            }
+        }
+        if let [index] = pk_indexes {
+            let value = (*values.get(*index as usize)?)?;
+            Some(murmur3_cassandra_x64_128(value, 0).0)
+        } else {
+            let mut chain = TokenEncodeChain::default();
+            for index in pk_indexes {
+                chain = chain.chain_bytes((*values.get(*index as usize)?)?);
+            }
+            Some(chain.finish())
+        }
+    }
     /// Set the page size in the query frame, with values.
     pub fn page_size(mut self, page_size: i32) -> QueryBuilder<QueryPagingState> {
         // add page_size query_flag to the buffer
@@ -398,21 +509,79 @@ impl QueryBuilder<QueryValues> {
         }
     }
 
-    /// Build a query frame with an assigned compression type, with values.
+    /// Build a query frame, with values.
     pub fn build(mut self) -> anyhow::Result<Query> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
         // modiy the buffer total value_count
         let start = self.stage.query_flags.index + 1;
         let end = start + 2;
         self.buffer[start..end].copy_from_slice(&self.stage.value_count.to_be_bytes());
-        // apply compression to query frame
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         // create query
         Ok(Query(self.buffer))
     }
 }
 
+impl NamedValues for QueryBuilder<QueryNamedValues> {
+    type Return = QueryBuilder<QueryNamedValues>;
+    /// Set the next named value in the query frame.
+    fn named_value<V: ColumnEncoder>(mut self, name: &str, value: &V) -> Self {
+        // increase the value_count
+        self.stage.0.value_count += 1;
+        // apply name and value
+        encode_string(name, &mut self.buffer);
+        value.encode(&mut self.buffer);
+        self
+    }
+    /// Set the named value to be unset in the query frame.
+    fn named_unset_value(mut self, name: &str) -> Self {
+        // increase the value_count
+        self.stage.0.value_count += 1;
+        // apply name and value
+        encode_string(name, &mut self.buffer);
+        self.buffer.extend(&BE_UNSET_BYTES_LEN);
+        self
+    }
+    /// Set the named value to be null in the query frame.
+    fn named_null_value(mut self, name: &str) -> Self {
+        // increase the value_count
+        self.stage.0.value_count += 1;
+        // apply name and value
+        encode_string(name, &mut self.buffer);
+        self.buffer.extend(&BE_NULL_BYTES_LEN);
+        self
+    }
+}
+impl QueryBuilder<QueryNamedValues> {
+    /// The named values only differ from the positional ones by their names, so the remaining stages are shared.
+    fn values(self) -> QueryBuilder<QueryValues> {
+        QueryBuilder::<QueryValues> {
+            buffer: self.buffer,
+            stage: self.stage.0,
+        }
+    }
+    /// Set the page size in the query frame, with named values.
+    pub fn page_size(self, page_size: i32) -> QueryBuilder<QueryPagingState> {
+        self.values().page_size(page_size)
+    }
+    /// Set the paging state in the query frame, with named values.
+    pub fn paging_state(self, paging_state: &Option<Vec<u8>>) -> QueryBuilder<QuerySerialConsistency> {
+        self.values().paging_state(paging_state)
+    }
+    /// Set serial consistency for the query frame, with named values.
+    pub fn serial_consistency(self, consistency: Consistency) -> QueryBuilder<QueryTimestamp> {
+        self.values().serial_consistency(consistency)
+    }
+    /// Set the timestamp of the query frame, with named values.
+    pub fn timestamp(self, timestamp: i64) -> QueryBuilder<QueryBuild> {
+        self.values().timestamp(timestamp)
+    }
+    /// Build a query frame, with named values.
+    pub fn build(self) -> anyhow::Result<Query> {
+        self.values().build()
+    }
+}
+
 impl QueryBuilder<QueryPagingState> {
     /// Set the paging state in the query frame.
     pub fn paging_state(mut self, paging_state: &Option<Vec<u8>>) -> QueryBuilder<QuerySerialConsistency> {
@@ -462,12 +631,10 @@ impl QueryBuilder<QueryPagingState> {
         }
     }
 
-    /// Build a query frame with an assigned compression type.
+    /// Build a query frame.
     pub fn build(mut self) -> anyhow::Result<Query> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
-        // apply compression to query frame
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         // create query
         Ok(Query(self.buffer))
     }
@@ -504,12 +671,10 @@ impl QueryBuilder<QuerySerialConsistency> {
         }
     }
 
-    /// Build a query frame with an assigned compression type.
+    /// Build a query frame.
     pub fn build(mut self) -> anyhow::Result<Query> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
-        // apply compression to query frame
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         // create query
         Ok(Query(self.buffer))
     }
@@ -530,24 +695,20 @@ impl QueryBuilder<QueryTimestamp> {
             stage: query_build,
         }
     }
-    /// Build a query frame with an assigned compression type.
+    /// Build a query frame.
     pub fn build(mut self) -> anyhow::Result<Query> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
-        // apply compression to query frame
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         // create query
         Ok(Query(self.buffer))
     }
 }
 
 impl QueryBuilder<QueryBuild> {
-    /// Build a query frame with an assigned compression type.
+    /// Build a query frame.
     pub fn build(mut self) -> anyhow::Result<Query> {
-        // apply compression flag(if any to the header)
-        self.buffer[1] |= MyCompression::flag();
-        // apply compression to query frame
-        self.buffer = MyCompression::get().compress(self.buffer)?;
+        // adjust the body length, the frame is compressed by the connection which carries it
+        self.buffer = UNCOMPRESSED.compress(self.buffer)?;
         // create query
         Ok(Query(self.buffer))
     }
@@ -595,4 +756,91 @@ mod tests {
             .build()
             .unwrap();
     }
+
+    #[test]
+    fn partition_key_token_test() {
+        use crate::cql::TokenEncoder;
+        let id = [7; 16];
+        let builder = Query::new()
+            .custom_payload(&HashMap::new())
+            .id(&id)
+            .consistency(Consistency::One)
+            .value(&"name")
+            .value(&42i32)
+            .null_value()
+            .value(&"bucket");
+        assert_eq!(builder.prepared_id(), Some(id));
+        assert_eq!(builder.partition_key_token(&[1]), Some(42i32.get_token()));
+        assert_eq!(
+            builder.partition_key_token(&[3, 1]),
+            Some("bucket".chain_token(&42i32).finish())
+        );
+        assert_eq!(builder.partition_key_token(&[2]), None);
+        assert_eq!(builder.partition_key_token(&[4]), None);
+        let builder = Query::new()
+            .statement("SELECT * FROM keyspace.table WHERE key = ?")
+            .consistency(Consistency::One)
+            .value(&42i32);
+        assert_eq!(builder.prepared_id(), None);
+    }
+
+    #[test]
+    fn named_values_query_builder_test() {
+        let Query(payload) = Query::new()
+            .statement("INSERT INTO keyspace.table (key, value) VALUES (:key, :value)")
+            .consistency(Consistency::One)
+            .named_value("key", &42i32)
+            .named_null_value("value")
+            .page_size(10)
+            .build()
+            .unwrap();
+        // the query flags follow the header, the statement and the consistency
+        let flags_index = 9 + 4 + 61 + 2;
+        assert_eq!(
+            payload[flags_index],
+            SKIP_METADATA | VALUES | WITH_NAMES_FOR_VALUES | PAGE_SIZE
+        );
+        assert_eq!(&payload[(flags_index + 1)..(flags_index + 3)], &[0, 2]);
+        assert_eq!(
+            &payload[(flags_index + 3)..],
+            &[
+                0, 3, b'k', b'e', b'y', 0, 0, 0, 4, 0, 0, 0, 42, 0, 5, b'v', b'a', b'l', b'u', b'e', 255, 255, 255,
+                255, 0, 0, 0, 10
+            ][..]
+        );
+    }
+
+    #[test]
+    fn tracing_query_builder_test() {
+        let Query(payload) = Query::new()
+            .statement("SELECT * FROM keyspace.table")
+            .tracing()
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert_eq!(payload[1] & TRACING, TRACING);
+        let Query(payload) = Query::new()
+            .statement("SELECT * FROM keyspace.table")
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert_eq!(payload[1] & TRACING, 0);
+    }
+
+    #[test]
+    fn custom_payload_query_builder_test() {
+        let mut custom_payload = HashMap::new();
+        custom_payload.insert("key".to_string(), vec![1, 2, 3]);
+        let Query(payload) = Query::new()
+            .custom_payload(&custom_payload)
+            .id(&[0; 16])
+            .consistency(Consistency::One)
+            .build()
+            .unwrap();
+        assert_eq!(payload[1] & CUSTOM_PAYLOAD, CUSTOM_PAYLOAD);
+        assert_eq!(payload[4], EXECUTE);
+        // the custom payload precedes the prepared id
+        assert_eq!(&payload[9..23], &[0, 1, 0, 3, b'k', b'e', b'y', 0, 0, 0, 3, 1, 2, 3]);
+        assert_eq!(&payload[23..25], &[0, 16]);
+    }
 }
@@ -15,3 +15,5 @@ pub const PAGING_STATE: u8 = 0x08;
 pub const SERIAL_CONSISTENCY: u8 = 0x10;
 /// The query flag indicates whether to use the default timestamp or not.
 pub const TIMESTAMP: u8 = 0x20;
+/// The query flag indicates that the values are preceded by their names.
+pub const WITH_NAMES_FOR_VALUES: u8 = 0x40;
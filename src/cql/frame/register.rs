@@ -0,0 +1,73 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the Register frame.
+
+use super::{event::EventType, header::PROTOCOL_V4, opcode::REGISTER};
+
+/// Blanket cql frame header for REGISTER frame.
+const REGISTER_HEADER: &[u8] = &[PROTOCOL_V4, 0, 0, 0, REGISTER, 0, 0, 0, 0];
+
+/// The Register frame structure.
+pub(crate) struct Register(pub Vec<u8>);
+
+pub(crate) struct RegisterBuilder<Stage> {
+    buffer: Vec<u8>,
+    #[allow(unused)]
+    stage: Stage,
+}
+
+pub(crate) struct RegisterEvents;
+pub(crate) struct RegisterBuild;
+
+impl RegisterBuilder<RegisterEvents> {
+    /// Create a new Register frame builder.
+    pub(crate) fn new() -> Self {
+        let mut buffer: Vec<u8> = Vec::new();
+        buffer.extend_from_slice(REGISTER_HEADER);
+        Self {
+            buffer,
+            stage: RegisterEvents,
+        }
+    }
+    /// Set the event types to register for.
+    pub fn events(mut self, events: &[EventType]) -> RegisterBuilder<RegisterBuild> {
+        self.buffer.extend(&u16::to_be_bytes(events.len() as u16));
+        for event in events {
+            let event = event.as_str();
+            self.buffer.extend(&u16::to_be_bytes(event.len() as u16));
+            self.buffer.extend(event.bytes());
+        }
+        let body_length = i32::to_be_bytes((self.buffer.len() as i32) - 9);
+        self.buffer[5..9].copy_from_slice(&body_length);
+        RegisterBuilder {
+            buffer: self.buffer,
+            stage: RegisterBuild,
+        }
+    }
+}
+
+impl RegisterBuilder<RegisterBuild> {
+    /// Build the Register frame.
+    pub(crate) fn build(self) -> Register {
+        Register(self.buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_register_builder_test() {
+        let Register(payload) = RegisterBuilder::new()
+            .events(&[EventType::TopologyChange, EventType::StatusChange])
+            .build();
+        assert_eq!(payload[4], REGISTER);
+        assert_eq!(&payload[5..9], &34i32.to_be_bytes());
+        assert_eq!(&payload[9..13], &[0, 2, 0, 15]);
+        assert_eq!(&payload[13..28], b"TOPOLOGY_CHANGE");
+        assert_eq!(&payload[28..30], &[0, 13]);
+        assert_eq!(&payload[30..], b"STATUS_CHANGE");
+    }
+}
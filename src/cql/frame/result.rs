@@ -1,11 +1,275 @@
 // Copyright 2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-//! This module defines the information in result frame.
+//! This module defines the information in result frame, and implements the decoders of the `SET_KEYSPACE`,
+//! `PREPARED` and `SCHEMA_CHANGE` results.
 
-#![allow(unused)]
+use super::{
+    decoder::{Decoder, Frame},
+    dynamic::column_specs,
+    event::SchemaChange,
+    header,
+    rows::Flags,
+    ColumnSpec,
+};
+use anyhow::{anyhow, bail, ensure};
+use std::convert::{TryFrom, TryInto};
+
+/// The `VOID` result kind.
 pub const VOID: i32 = 0x0001;
+/// The `ROWS` result kind.
 pub const ROWS: i32 = 0x0002;
+/// The `SET_KEYSPACE` result kind.
 pub const SETKEYSPACE: i32 = 0x0003;
+/// The `PREPARED` result kind.
 pub const PREPARED: i32 = 0x0004;
+/// The `SCHEMA_CHANGE` result kind.
 pub const SCHEMACHANGE: i32 = 0x0005;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The decoded RESULT frame, the rows are decoded by the [`super::Rows`] iterators instead.
+pub enum ResultKind {
+    /// The request returned nothing.
+    Void,
+    /// The request returned rows.
+    Rows,
+    /// The `USE` request set the keyspace.
+    SetKeyspace(SetKeyspace),
+    /// The statement was prepared.
+    Prepared(Prepared),
+    /// The request altered the schema.
+    SchemaChange(SchemaChange),
+}
+
+impl ResultKind {
+    /// Create a ResultKind from the frame decoder.
+    pub fn new(decoder: &Decoder) -> anyhow::Result<Self> {
+        ensure!(decoder.is_result()?, "Not a result frame!");
+        Ok(match decoder.body_kind()? {
+            VOID => ResultKind::Void,
+            ROWS => ResultKind::Rows,
+            SETKEYSPACE => ResultKind::SetKeyspace(SetKeyspace::new(decoder)?),
+            PREPARED => ResultKind::Prepared(Prepared::new(decoder)?),
+            SCHEMACHANGE => ResultKind::SchemaChange(SchemaChange::from_result(decoder)?),
+            kind => bail!("Unknown result kind: {}", kind),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The `SET_KEYSPACE` result of a `USE` request.
+pub struct SetKeyspace {
+    /// The keyspace which is now used by the connection.
+    pub keyspace: String,
+}
+
+impl SetKeyspace {
+    /// Create a SetKeyspace from the frame decoder.
+    pub fn new(decoder: &Decoder) -> anyhow::Result<Self> {
+        ensure!(decoder.is_set_keyspace()?, "Not a set keyspace result!");
+        let mut i = 4;
+        Ok(Self {
+            keyspace: string(decoder.body()?, &mut i)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The `PREPARED` result of a PREPARE request.
+pub struct Prepared {
+    /// The id of the prepared statement, which is the MD5 digest of the statement.
+    pub id: [u8; 16],
+    /// The id of the result metadata (protocol v5).
+    pub result_metadata_id: Option<Vec<u8>>,
+    /// The indexes of the bind variables which form the partition key, in the order of the partition key columns.
+    pub pk_indexes: Vec<u16>,
+    /// The specs of the bind variables.
+    pub bind_specs: Vec<ColumnSpec>,
+    /// The specs of the result columns, which are omitted for the statements which don't return rows.
+    pub result_specs: Option<Vec<ColumnSpec>>,
+}
+
+impl Prepared {
+    /// Create a Prepared from the frame decoder.
+    pub fn new(decoder: &Decoder) -> anyhow::Result<Self> {
+        ensure!(decoder.is_prepared()?, "Not a prepared result!");
+        let body = decoder.body()?;
+        let mut i = 4;
+        let id = short_bytes(body, &mut i)?;
+        let id = id
+            .try_into()
+            .map_err(|_| anyhow!("Invalid prepared id length {}", id.len()))?;
+        let result_metadata_id = if decoder.protocol_version()? >= header::PROTOCOL_V5 {
+            Some(short_bytes(body, &mut i)?.to_vec())
+        } else {
+            None
+        };
+        // <flags><columns_count><pk_count>[<pk_index>]*[<global_table_spec>]<col_spec>*
+        let flags = Flags::from_i32(int(body, &mut i)?);
+        let columns_count = int(body, &mut i)?;
+        let pk_count = int(body, &mut i)?;
+        let mut pk_indexes = Vec::with_capacity(pk_count.max(0) as usize);
+        for _ in 0..pk_count {
+            ensure!(body.len() >= i + 2, "Buffer is too small!");
+            pk_indexes.push(u16::from_be_bytes(body[i..(i + 2)].try_into()?));
+            i += 2;
+        }
+        let (bind_specs, end) = column_specs(body, i, flags.global_table_spec(), columns_count)?;
+        i = end;
+        // the result metadata, with the same layout as the rows metadata
+        let flags = Flags::from_i32(int(body, &mut i)?);
+        let columns_count = int(body, &mut i)?;
+        if flags.has_more_pages() {
+            let length = int(body, &mut i)?;
+            i += length.max(0) as usize;
+        }
+        if flags.metadata_changed() && result_metadata_id.is_some() {
+            short_bytes(body, &mut i)?;
+        }
+        let result_specs = if flags.no_metadata() {
+            None
+        } else {
+            Some(column_specs(body, i, flags.global_table_spec(), columns_count)?.0)
+        };
+        Ok(Self {
+            id,
+            result_metadata_id,
+            pk_indexes,
+            bind_specs,
+            result_specs,
+        })
+    }
+}
+
+impl SchemaChange {
+    /// Create a SchemaChange from the frame decoder of a `SCHEMA_CHANGE` result.
+    pub fn from_result(decoder: &Decoder) -> anyhow::Result<Self> {
+        ensure!(decoder.is_schema_change()?, "Not a schema change result!");
+        Self::try_from(&decoder.body()?[4..])
+    }
+}
+
+/// Decode the [int] at `i` in the body.
+fn int(body: &[u8], i: &mut usize) -> anyhow::Result<i32> {
+    ensure!(body.len() >= *i + 4, "Buffer is too small!");
+    let int = i32::from_be_bytes(body[*i..(*i + 4)].try_into()?);
+    *i += 4;
+    Ok(int)
+}
+
+/// Decode the [short bytes] at `i` in the body.
+fn short_bytes<'a>(body: &'a [u8], i: &mut usize) -> anyhow::Result<&'a [u8]> {
+    ensure!(body.len() >= *i + 2, "Buffer is too small!");
+    let length = u16::from_be_bytes(body[*i..(*i + 2)].try_into()?) as usize;
+    *i += 2;
+    ensure!(body.len() >= *i + length, "Buffer is too small!");
+    let bytes = &body[*i..(*i + length)];
+    *i += length;
+    Ok(bytes)
+}
+
+/// Decode the [string] at `i` in the body.
+fn string(body: &[u8], i: &mut usize) -> anyhow::Result<String> {
+    Ok(String::from_utf8(short_bytes(body, i)?.to_vec())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cql::{compression::UNCOMPRESSED, is_prepared_result, ColumnType, SchemaChangeTarget, SchemaChangeType};
+
+    fn result_frame(version: u8, body: &[u8]) -> Decoder {
+        let mut buffer = vec![0x80 | version, 0, 0, 0, super::super::opcode::RESULT];
+        buffer.extend(&(body.len() as i32).to_be_bytes());
+        buffer.extend(body);
+        Decoder::new(buffer, UNCOMPRESSED).unwrap()
+    }
+
+    fn strings(body: &mut Vec<u8>, strings: &[&str]) {
+        for string in strings {
+            body.extend(&(string.len() as u16).to_be_bytes());
+            body.extend(string.as_bytes());
+        }
+    }
+
+    /// The PREPARED result of `SELECT name FROM ks.people WHERE id = ? AND bucket = ?`, the partition key is
+    /// (bucket, id).
+    fn prepared_body(v5: bool) -> Vec<u8> {
+        let mut body = PREPARED.to_be_bytes().to_vec();
+        body.extend(&[0, 16]);
+        body.extend(&[7; 16]);
+        if v5 {
+            body.extend(&[0, 2, 0xab, 0xcd]);
+        }
+        // flags (global_table_spec), columns_count, pk_count, pk_indexes
+        for int in [1i32, 2, 2].iter() {
+            body.extend(&int.to_be_bytes());
+        }
+        body.extend(&[0, 1, 0, 0]);
+        strings(&mut body, &["ks", "people", "id"]);
+        body.extend(&[0x00, 0x09]);
+        strings(&mut body, &["bucket"]);
+        body.extend(&[0x00, 0x13]);
+        // result metadata, flags (global_table_spec), columns_count
+        for int in [1i32, 1].iter() {
+            body.extend(&int.to_be_bytes());
+        }
+        strings(&mut body, &["ks", "people", "name"]);
+        body.extend(&[0x00, 0x0D]);
+        body
+    }
+
+    #[test]
+    fn decode_prepared() {
+        for (version, v5) in [(header::PROTOCOL_V4, false), (header::PROTOCOL_V5, true)].iter() {
+            let decoder = result_frame(*version, &prepared_body(*v5));
+            assert!(is_prepared_result(decoder.buffer_as_ref()));
+            let prepared = match ResultKind::new(&decoder).unwrap() {
+                ResultKind::Prepared(prepared) => prepared,
+                kind => panic!("unexpected result kind {:?}", kind),
+            };
+            assert_eq!(prepared.id, [7; 16]);
+            assert_eq!(
+                prepared.result_metadata_id,
+                if *v5 { Some(vec![0xab, 0xcd]) } else { None }
+            );
+            assert_eq!(prepared.pk_indexes, vec![1, 0]);
+            assert_eq!(prepared.bind_specs.len(), 2);
+            assert_eq!(prepared.bind_specs[1].name, "bucket");
+            assert_eq!(prepared.bind_specs[1].column_type, ColumnType::Smallint);
+            let result_specs = prepared.result_specs.unwrap();
+            assert_eq!(result_specs[0].name, "name");
+            assert_eq!(result_specs[0].column_type, ColumnType::Varchar);
+        }
+    }
+
+    #[test]
+    fn decode_set_keyspace_and_schema_change() {
+        let mut body = SETKEYSPACE.to_be_bytes().to_vec();
+        strings(&mut body, &["ks"]);
+        assert!(!is_prepared_result(
+            result_frame(header::PROTOCOL_V4, &body).buffer_as_ref()
+        ));
+        assert_eq!(
+            ResultKind::new(&result_frame(header::PROTOCOL_V4, &body)).unwrap(),
+            ResultKind::SetKeyspace(SetKeyspace {
+                keyspace: "ks".to_string()
+            })
+        );
+        let mut body = SCHEMACHANGE.to_be_bytes().to_vec();
+        strings(&mut body, &["DROPPED", "KEYSPACE", "ks"]);
+        assert_eq!(
+            ResultKind::new(&result_frame(header::PROTOCOL_V4, &body)).unwrap(),
+            ResultKind::SchemaChange(SchemaChange {
+                change: SchemaChangeType::Dropped,
+                target: SchemaChangeTarget::Keyspace("ks".to_string()),
+            })
+        );
+        let body = VOID.to_be_bytes();
+        assert_eq!(
+            ResultKind::new(&result_frame(header::PROTOCOL_V4, &body)).unwrap(),
+            ResultKind::Void
+        );
+        assert!(Prepared::new(&result_frame(header::PROTOCOL_V4, &body)).is_err());
+    }
+}
@@ -3,14 +3,15 @@
 
 //! This module defines the row/column decoder/encoder for the frame structure.
 
-use super::{ColumnDecoder, Frame};
-use anyhow::ensure;
+use super::{Blob, ColumnDecoder, ColumnSpec, Counter, CqlDuration, Frame};
+use anyhow::{bail, ensure};
 use log::error;
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
     convert::TryInto,
     hash::Hash,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::{Arc, OnceLock},
 };
 
 /// The column count type.
@@ -21,6 +22,7 @@ pub struct Flags {
     global_table_spec: bool,
     has_more_pages: bool,
     no_metadata: bool,
+    metadata_changed: bool,
 }
 
 impl Flags {
@@ -30,12 +32,25 @@ impl Flags {
             global_table_spec: (flags & 1) == 1,
             has_more_pages: (flags & 2) == 2,
             no_metadata: (flags & 4) == 4,
+            metadata_changed: (flags & 8) == 8,
         }
     }
     /// Check if are there more pages to decode.
     pub fn has_more_pages(&self) -> bool {
         self.has_more_pages
     }
+    /// Check if the column specs share a global table spec.
+    pub fn global_table_spec(&self) -> bool {
+        self.global_table_spec
+    }
+    /// Check if the column specs are omitted.
+    pub fn no_metadata(&self) -> bool {
+        self.no_metadata
+    }
+    /// Check if the result metadata has changed since the statement was prepared (protocol v5).
+    pub fn metadata_changed(&self) -> bool {
+        self.metadata_changed
+    }
 }
 #[derive(Debug, Clone)]
 /// The pageing state of the response.
@@ -48,6 +63,14 @@ impl PagingState {
     pub fn new(paging_state: Option<Vec<u8>>, end: usize) -> Self {
         PagingState { paging_state, end }
     }
+    /// Get the end of the paging state in the buffer.
+    pub(crate) fn end(&self) -> usize {
+        self.end
+    }
+    /// Move the end, which is where the rows start, past the result metadata which follows the paging state.
+    pub(crate) fn set_end(&mut self, end: usize) {
+        self.end = end;
+    }
 }
 #[derive(Debug, Clone)]
 /// The meta structure of the row.
@@ -55,6 +78,7 @@ pub struct Metadata {
     flags: Flags,
     columns_count: ColumnsCount,
     paging_state: PagingState,
+    column_specs_start: Option<usize>,
 }
 
 impl Metadata {
@@ -64,8 +88,23 @@ impl Metadata {
             flags,
             columns_count,
             paging_state,
+            column_specs_start: None,
         }
     }
+    /// Set the start of the column specs of the result metadata in the frame buffer.
+    pub(crate) fn set_column_specs_start(&mut self, column_specs_start: usize) {
+        self.column_specs_start = Some(column_specs_start);
+    }
+    /// Decode the column specs of the result from the frame buffer, which are omitted if the `no_metadata` flag is
+    /// set.
+    pub fn column_specs(&self, buffer: &[u8]) -> anyhow::Result<Option<Vec<ColumnSpec>>> {
+        self.column_specs_start
+            .map(|start| {
+                super::dynamic::column_specs(buffer, start, self.flags.global_table_spec(), self.columns_count)
+                    .map(|(column_specs, _)| column_specs)
+            })
+            .transpose()
+    }
     /// Get the starting rows.
     pub fn rows_start(&self) -> usize {
         self.paging_state.end
@@ -110,6 +149,37 @@ pub trait Row: Sized {
 pub trait ColumnValue {
     /// Decode the column value of C type;
     fn column_value<C: ColumnDecoder>(&mut self) -> anyhow::Result<C>;
+    /// Get the next column value as its raw bytes, which are `None` if the value is null.
+    fn column_bytes(&mut self) -> anyhow::Result<Option<&[u8]>> {
+        bail!("The raw column bytes are not supported")
+    }
+    /// Get the column specs of the result-set, if the result metadata is included.
+    fn column_specs(&self) -> anyhow::Result<Option<&Arc<[ColumnSpec]>>> {
+        Ok(None)
+    }
+}
+
+/// Check that the column names of the result-set match the names of the decoded fields, which is
+/// used by the `Row` derive macro in the name-based mode.
+#[doc(hidden)]
+pub fn check_column_names(columns: &[ColumnSpec], fields: &[&str]) -> anyhow::Result<()> {
+    ensure!(
+        columns.len() == fields.len(),
+        "Expected {} columns {:?}, got {} columns {:?}",
+        fields.len(),
+        fields,
+        columns.len(),
+        columns.iter().map(|column| &column.name).collect::<Vec<_>>()
+    );
+    for (column, field) in columns.iter().zip(fields.iter()) {
+        ensure!(
+            column.name == *field,
+            "Expected column {}, got column {}",
+            field,
+            column.name
+        );
+    }
+    Ok(())
 }
 
 /// An iterator over the rows of a result-set
@@ -121,6 +191,7 @@ pub struct Iter<T: Row> {
     column_start: usize,
     remaining_rows_count: usize,
     metadata: Metadata,
+    column_specs: OnceLock<Option<Arc<[ColumnSpec]>>>,
     _marker: std::marker::PhantomData<T>,
 }
 impl<T: Row> Iter<T> {
@@ -151,6 +222,7 @@ impl<T: Row> Rows for Iter<T> {
         Ok(Self {
             decoder,
             metadata,
+            column_specs: OnceLock::new(),
             rows_count: rows_count as usize,
             remaining_rows_count: rows_count as usize,
             column_start,
@@ -177,24 +249,35 @@ impl<T: Row> Iterator for Iter<T> {
 
 impl<T: Row> ColumnValue for Iter<T> {
     fn column_value<C: ColumnDecoder>(&mut self) -> anyhow::Result<C> {
+        C::try_decode(self.column_bytes()?.unwrap_or(&[]))
+    }
+    fn column_bytes(&mut self) -> anyhow::Result<Option<&[u8]>> {
         ensure!(
             self.decoder.buffer_as_ref().len() >= self.column_start + 4,
             "Buffer is too small!"
         );
         let length = i32::from_be_bytes(self.decoder.buffer_as_ref()[self.column_start..][..4].try_into()?);
         self.column_start += 4; // now it become the column_value start, or next column_start if length < 0
-        if length > 0 {
+        if length >= 0 {
             ensure!(
                 self.decoder.buffer_as_ref().len() >= self.column_start + length as usize,
                 "Buffer is too small!"
             );
-            let col_slice = self.decoder.buffer_as_ref()[self.column_start..][..(length as usize)].into();
+            let col_slice = &self.decoder.buffer_as_ref()[self.column_start..][..(length as usize)];
             // update the next column_start to start from next column
             self.column_start += length as usize;
-            C::try_decode(col_slice)
+            Ok(Some(col_slice))
         } else {
-            C::try_decode(&[])
+            Ok(None)
+        }
+    }
+    fn column_specs(&self) -> anyhow::Result<Option<&Arc<[ColumnSpec]>>> {
+        // the column specs are decoded once, by the first row which needs them
+        if self.column_specs.get().is_none() {
+            let column_specs = self.metadata.column_specs(self.decoder.buffer_as_ref())?;
+            self.column_specs.get_or_init(|| column_specs.map(Into::into));
         }
+        Ok(self.column_specs.get().and_then(Option::as_ref))
     }
 }
 
@@ -364,6 +447,42 @@ impl Row for Ipv6Addr {
     }
 }
 
+impl Row for bool {
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        rows.column_value()
+    }
+}
+
+impl Row for Blob {
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        rows.column_value()
+    }
+}
+
+impl Row for Counter {
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        rows.column_value()
+    }
+}
+
+impl Row for CqlDuration {
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        rows.column_value()
+    }
+}
+
 impl<E> Row for Vec<E>
 where
     E: ColumnDecoder,
@@ -390,6 +509,31 @@ where
     }
 }
 
+impl<E, S> Row for HashSet<E, S>
+where
+    E: Eq + Hash + ColumnDecoder,
+    S: ::std::hash::BuildHasher + Default,
+{
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        rows.column_value()
+    }
+}
+
+impl<E> Row for BTreeSet<E>
+where
+    E: Ord + ColumnDecoder,
+{
+    fn try_decode_row<R: Rows + ColumnValue>(rows: &mut R) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        rows.column_value()
+    }
+}
+
 #[macro_export]
 /// The rows macro implements the row decoder.
 macro_rules! rows {
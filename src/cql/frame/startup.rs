@@ -3,7 +3,7 @@
 
 //! This module implements the Startup frame.
 
-use super::opcode::STARTUP;
+use super::{header::PROTOCOL_V4, opcode::STARTUP};
 use std::collections::HashMap;
 
 pub(crate) struct StartupBuilder<Stage> {
@@ -19,7 +19,7 @@ pub(crate) struct StartupBuild;
 pub(crate) struct Startup(pub Vec<u8>);
 
 /// Blanket cql frame header for startup frame.
-const STARTUP_HEADER: &'static [u8] = &[4, 0, 0, 0, STARTUP, 0, 0, 0, 0];
+const STARTUP_HEADER: &'static [u8] = &[PROTOCOL_V4, 0, 0, 0, STARTUP, 0, 0, 0, 0];
 
 impl StartupBuilder<StartupHeader> {
     pub fn new() -> StartupBuilder<StartupOptions> {
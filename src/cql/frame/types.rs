@@ -0,0 +1,458 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the cql types which have no std counterpart: `blob`, `counter` and `tuple`,
+//! along with `uuid`/`timeuuid` (`uuid` feature), `timestamp`/`date`/`time` (`chrono` feature) and
+//! `varint`/`decimal` (`num-bigint` feature).
+
+use super::{
+    decoder::ColumnDecoder,
+    encoder::{ColumnEncoder, BE_0_BYTES_LEN},
+};
+use anyhow::{anyhow, ensure};
+use std::{
+    convert::TryInto,
+    ops::{Deref, DerefMut},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+/// The cql `blob`, as a `Vec<u8>` is encoded as a `list<tinyint>`.
+pub struct Blob(pub Vec<u8>);
+
+impl From<Vec<u8>> for Blob {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Blob> for Vec<u8> {
+    fn from(blob: Blob) -> Self {
+        blob.0
+    }
+}
+
+impl Deref for Blob {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Blob {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl ColumnEncoder for Blob {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend(&i32::to_be_bytes(self.0.len() as i32));
+        buffer.extend(&self.0);
+    }
+}
+
+impl ColumnDecoder for Blob {
+    fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self(slice.to_vec()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+/// The cql `counter`, a 64-bit signed value which can only be incremented or decremented.
+pub struct Counter(pub i64);
+
+impl ColumnEncoder for Counter {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.0.encode(buffer)
+    }
+}
+
+impl ColumnDecoder for Counter {
+    fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+        i64::try_decode(slice).map(Self)
+    }
+}
+
+/// Decode the next tuple element at `start`, a null element is decoded from an empty slice.
+fn decode_element<E: ColumnDecoder>(slice: &[u8], start: &mut usize) -> anyhow::Result<E> {
+    ensure!(slice.len() >= *start + 4, "Buffer is too small!");
+    let length = i32::from_be_bytes(slice[*start..(*start + 4)].try_into()?);
+    *start += 4;
+    if length > 0 {
+        let end = *start + length as usize;
+        ensure!(slice.len() >= end, "Buffer is too small!");
+        let element = E::try_decode(&slice[*start..end])?;
+        *start = end;
+        Ok(element)
+    } else {
+        E::try_decode(&[])
+    }
+}
+
+/// Decode the next field of a UDT value, the trailing fields which are missing from the value
+/// (ie added to the type after the value was written) are decoded as nulls.
+#[doc(hidden)]
+pub fn decode_udt_field<E: ColumnDecoder>(slice: &[u8], start: &mut usize, name: &str) -> anyhow::Result<E> {
+    if *start >= slice.len() {
+        E::try_decode(&[])
+    } else {
+        decode_element(slice, start)
+    }
+    .map_err(|e| anyhow!("Failed to decode the UDT field {}: {}", name, e))
+}
+
+macro_rules! tuple {
+    (($($t:tt),*)) => {
+        #[allow(non_snake_case)]
+        impl<$($t: ColumnEncoder),*> ColumnEncoder for ($($t,)*) {
+            fn encode(&self, buffer: &mut Vec<u8>) {
+                let ($($t,)*) = self;
+                // the tuple byte_size is computed once all the elements are encoded
+                buffer.extend(&BE_0_BYTES_LEN);
+                let current_length = buffer.len();
+                $(
+                    $t.encode(buffer);
+                )*
+                let tuple_byte_size = buffer.len() - current_length;
+                buffer[(current_length - 4)..current_length].copy_from_slice(&i32::to_be_bytes(tuple_byte_size as i32));
+            }
+        }
+        impl<$($t: ColumnDecoder),*> ColumnDecoder for ($($t,)*) {
+            fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+                let mut start = 0;
+                Ok((
+                    $(
+                        decode_element::<$t>(slice, &mut start)?,
+                    )*
+                ))
+            }
+        }
+    };
+}
+
+tuple!((T));
+tuple!((T, TT));
+tuple!((T, TT, TTT));
+tuple!((T, TT, TTT, TTTT));
+tuple!((T, TT, TTT, TTTT, TTTTT));
+tuple!((T, TT, TTT, TTTT, TTTTT, TTTTTT));
+tuple!((T, TT, TTT, TTTT, TTTTT, TTTTTT, TTTTTTT));
+tuple!((T, TT, TTT, TTTT, TTTTT, TTTTTT, TTTTTTT, TTTTTTTT));
+tuple!((T, TT, TTT, TTTT, TTTTT, TTTTTT, TTTTTTT, TTTTTTTT, TTTTTTTTT));
+tuple!((T, TT, TTT, TTTT, TTTTT, TTTTTT, TTTTTTT, TTTTTTTT, TTTTTTTTT, TTTTTTTTTT));
+tuple!((
+    T,
+    TT,
+    TTT,
+    TTTT,
+    TTTTT,
+    TTTTTT,
+    TTTTTTT,
+    TTTTTTTT,
+    TTTTTTTTT,
+    TTTTTTTTTT,
+    TTTTTTTTTTT
+));
+tuple!((
+    T,
+    TT,
+    TTT,
+    TTTT,
+    TTTTT,
+    TTTTTT,
+    TTTTTTT,
+    TTTTTTTT,
+    TTTTTTTTT,
+    TTTTTTTTTT,
+    TTTTTTTTTTT,
+    TTTTTTTTTTTT
+));
+tuple!((
+    T,
+    TT,
+    TTT,
+    TTTT,
+    TTTTT,
+    TTTTTT,
+    TTTTTTT,
+    TTTTTTTT,
+    TTTTTTTTT,
+    TTTTTTTTTT,
+    TTTTTTTTTTT,
+    TTTTTTTTTTTT,
+    TTTTTTTTTTTTT
+));
+tuple!((
+    T,
+    TT,
+    TTT,
+    TTTT,
+    TTTTT,
+    TTTTTT,
+    TTTTTTT,
+    TTTTTTTT,
+    TTTTTTTTT,
+    TTTTTTTTTT,
+    TTTTTTTTTTT,
+    TTTTTTTTTTTT,
+    TTTTTTTTTTTTT,
+    TTTTTTTTTTTTTT
+));
+tuple!((
+    T,
+    TT,
+    TTT,
+    TTTT,
+    TTTTT,
+    TTTTTT,
+    TTTTTTT,
+    TTTTTTTT,
+    TTTTTTTTT,
+    TTTTTTTTTT,
+    TTTTTTTTTTT,
+    TTTTTTTTTTTT,
+    TTTTTTTTTTTTT,
+    TTTTTTTTTTTTTT,
+    TTTTTTTTTTTTTTT
+));
+tuple!((
+    T,
+    TT,
+    TTT,
+    TTTT,
+    TTTTT,
+    TTTTTT,
+    TTTTTTT,
+    TTTTTTTT,
+    TTTTTTTTT,
+    TTTTTTTTTT,
+    TTTTTTTTTTT,
+    TTTTTTTTTTTT,
+    TTTTTTTTTTTTT,
+    TTTTTTTTTTTTTT,
+    TTTTTTTTTTTTTTT,
+    TTTTTTTTTTTTTTTT
+));
+
+#[cfg(feature = "uuid")]
+mod uuid_types {
+    use super::*;
+    use crate::cql::frame::encoder::BE_16_BYTES_LEN;
+    use uuid::Uuid;
+
+    /// The `uuid` and `timeuuid` types.
+    impl ColumnEncoder for Uuid {
+        fn encode(&self, buffer: &mut Vec<u8>) {
+            buffer.extend(&BE_16_BYTES_LEN);
+            buffer.extend(self.as_bytes());
+        }
+    }
+
+    impl ColumnDecoder for Uuid {
+        fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+            Ok(Uuid::from_slice(slice)?)
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_types {
+    use super::*;
+    use anyhow::anyhow;
+    use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+
+    /// The `date` of the unix epoch is encoded as 2^31.
+    const EPOCH_DAYS: i64 = 1 << 31;
+
+    fn epoch() -> NaiveDate {
+        NaiveDate::from_ymd_opt(1970, 1, 1).expect("The unix epoch is a valid date")
+    }
+
+    /// The `timestamp` type, the number of milliseconds since the unix epoch.
+    impl ColumnEncoder for DateTime<Utc> {
+        fn encode(&self, buffer: &mut Vec<u8>) {
+            self.timestamp_millis().encode(buffer)
+        }
+    }
+
+    impl ColumnDecoder for DateTime<Utc> {
+        fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+            let millis = i64::try_decode(slice)?;
+            Utc.timestamp_millis_opt(millis)
+                .single()
+                .ok_or_else(|| anyhow!("Timestamp {} is out of range!", millis))
+        }
+    }
+
+    /// The `date` type, the number of days since the unix epoch centered at 2^31.
+    impl ColumnEncoder for NaiveDate {
+        fn encode(&self, buffer: &mut Vec<u8>) {
+            let days = self.signed_duration_since(epoch()).num_days() + EPOCH_DAYS;
+            (days as u32).encode(buffer)
+        }
+    }
+
+    impl ColumnDecoder for NaiveDate {
+        fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+            let days = u32::try_decode(slice)? as i64 - EPOCH_DAYS;
+            epoch()
+                .checked_add_signed(Duration::days(days))
+                .ok_or_else(|| anyhow!("Date {} is out of range!", days))
+        }
+    }
+
+    /// The `time` type, the number of nanoseconds since midnight.
+    impl ColumnEncoder for NaiveTime {
+        fn encode(&self, buffer: &mut Vec<u8>) {
+            let nanos = self.num_seconds_from_midnight() as i64 * 1_000_000_000 + self.nanosecond() as i64;
+            nanos.encode(buffer)
+        }
+    }
+
+    impl ColumnDecoder for NaiveTime {
+        fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+            let nanos = i64::try_decode(slice)?;
+            ensure!(nanos >= 0, "Time {} is out of range!", nanos);
+            NaiveTime::from_num_seconds_from_midnight_opt(
+                (nanos / 1_000_000_000) as u32,
+                (nanos % 1_000_000_000) as u32,
+            )
+            .ok_or_else(|| anyhow!("Time {} is out of range!", nanos))
+        }
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+pub use bigint_types::CqlDecimal;
+
+#[cfg(feature = "num-bigint")]
+mod bigint_types {
+    use super::*;
+    use num_bigint::BigInt;
+
+    /// The `varint` type, an arbitrary-precision integer.
+    impl ColumnEncoder for BigInt {
+        fn encode(&self, buffer: &mut Vec<u8>) {
+            let bytes = self.to_signed_bytes_be();
+            buffer.extend(&i32::to_be_bytes(bytes.len() as i32));
+            buffer.extend(bytes);
+        }
+    }
+
+    impl ColumnDecoder for BigInt {
+        fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+            Ok(BigInt::from_signed_bytes_be(slice))
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+    /// The cql `decimal`, an arbitrary-precision unscaled value and its scale, ie `unscaled * 10^-scale`.
+    pub struct CqlDecimal {
+        /// The unscaled value.
+        pub unscaled: BigInt,
+        /// The scale.
+        pub scale: i32,
+    }
+
+    impl CqlDecimal {
+        /// Create a new decimal.
+        pub fn new(unscaled: BigInt, scale: i32) -> Self {
+            Self { unscaled, scale }
+        }
+    }
+
+    impl ColumnEncoder for CqlDecimal {
+        fn encode(&self, buffer: &mut Vec<u8>) {
+            let bytes = self.unscaled.to_signed_bytes_be();
+            buffer.extend(&i32::to_be_bytes(4 + bytes.len() as i32));
+            buffer.extend(&i32::to_be_bytes(self.scale));
+            buffer.extend(bytes);
+        }
+    }
+
+    impl ColumnDecoder for CqlDecimal {
+        fn try_decode(slice: &[u8]) -> anyhow::Result<Self> {
+            ensure!(slice.len() >= 4, "Buffer is too small!");
+            Ok(Self {
+                scale: i32::from_be_bytes(slice[..4].try_into()?),
+                unscaled: BigInt::from_signed_bytes_be(&slice[4..]),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, HashSet};
+
+    fn round_trip<T: ColumnEncoder + ColumnDecoder + PartialEq + std::fmt::Debug>(value: T) {
+        let buffer = value.encode_new();
+        assert_eq!(
+            i32::from_be_bytes(buffer[..4].try_into().unwrap()) as usize,
+            buffer.len() - 4
+        );
+        assert_eq!(T::try_decode(&buffer[4..]).unwrap(), value);
+    }
+
+    #[test]
+    fn std_types_round_trip() {
+        round_trip(true);
+        round_trip(false);
+        round_trip(Blob(vec![1, 2, 3]));
+        round_trip(Counter(-42));
+        round_trip(vec![1i32, 2, 3].into_iter().collect::<HashSet<_>>());
+        round_trip(
+            vec!["a".to_string(), "b".to_string()]
+                .into_iter()
+                .collect::<BTreeSet<_>>(),
+        );
+        round_trip((1i32, "two".to_string(), Some(3i64), None::<String>));
+    }
+
+    #[test]
+    fn blob_is_not_a_list() {
+        assert_eq!(Blob(vec![1, 2]).encode_new(), vec![0, 0, 0, 2, 1, 2]);
+        assert_eq!(
+            vec![1u8, 2].encode_new(),
+            vec![0, 0, 0, 14, 0, 0, 0, 2, 0, 0, 0, 1, 1, 0, 0, 0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn tuple_with_null_element() {
+        // (int, text) with a null text
+        let slice = [0, 0, 0, 4, 0, 0, 0, 7, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(<(i32, Option<String>)>::try_decode(&slice).unwrap(), (7, None));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_round_trip() {
+        round_trip(uuid::Uuid::from_bytes([7; 16]));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_types_round_trip() {
+        use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+        round_trip(Utc.timestamp_millis_opt(1_600_000_000_123).unwrap());
+        round_trip(NaiveDate::from_ymd_opt(1969, 12, 31).unwrap());
+        round_trip(NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap());
+        // the unix epoch is 2^31 days
+        assert_eq!(
+            NaiveDate::from_ymd_opt(1970, 1, 1).unwrap().encode_new(),
+            vec![0, 0, 0, 4, 0x80, 0, 0, 0]
+        );
+    }
+
+    #[cfg(feature = "num-bigint")]
+    #[test]
+    fn bigint_types_round_trip() {
+        use num_bigint::BigInt;
+        round_trip(BigInt::from(-129));
+        round_trip(CqlDecimal::new(BigInt::from(12345), 2));
+        assert_eq!(BigInt::from(-129).encode_new(), vec![0, 0, 0, 2, 0xff, 0x7f]);
+    }
+}
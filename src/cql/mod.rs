@@ -3,19 +3,25 @@
 
 //! This module implements the binary Cql protocol V4.
 //! See `https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec` for more details.
+//! The binary Cql protocol V5 is supported as well, see the segment module.
 
 #![warn(missing_docs)]
+mod codec;
 pub mod compression;
 mod connection;
 mod frame;
 mod murmur3;
+mod segment;
 mod tests;
 
+pub use codec::{frame_length, FrameCodec, FRAME_HEADER_LENGTH, MAX_FRAME_LENGTH};
 pub use connection::*;
 /// This is the public API of this module
 pub use frame::*;
 
 pub use murmur3::murmur3_cassandra_x64_128;
 
+pub use segment::{SegmentCodec, SegmentDecoder, MAX_SEGMENT_PAYLOAD_LENGTH};
+
 /// expose MyCompression
-pub use compression::MyCompression;
+pub use compression::{CompressionStats, MyCompression};
@@ -0,0 +1,85 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the checksums of the protocol v5 segments.
+
+/// The initial value of the CRC24 header checksum.
+const CRC24_INIT: u32 = 0x875060;
+/// The polynomial of the CRC24 header checksum.
+const CRC24_POLY: u32 = 0x1974F0B;
+/// The bytes which seed the CRC32 payload checksum.
+const CRC32_INITIAL_BYTES: [u8; 4] = [0xFA, 0x2D, 0x55, 0xCA];
+/// The lookup table of the (IEEE) CRC32.
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Compute the CRC24 of the segment header bytes.
+pub(crate) fn crc24(header: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for byte in header {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc
+}
+
+/// Compute the CRC32 of the segment payload.
+pub(crate) fn crc32(payload: &[u8]) -> u32 {
+    let update = |crc: u32, bytes: &[u8]| {
+        bytes.iter().fold(crc, |crc, byte| {
+            CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+        })
+    };
+    !update(update(!0, &CRC32_INITIAL_BYTES), payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_ieee() {
+        // the seed bytes are part of the checksummed data
+        let mut data = CRC32_INITIAL_BYTES.to_vec();
+        data.extend(b"123456789");
+        let ieee = |bytes: &[u8]| {
+            !bytes.iter().fold(!0u32, |crc, byte| {
+                CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+            })
+        };
+        assert_eq!(ieee(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b"123456789"), ieee(&data));
+    }
+
+    #[test]
+    fn crc24_fits_in_three_bytes() {
+        assert_eq!(crc24(&[]), CRC24_INIT);
+        for header in [[0u8, 0, 0], [0xff, 0xff, 0x03], [0x12, 0x34, 0x02]].iter() {
+            assert!(crc24(header) < 1 << 24);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the segment framing of the binary Cql protocol V5.
+//! See `https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v5.spec` for more details.
+//!
+//! Once a v5 connection is ready, every frame is carried in one or more segments. A self-contained segment
+//! holds one or more complete frames, while a frame larger than the max payload is split across several
+//! non self-contained segments. The segment payload is optionally compressed with LZ4.
+
+mod crc;
+
+use super::codec::{frame_length, FRAME_HEADER_LENGTH};
+use anyhow::{anyhow, bail, ensure};
+use crc::{crc24, crc32};
+use std::{collections::VecDeque, convert::TryInto};
+
+/// The max payload length of a single segment (128KiB - 1).
+pub const MAX_SEGMENT_PAYLOAD_LENGTH: usize = (1 << 17) - 1;
+/// The length of the uncompressed segment header including its CRC24.
+const UNCOMPRESSED_HEADER_LENGTH: usize = 6;
+/// The length of the LZ4 compressed segment header including its CRC24.
+const COMPRESSED_HEADER_LENGTH: usize = 8;
+/// The length of the payload CRC32.
+const CRC32_LENGTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The segment codec of a v5 connection, which is either uncompressed or LZ4 compressed.
+pub struct SegmentCodec {
+    lz4: bool,
+}
+
+impl SegmentCodec {
+    /// Create the uncompressed segment codec
+    pub fn uncompressed() -> Self {
+        Self { lz4: false }
+    }
+    /// Create the LZ4 compressed segment codec
+    pub fn lz4() -> Self {
+        Self { lz4: true }
+    }
+    /// Check if the segment payloads are LZ4 compressed
+    pub fn is_lz4(&self) -> bool {
+        self.lz4
+    }
+    /// Create a decoder which reassembles the frames of this codec
+    pub fn decoder(&self) -> SegmentDecoder {
+        SegmentDecoder {
+            codec: *self,
+            buffer: Vec::new(),
+            large_frame: None,
+            frames: VecDeque::new(),
+        }
+    }
+    /// Encode the frame into the segments, large frames are split into non self-contained segments
+    pub fn encode(&self, frame: &[u8], buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+        if frame.len() <= MAX_SEGMENT_PAYLOAD_LENGTH {
+            self.encode_segment(frame, true, buffer)
+        } else {
+            for chunk in frame.chunks(MAX_SEGMENT_PAYLOAD_LENGTH) {
+                self.encode_segment(chunk, false, buffer)?;
+            }
+            Ok(())
+        }
+    }
+    /// Encode the frame into a new segments buffer
+    pub fn encode_new(&self, frame: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let segments = frame.len() / MAX_SEGMENT_PAYLOAD_LENGTH + 1;
+        let mut buffer = Vec::with_capacity(frame.len() + segments * (COMPRESSED_HEADER_LENGTH + CRC32_LENGTH));
+        self.encode(frame, &mut buffer)?;
+        Ok(buffer)
+    }
+    fn encode_segment(&self, payload: &[u8], self_contained: bool, buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+        ensure!(
+            payload.len() <= MAX_SEGMENT_PAYLOAD_LENGTH,
+            "Segment payload is too large!"
+        );
+        let self_contained_flag = self_contained as u64;
+        if self.lz4 {
+            let compressed = lz4::block::compress(payload, None, false)?;
+            // store the payload uncompressed (with zero uncompressed length) if compression doesn't pay off
+            let (payload, uncompressed_length) = if compressed.len() < payload.len() {
+                (compressed.as_slice(), payload.len() as u64)
+            } else {
+                (payload, 0)
+            };
+            let header = payload.len() as u64 | uncompressed_length << 17 | self_contained_flag << 34;
+            let header = &header.to_le_bytes()[..5];
+            buffer.extend(header);
+            buffer.extend(&crc24(header).to_le_bytes()[..3]);
+            buffer.extend(payload);
+            buffer.extend(&crc32(payload).to_le_bytes());
+        } else {
+            let header = payload.len() as u64 | self_contained_flag << 17;
+            let header = &header.to_le_bytes()[..3];
+            buffer.extend(header);
+            buffer.extend(&crc24(header).to_le_bytes()[..3]);
+            buffer.extend(payload);
+            buffer.extend(&crc32(payload).to_le_bytes());
+        }
+        Ok(())
+    }
+}
+
+/// The segment decoder, which reassembles the frames out of the received segments.
+pub struct SegmentDecoder {
+    codec: SegmentCodec,
+    buffer: Vec<u8>,
+    large_frame: Option<Vec<u8>>,
+    frames: VecDeque<Vec<u8>>,
+}
+
+impl SegmentDecoder {
+    /// Push the received bytes into the decoder
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+    /// Get the next complete frame (if any)
+    pub fn next_frame(&mut self) -> anyhow::Result<Option<Vec<u8>>> {
+        while self.frames.is_empty() {
+            if !self.decode_segment()? {
+                break;
+            }
+        }
+        Ok(self.frames.pop_front())
+    }
+    /// Decode the next segment in the buffer, returns false if the segment is not complete yet
+    fn decode_segment(&mut self) -> anyhow::Result<bool> {
+        let header_length = if self.codec.lz4 {
+            COMPRESSED_HEADER_LENGTH
+        } else {
+            UNCOMPRESSED_HEADER_LENGTH
+        };
+        if self.buffer.len() < header_length {
+            return Ok(false);
+        }
+        let crc_start = header_length - 3;
+        let mut header = [0; 8];
+        header[..crc_start].copy_from_slice(&self.buffer[..crc_start]);
+        let header = u64::from_le_bytes(header);
+        let mut crc = [0; 4];
+        crc[..3].copy_from_slice(&self.buffer[crc_start..header_length]);
+        ensure!(
+            crc24(&self.buffer[..crc_start]) == u32::from_le_bytes(crc),
+            "Segment header CRC24 mismatch!"
+        );
+        let payload_length = (header & MAX_SEGMENT_PAYLOAD_LENGTH as u64) as usize;
+        let (uncompressed_length, self_contained) = if self.codec.lz4 {
+            (
+                ((header >> 17) & MAX_SEGMENT_PAYLOAD_LENGTH as u64) as usize,
+                header & (1 << 34) != 0,
+            )
+        } else {
+            (0, header & (1 << 17) != 0)
+        };
+        let segment_length = header_length + payload_length + CRC32_LENGTH;
+        if self.buffer.len() < segment_length {
+            return Ok(false);
+        }
+        let payload = &self.buffer[header_length..(header_length + payload_length)];
+        ensure!(
+            crc32(payload)
+                == u32::from_le_bytes(self.buffer[(segment_length - CRC32_LENGTH)..segment_length].try_into()?),
+            "Segment payload CRC32 mismatch!"
+        );
+        let payload = if uncompressed_length > 0 {
+            lz4::block::decompress(payload, Some(uncompressed_length as i32))?
+        } else {
+            payload.to_vec()
+        };
+        self.buffer.drain(..segment_length);
+        if self_contained {
+            ensure!(
+                self.large_frame.is_none(),
+                "Self-contained segment received in the middle of a large frame!"
+            );
+            self.split_frames(payload)?;
+        } else {
+            let large_frame = self.large_frame.get_or_insert_with(Vec::new);
+            large_frame.extend(payload);
+            if large_frame.len() >= FRAME_HEADER_LENGTH {
+                let frame_length = frame_length(large_frame)?;
+                if large_frame.len() >= frame_length {
+                    ensure!(
+                        large_frame.len() == frame_length,
+                        "Non self-contained segments carry more than one frame!"
+                    );
+                    let frame = self.large_frame.take().ok_or_else(|| anyhow!("No large frame!"))?;
+                    self.frames.push_back(frame);
+                }
+            }
+        }
+        Ok(true)
+    }
+    fn split_frames(&mut self, payload: Vec<u8>) -> anyhow::Result<()> {
+        let mut start = 0;
+        while start < payload.len() {
+            let frame_length = frame_length(&payload[start..])?;
+            if start + frame_length > payload.len() {
+                bail!("Self-contained segment has an incomplete frame!");
+            }
+            self.frames.push_back(payload[start..(start + frame_length)].to_vec());
+            start += frame_length;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(stream: u8, body_length: usize) -> Vec<u8> {
+        let mut frame = vec![5, 0, 0, stream, 0x08];
+        frame.extend(&(body_length as i32).to_be_bytes());
+        frame.extend((0..body_length).map(|i| (i % 7) as u8));
+        frame
+    }
+
+    #[test]
+    fn uncompressed_segment_layout() {
+        let frame = frame(1, 3);
+        let segment = SegmentCodec::uncompressed().encode_new(&frame).unwrap();
+        assert_eq!(segment.len(), 6 + frame.len() + 4);
+        // 12 bytes payload with the self-contained flag
+        assert_eq!(&segment[..3], &[12, 0, 2]);
+        assert_eq!(&segment[6..18], frame.as_slice());
+    }
+
+    #[test]
+    fn segments_round_trip() {
+        for codec in [SegmentCodec::uncompressed(), SegmentCodec::lz4()].iter() {
+            let frames = vec![frame(1, 10), frame(2, 0), frame(3, 300_000), frame(4, 42)];
+            let mut bytes = Vec::new();
+            for frame in frames.iter() {
+                codec.encode(frame, &mut bytes).unwrap();
+            }
+            let mut decoder = codec.decoder();
+            let mut decoded = Vec::new();
+            // feed the decoder in small chunks to simulate partial reads
+            for chunk in bytes.chunks(1000) {
+                decoder.push(chunk);
+                while let Some(frame) = decoder.next_frame().unwrap() {
+                    decoded.push(frame);
+                }
+            }
+            assert_eq!(decoded, frames);
+        }
+    }
+
+    #[test]
+    fn corrupted_segment_is_rejected() {
+        let mut segment = SegmentCodec::uncompressed().encode_new(&frame(1, 10)).unwrap();
+        let last = segment.len() - 5;
+        segment[last] ^= 0xff;
+        let mut decoder = SegmentCodec::uncompressed().decoder();
+        decoder.push(&segment);
+        assert!(decoder.next_frame().is_err());
+        let mut segment = SegmentCodec::lz4().encode_new(&frame(1, 10)).unwrap();
+        segment[0] ^= 0xff;
+        let mut decoder = SegmentCodec::lz4().decoder();
+        decoder.push(&segment);
+        assert!(decoder.next_frame().is_err());
+    }
+}
@@ -1,3 +1,11 @@
 // Copyright 2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 mod connection;
+#[cfg(test)]
+mod protocol_v5;
+#[cfg(all(test, feature = "derive"))]
+mod row;
+#[cfg(test)]
+mod tls;
+#[cfg(all(test, feature = "derive"))]
+mod udt;
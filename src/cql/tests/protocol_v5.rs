@@ -0,0 +1,289 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cql::{Cql, Event, EventType, Peer, SegmentCodec, StatusChange, StatusChangeType, PROTOCOL_V4, PROTOCOL_V5};
+use std::{convert::TryInto, net::SocketAddr};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Spawn a local server which speaks the cql handshake and answers every query with the system.local row,
+/// a REGISTER is answered with READY followed by a STATUS_CHANGE event and system.peers lists two peers
+/// (system.peers_v2 doesn't exist).
+async fn spawn_server(supports_v5: bool) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream, supports_v5));
+        }
+    });
+    address
+}
+
+async fn serve(mut stream: TcpStream, supports_v5: bool) -> anyhow::Result<()> {
+    // OPTIONS -> SUPPORTED
+    let options = read_frame(&mut stream).await?;
+    let version = options[0];
+    if version == PROTOCOL_V5 && !supports_v5 {
+        let mut body = 0x000A_i32.to_be_bytes().to_vec();
+        let message = "Invalid or unsupported protocol version (5)";
+        body.extend(&(message.len() as u16).to_be_bytes());
+        body.extend(message.as_bytes());
+        stream.write_all(&frame(PROTOCOL_V4, 0x00, &body)).await?;
+        return Ok(());
+    }
+    stream.write_all(&frame(version, 0x06, &supported_body())).await?;
+    // STARTUP -> READY, which is never carried in segments
+    read_frame(&mut stream).await?;
+    stream.write_all(&frame(version, 0x02, &[])).await?;
+    if version == PROTOCOL_V5 {
+        let codec = SegmentCodec::uncompressed();
+        let mut decoder = codec.decoder();
+        let mut buffer = vec![0; 1024];
+        loop {
+            let n = stream.read(&mut buffer).await?;
+            anyhow::ensure!(n != 0, "connection closed");
+            decoder.push(&buffer[..n]);
+            while let Some(query) = decoder.next_frame()? {
+                anyhow::ensure!(query[0] == PROTOCOL_V5, "not a v5 frame");
+                for response in responses(version, &query) {
+                    stream.write_all(&codec.encode_new(&response)?).await?;
+                }
+            }
+        }
+    } else {
+        loop {
+            let query = read_frame(&mut stream).await?;
+            for response in responses(version, &query) {
+                stream.write_all(&response).await?;
+            }
+        }
+    }
+}
+
+fn responses(version: u8, request: &[u8]) -> Vec<Vec<u8>> {
+    if request[4] == 0x0B {
+        let mut body = Vec::new();
+        for string in ["STATUS_CHANGE", "DOWN"].iter() {
+            body.extend(&(string.len() as u16).to_be_bytes());
+            body.extend(string.as_bytes());
+        }
+        body.extend(&[4, 127, 0, 0, 2]);
+        body.extend(&9042i32.to_be_bytes());
+        let mut event = frame(version, 0x0C, &body);
+        // events are pushed on stream id -1
+        event[2..4].copy_from_slice(&(-1i16).to_be_bytes());
+        vec![frame(version, 0x02, &[]), event]
+    } else if contains(request, b"system.peers_v2") {
+        let mut body = 0x2200_i32.to_be_bytes().to_vec();
+        let message = "unconfigured table peers_v2";
+        body.extend(&(message.len() as u16).to_be_bytes());
+        body.extend(message.as_bytes());
+        vec![frame(version, 0x00, &body)]
+    } else if contains(request, b"system.peers") {
+        vec![frame(version, 0x08, &peers_body())]
+    } else {
+        vec![frame(version, 0x08, &rows_body(version == PROTOCOL_V5))]
+    }
+}
+
+fn contains(request: &[u8], statement: &[u8]) -> bool {
+    request.windows(statement.len()).any(|window| window == statement)
+}
+
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = vec![0; 9];
+    stream.read_exact(&mut buffer).await?;
+    let body_length = i32::from_be_bytes(buffer[5..9].try_into()?);
+    buffer.resize(body_length as usize + 9, 0);
+    stream.read_exact(&mut buffer[9..]).await?;
+    Ok(buffer)
+}
+
+fn frame(version: u8, opcode: u8, body: &[u8]) -> Vec<u8> {
+    let mut frame = vec![version | 0x80, 0, 0, 0, opcode];
+    frame.extend(&(body.len() as i32).to_be_bytes());
+    frame.extend(body);
+    frame
+}
+
+fn supported_body() -> Vec<u8> {
+    let options = [
+        ("CQL_VERSION", "3.4.5"),
+        ("SCYLLA_SHARD", "0"),
+        ("SCYLLA_NR_SHARDS", "1"),
+        ("SCYLLA_SHARDING_IGNORE_MSB", "12"),
+        ("SCYLLA_SHARD_AWARE_PORT", "19042"),
+    ];
+    let mut body: Vec<u8> = Vec::new();
+    body.extend(&(options.len() as u16).to_be_bytes());
+    for (key, value) in options.iter() {
+        body.extend(&(key.len() as u16).to_be_bytes());
+        body.extend(key.as_bytes());
+        body.extend(&1u16.to_be_bytes());
+        body.extend(&(value.len() as u16).to_be_bytes());
+        body.extend(value.as_bytes());
+    }
+    body
+}
+
+/// The ROWS result of `SELECT data_center, rack, tokens FROM system.local`, with the changed metadata (v5)
+/// or without metadata.
+fn rows_body(metadata_changed: bool) -> Vec<u8> {
+    let mut body: Vec<u8> = Vec::new();
+    if metadata_changed {
+        // kind, flags (global_table_spec | metadata_changed), columns_count
+        for int in [2i32, 9, 3].iter() {
+            body.extend(&int.to_be_bytes());
+        }
+        // new_metadata_id
+        body.extend(&[0, 2, 0xab, 0xcd]);
+        for string in ["system", "local", "data_center"].iter() {
+            body.extend(&(string.len() as u16).to_be_bytes());
+            body.extend(string.as_bytes());
+        }
+        // varchar
+        body.extend(&[0x00, 0x0D]);
+        body.extend(&[0, 4]);
+        body.extend(b"rack");
+        // varchar
+        body.extend(&[0x00, 0x0D]);
+        body.extend(&[0, 6]);
+        body.extend(b"tokens");
+        // set<varchar>
+        body.extend(&[0x00, 0x22, 0x00, 0x0D]);
+    } else {
+        // kind, flags (no_metadata), columns_count
+        for int in [2i32, 4, 3].iter() {
+            body.extend(&int.to_be_bytes());
+        }
+    }
+    // rows_count
+    body.extend(&1i32.to_be_bytes());
+    for string in ["dc1", "rack1"].iter() {
+        body.extend(&(string.len() as i32).to_be_bytes());
+        body.extend(string.as_bytes());
+    }
+    let mut tokens: Vec<u8> = 2i32.to_be_bytes().to_vec();
+    for token in ["-42", "42"].iter() {
+        tokens.extend(&(token.len() as i32).to_be_bytes());
+        tokens.extend(token.as_bytes());
+    }
+    body.extend(&(tokens.len() as i32).to_be_bytes());
+    body.extend(tokens);
+    body
+}
+
+/// The ROWS result of `SELECT peer, rpc_address, data_center, rack, tokens FROM system.peers`.
+fn peers_body() -> Vec<u8> {
+    let mut body: Vec<u8> = Vec::new();
+    // kind, flags (global_table_spec), columns_count
+    for int in [2i32, 1, 5].iter() {
+        body.extend(&int.to_be_bytes());
+    }
+    for string in ["system", "peers"].iter() {
+        body.extend(&(string.len() as u16).to_be_bytes());
+        body.extend(string.as_bytes());
+    }
+    // inet, inet, varchar, varchar, set<varchar>
+    let columns: [(&str, &[u8]); 5] = [
+        ("peer", &[0x00, 0x10]),
+        ("rpc_address", &[0x00, 0x10]),
+        ("data_center", &[0x00, 0x0D]),
+        ("rack", &[0x00, 0x0D]),
+        ("tokens", &[0x00, 0x22, 0x00, 0x0D]),
+    ];
+    for (name, option) in columns.iter() {
+        body.extend(&(name.len() as u16).to_be_bytes());
+        body.extend(name.as_bytes());
+        body.extend(*option);
+    }
+    // rows_count
+    body.extend(&2i32.to_be_bytes());
+    // the first peer listens on all interfaces
+    let peers = [
+        ([127, 0, 0, 2], [0, 0, 0, 0], "7"),
+        ([127, 0, 0, 3], [127, 0, 0, 3], "-7"),
+    ];
+    for (peer, rpc_address, token) in peers.iter() {
+        for address in [peer, rpc_address].iter() {
+            body.extend(&4i32.to_be_bytes());
+            body.extend(address.iter());
+        }
+        for string in ["dc1", "rack1"].iter() {
+            body.extend(&(string.len() as i32).to_be_bytes());
+            body.extend(string.as_bytes());
+        }
+        let mut tokens: Vec<u8> = 1i32.to_be_bytes().to_vec();
+        tokens.extend(&(token.len() as i32).to_be_bytes());
+        tokens.extend(token.as_bytes());
+        body.extend(&(tokens.len() as i32).to_be_bytes());
+        body.extend(tokens);
+    }
+    body
+}
+
+#[tokio::test]
+async fn negotiate_protocol_v5() {
+    let address = spawn_server(true).await;
+    let mut cql = Cql::new().address(address).tokens().build().await.unwrap();
+    assert_eq!(cql.protocol_version(), PROTOCOL_V5);
+    assert_eq!(cql.segment_codec(), Some(SegmentCodec::uncompressed()));
+    assert_eq!(cql.take_dc().as_deref(), Some("dc1"));
+    assert_eq!(cql.take_rack().as_deref(), Some("rack1"));
+    assert_eq!(cql.take_tokens(), Some(vec![-42, 42]));
+}
+
+#[tokio::test]
+async fn fall_back_to_protocol_v4() {
+    let address = spawn_server(false).await;
+    let mut cql = Cql::new().address(address).tokens().build().await.unwrap();
+    assert_eq!(cql.protocol_version(), PROTOCOL_V4);
+    assert!(cql.segment_codec().is_none());
+    assert_eq!(cql.take_tokens(), Some(vec![-42, 42]));
+    // the negotiation can be skipped
+    let cql = Cql::new()
+        .address(address)
+        .protocol_version(PROTOCOL_V4)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(cql.protocol_version(), PROTOCOL_V4);
+}
+
+#[tokio::test]
+async fn register_for_events() {
+    for supports_v5 in [true, false].iter() {
+        let address = spawn_server(*supports_v5).await;
+        let mut cql = Cql::new().address(address).build().await.unwrap();
+        cql.register(&[EventType::StatusChange]).await.unwrap();
+        assert_eq!(
+            cql.next_event().await.unwrap(),
+            Event::StatusChange(StatusChange {
+                change: StatusChangeType::Down,
+                address: "127.0.0.2:9042".parse().unwrap(),
+            })
+        );
+    }
+}
+
+#[tokio::test]
+async fn discover_peers() {
+    for supports_v5 in [true, false].iter() {
+        let address = spawn_server(*supports_v5).await;
+        let mut cql = Cql::new().address(address).tokens().peers().build().await.unwrap();
+        assert_eq!(cql.take_tokens(), Some(vec![-42, 42]));
+        let peer = |ip: &str, token| Peer {
+            address: SocketAddr::new(ip.parse().unwrap(), address.port()),
+            data_center: "dc1".to_string(),
+            rack: "rack1".to_string(),
+            tokens: vec![token],
+        };
+        assert_eq!(
+            cql.take_peers(),
+            Some(vec![peer("127.0.0.2", 7), peer("127.0.0.3", -7)])
+        );
+    }
+}
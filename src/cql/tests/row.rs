@@ -0,0 +1,133 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cql::{compression::UNCOMPRESSED, decode_first_row, Decoder, Iter, Row, Rows};
+
+#[derive(Row, Debug, PartialEq)]
+struct Person {
+    name: String,
+    age: Option<i32>,
+    #[cql(skip)]
+    cached: Vec<u8>,
+    tags: Vec<String>,
+}
+
+#[derive(Row, Debug, PartialEq)]
+struct PersonTuple(String, Option<i32>, Vec<String>);
+
+#[derive(Row, Debug, PartialEq)]
+#[cql(by_name)]
+struct NamedPerson {
+    name: String,
+    #[cql(rename = "age")]
+    years: Option<i32>,
+    tags: Vec<String>,
+}
+
+#[derive(Row, Debug, PartialEq)]
+#[cql(by_name)]
+struct WrongPerson {
+    name: String,
+    tags: Vec<String>,
+    age: Option<i32>,
+}
+
+/// The ROWS result of `SELECT name, age, tags FROM ks.people`, the second row has a null age.
+fn rows_decoder(no_metadata: bool) -> Decoder {
+    let mut body: Vec<u8> = Vec::new();
+    // kind, flags (global_table_spec or no_metadata), columns_count
+    let flags = if no_metadata { 4 } else { 1 };
+    for int in [2i32, flags, 3].iter() {
+        body.extend(&int.to_be_bytes());
+    }
+    if !no_metadata {
+        for string in ["ks", "people"].iter() {
+            body.extend(&(string.len() as u16).to_be_bytes());
+            body.extend(string.as_bytes());
+        }
+        // varchar, int, list<varchar>
+        let columns: [(&str, &[u8]); 3] = [
+            ("name", &[0x00, 0x0D]),
+            ("age", &[0x00, 0x09]),
+            ("tags", &[0x00, 0x20, 0x00, 0x0D]),
+        ];
+        for (name, option) in columns.iter() {
+            body.extend(&(name.len() as u16).to_be_bytes());
+            body.extend(name.as_bytes());
+            body.extend(*option);
+        }
+    }
+    // rows_count
+    body.extend(&2i32.to_be_bytes());
+    for (name, age) in [("alice", Some(42i32)), ("bob", None)].iter() {
+        body.extend(&(name.len() as i32).to_be_bytes());
+        body.extend(name.as_bytes());
+        match age {
+            Some(age) => {
+                body.extend(&4i32.to_be_bytes());
+                body.extend(&age.to_be_bytes());
+            }
+            None => body.extend(&(-1i32).to_be_bytes()),
+        }
+        let mut tags: Vec<u8> = 1i32.to_be_bytes().to_vec();
+        tags.extend(&4i32.to_be_bytes());
+        tags.extend(b"user");
+        body.extend(&(tags.len() as i32).to_be_bytes());
+        body.extend(tags);
+    }
+    let mut frame = vec![0x84, 0, 0, 0, 0x08];
+    frame.extend(&(body.len() as i32).to_be_bytes());
+    frame.extend(body);
+    Decoder::new(frame, UNCOMPRESSED).unwrap()
+}
+
+#[test]
+fn derive_row_positional() {
+    let tags = vec!["user".to_string()];
+    let people: Vec<Person> = Person::rows_iter(rows_decoder(false)).unwrap().collect();
+    assert_eq!(
+        people,
+        vec![
+            Person {
+                name: "alice".to_string(),
+                age: Some(42),
+                cached: Vec::new(),
+                tags: tags.clone(),
+            },
+            Person {
+                name: "bob".to_string(),
+                age: None,
+                cached: Vec::new(),
+                tags: tags.clone(),
+            },
+        ]
+    );
+    let people: Vec<PersonTuple> = PersonTuple::rows_iter(rows_decoder(true)).unwrap().collect();
+    assert_eq!(people[1], PersonTuple("bob".to_string(), None, tags));
+}
+
+#[test]
+fn derive_row_by_name() {
+    let people: Vec<NamedPerson> = NamedPerson::rows_iter(rows_decoder(false)).unwrap().collect();
+    assert_eq!(people.len(), 2);
+    assert_eq!(people[0].years, Some(42));
+    // the column names are checked against the result metadata
+    let mut rows = Iter::<WrongPerson>::new(rows_decoder(false)).unwrap();
+    let error = WrongPerson::try_decode_row(&mut rows).unwrap_err();
+    assert!(error.to_string().contains("Expected column tags, got column age"));
+    // which can't be checked if it's omitted
+    let mut rows = Iter::<NamedPerson>::new(rows_decoder(true)).unwrap();
+    assert_eq!(NamedPerson::try_decode_row(&mut rows).unwrap().name, "alice");
+}
+
+#[test]
+fn decode_the_first_row() {
+    let person: Option<NamedPerson> = decode_first_row(rows_decoder(false)).unwrap();
+    assert_eq!(person.unwrap().name, "alice");
+    // a non rows result is an error
+    let mut frame = vec![0x84, 0, 0, 0, 0x08, 0, 0, 0, 4];
+    frame.extend(&1i32.to_be_bytes());
+    let void = Decoder::new(frame, UNCOMPRESSED).unwrap();
+    let error = decode_first_row::<NamedPerson>(void).unwrap_err();
+    assert_eq!(error.to_string(), "Expected a rows result, got a void result");
+}
@@ -0,0 +1,124 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cql::{Cql, TlsConfig};
+use std::{convert::TryInto, net::SocketAddr, sync::Arc};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tokio_rustls::{
+    rustls::{Certificate, NoClientAuth, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
+
+/// Spawn a local TLS server which completes the cql handshake and then echoes every frame back.
+async fn spawn_tls_echo_server(cert: &rcgen::Certificate) -> SocketAddr {
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(
+            vec![Certificate(cert.serialize_der().unwrap())],
+            PrivateKey(cert.serialize_private_key_der()),
+        )
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let mut stream = acceptor.accept(stream).await?;
+                // OPTIONS -> SUPPORTED
+                read_frame(&mut stream).await?;
+                stream.write_all(&supported_frame()).await?;
+                // STARTUP -> READY
+                read_frame(&mut stream).await?;
+                stream.write_all(&[4 | 0x80, 0, 0, 0, 0x02, 0, 0, 0, 0]).await?;
+                loop {
+                    let frame = read_frame(&mut stream).await?;
+                    stream.write_all(&frame).await?;
+                }
+                #[allow(unreachable_code)]
+                anyhow::Result::<()>::Ok(())
+            });
+        }
+    });
+    address
+}
+
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = vec![0; 9];
+    stream.read_exact(&mut buffer).await?;
+    let body_length = i32::from_be_bytes(buffer[5..9].try_into()?);
+    buffer.resize(body_length as usize + 9, 0);
+    stream.read_exact(&mut buffer[9..]).await?;
+    Ok(buffer)
+}
+
+fn supported_frame() -> Vec<u8> {
+    let options = [
+        ("CQL_VERSION", "3.3.1"),
+        ("SCYLLA_SHARD", "0"),
+        ("SCYLLA_NR_SHARDS", "1"),
+        ("SCYLLA_SHARDING_IGNORE_MSB", "12"),
+        ("SCYLLA_SHARD_AWARE_PORT", "19042"),
+    ];
+    let mut body: Vec<u8> = Vec::new();
+    body.extend(&(options.len() as u16).to_be_bytes());
+    for (key, value) in options.iter() {
+        body.extend(&(key.len() as u16).to_be_bytes());
+        body.extend(key.as_bytes());
+        body.extend(&1u16.to_be_bytes());
+        body.extend(&(value.len() as u16).to_be_bytes());
+        body.extend(value.as_bytes());
+    }
+    let mut frame = vec![4 | 0x80, 0, 0, 0, 0x06];
+    frame.extend(&(body.len() as i32).to_be_bytes());
+    frame.extend(body);
+    frame
+}
+
+#[tokio::test]
+async fn establish_tls_connection_and_echo() {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let address = spawn_tls_echo_server(&cert).await;
+    let tls = TlsConfig::builder()
+        .ca_pem(cert.serialize_pem().unwrap().as_bytes())
+        .sni("localhost")
+        .build()
+        .unwrap();
+    let mut cql = Cql::new().address(address).tls(Some(tls)).build().await.unwrap();
+    assert!(cql.stream().is_tls());
+    let frame = vec![4, 0, 0, 1, 0x05, 0, 0, 0, 2, 1, 2];
+    cql.stream().write_all(&frame).await.unwrap();
+    assert_eq!(read_frame(cql.stream()).await.unwrap(), frame);
+}
+
+#[tokio::test]
+async fn tls_hostname_verification() {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let address = spawn_tls_echo_server(&cert).await;
+    let ca = cert.serialize_pem().unwrap();
+    let tls = TlsConfig::builder()
+        .ca_pem(ca.as_bytes())
+        .sni("scylla.local")
+        .build()
+        .unwrap();
+    assert!(Cql::new().address(address).tls(Some(tls)).build().await.is_err());
+    let tls = TlsConfig::builder()
+        .ca_pem(ca.as_bytes())
+        .sni("scylla.local")
+        .verify_hostname(false)
+        .build()
+        .unwrap();
+    assert!(Cql::new().address(address).tls(Some(tls)).build().await.is_ok());
+    // the certificate chain is still verified
+    let other = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let tls = TlsConfig::builder()
+        .ca_pem(other.serialize_pem().unwrap().as_bytes())
+        .verify_hostname(false)
+        .build()
+        .unwrap();
+    assert!(Cql::new().address(address).tls(Some(tls)).build().await.is_err());
+}
@@ -0,0 +1,80 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cql::{ColumnDecoder, ColumnEncoder, Consistency, Query, Statements, Values};
+
+#[derive(ColumnEncoder, ColumnDecoder, Debug, PartialEq)]
+struct Address {
+    street: String,
+    #[cql(rename = "zip_code")]
+    zip: i32,
+    tags: Vec<String>,
+}
+
+#[derive(ColumnEncoder, ColumnDecoder, Debug, PartialEq)]
+struct Person {
+    name: String,
+    address: Address,
+    nickname: Option<String>,
+}
+
+fn person() -> Person {
+    Person {
+        name: "alice".to_string(),
+        address: Address {
+            street: "main street".to_string(),
+            zip: 1234,
+            tags: vec!["home".to_string()],
+        },
+        nickname: Some("al".to_string()),
+    }
+}
+
+#[test]
+fn udt_round_trip() {
+    let mut buffer = Vec::new();
+    person().encode(&mut buffer);
+    let byte_size = i32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+    assert_eq!(byte_size as usize, buffer.len() - 4);
+    assert_eq!(Person::try_decode(&buffer[4..]).unwrap(), person());
+    // a null field
+    let person = Person {
+        nickname: None,
+        ..person()
+    };
+    buffer.clear();
+    person.encode(&mut buffer);
+    assert_eq!(Person::try_decode(&buffer[4..]).unwrap(), person);
+}
+
+#[test]
+fn udt_missing_trailing_fields() {
+    // the nickname field was added to the type after the value was written
+    let mut buffer = Vec::new();
+    "alice".to_string().encode(&mut buffer);
+    person().address.encode(&mut buffer);
+    let person = Person::try_decode(&buffer).unwrap();
+    assert_eq!(person.nickname, None);
+    assert_eq!(person.address.zip, 1234);
+    // the error names the (renamed) field
+    let mut buffer = Vec::new();
+    "main street".to_string().encode(&mut buffer);
+    buffer.extend(&2i32.to_be_bytes());
+    buffer.extend(&[0, 1]);
+    let error = Address::try_decode(&buffer).unwrap_err();
+    assert!(error.to_string().contains("zip_code"));
+}
+
+#[test]
+fn udt_query_value() {
+    let query = Query::new()
+        .statement("INSERT INTO ks.people (id, person) VALUES (?, ?)")
+        .consistency(Consistency::One)
+        .value(&1i32)
+        .value(&person())
+        .build()
+        .unwrap();
+    let mut value = Vec::new();
+    person().encode(&mut value);
+    assert!(query.0.windows(value.len()).any(|window| window == value.as_slice()));
+}
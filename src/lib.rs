@@ -1,19 +1,25 @@
 // Copyright 2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
+
+// allows the derive macros to refer to `::scylla_rs` within this crate
+extern crate self as scylla_rs;
+
 pub mod cql;
 #[cfg(not(feature = "app"))]
 pub use cql::*;
 #[cfg(feature = "app")]
 pub mod app;
+#[doc(hidden)]
+pub use anyhow;
 
 #[cfg(feature = "app")]
 pub mod prelude {
     pub use super::{
         app::{access::*, worker::*, *},
         cql::{
-            Batch, ColumnDecoder, ColumnEncoder, ColumnValue, Consistency, Decoder, Frame, Iter, Prepare,
-            PreparedStatement, Query, QueryStatement, Row, Rows, RowsDecoder, Statements, TokenEncoder, Values,
-            VoidDecoder,
+            decode_first_row, Batch, ColumnDecoder, ColumnEncoder, ColumnValue, Consistency, Decoder, Frame, Iter,
+            Prepare, PreparedStatement, Query, QueryStatement, Row, Rows, RowsDecoder, Statements, TokenEncoder,
+            Values, VoidDecoder,
         },
     };
     pub use backstage::*;